@@ -89,7 +89,8 @@ impl Perform for BanFromCommunity {
     ModBanFromCommunity::create(context.pool(), &form).await?;
 
     let person_id = data.person_id;
-    let person_view = PersonView::read(context.pool(), person_id).await?;
+    let person_view =
+      PersonView::read(context.pool(), person_id, Some(local_user_view.person.id)).await?;
 
     Ok(BanFromCommunityResponse {
       person_view,
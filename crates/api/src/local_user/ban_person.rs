@@ -71,7 +71,8 @@ impl Perform for BanPerson {
     ModBan::create(context.pool(), &form).await?;
 
     let person_id = data.person_id;
-    let person_view = PersonView::read(context.pool(), person_id).await?;
+    let person_view =
+      PersonView::read(context.pool(), person_id, Some(local_user_view.person.id)).await?;
 
     Ok(BanPersonResponse {
       person_view,
@@ -34,7 +34,8 @@ impl Perform for BlockPerson {
       target_id,
     };
 
-    let target_person_view = PersonView::read(context.pool(), target_id).await?;
+    let mut target_person_view =
+      PersonView::read(context.pool(), target_id, Some(person_id)).await?;
 
     if target_person_view.person.admin {
       return Err(LemmyError::from_message("cant_block_admin"));
@@ -49,6 +50,7 @@ impl Perform for BlockPerson {
         .await
         .map_err(|e| LemmyError::from_error_message(e, "person_block_already_exists"))?;
     }
+    target_person_view.is_blocked_by_viewer = data.block;
 
     Ok(BlockPersonResponse {
       person_view: target_person_view,
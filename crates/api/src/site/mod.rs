@@ -2,4 +2,5 @@ mod federated_instances;
 mod leave_admin;
 mod mod_log;
 mod purge;
+mod recalculate_aggregates;
 mod registration_applications;
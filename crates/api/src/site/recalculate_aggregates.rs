@@ -0,0 +1,27 @@
+use crate::Perform;
+use actix_web::web::Data;
+use lemmy_api_common::{
+  context::LemmyContext,
+  site::{RecalculateSiteAggregates, RecalculateSiteAggregatesResponse},
+  utils::{is_admin, local_user_view_from_jwt},
+};
+use lemmy_db_schema::aggregates::structs::SiteAggregates;
+use lemmy_utils::error::LemmyError;
+
+#[async_trait::async_trait(?Send)]
+impl Perform for RecalculateSiteAggregates {
+  type Response = RecalculateSiteAggregatesResponse;
+
+  #[tracing::instrument(skip(context))]
+  async fn perform(&self, context: &Data<LemmyContext>) -> Result<Self::Response, LemmyError> {
+    let data: &Self = self;
+    let local_user_view = local_user_view_from_jwt(&data.auth, context).await?;
+
+    // Only let admins recount the site statistics
+    is_admin(&local_user_view)?;
+
+    let counts = SiteAggregates::recalculate_all(context.pool()).await?;
+
+    Ok(RecalculateSiteAggregatesResponse { counts })
+  }
+}
@@ -1,5 +1,6 @@
 use crate::sensitive::Sensitive;
 use lemmy_db_schema::{
+  aggregates::structs::SiteAggregates,
   newtypes::{CommentId, CommunityId, LanguageId, PersonId, PostId},
   source::{instance::Instance, language::Language, tagline::Tagline},
   ListingType,
@@ -403,6 +404,22 @@ pub struct PurgeItemResponse {
   pub success: bool,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "full", derive(TS))]
+#[cfg_attr(feature = "full", ts(export))]
+/// Recounts the site statistics, in case they drifted from the actual content.
+pub struct RecalculateSiteAggregates {
+  pub auth: Sensitive<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "full", derive(TS))]
+#[cfg_attr(feature = "full", ts(export))]
+/// The recounted site statistics.
+pub struct RecalculateSiteAggregatesResponse {
+  pub counts: SiteAggregates,
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[cfg_attr(feature = "full", derive(TS))]
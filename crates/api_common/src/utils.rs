@@ -672,6 +672,7 @@ pub async fn remove_user_data_in_community(
     .pool(pool)
     .creator_id(Some(banned_person_id))
     .community_id(Some(community_id))
+    .hide_globally_banned(Some(false))
     .limit(Some(i64::MAX))
     .build()
     .list()
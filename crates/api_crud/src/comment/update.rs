@@ -12,7 +12,6 @@ use lemmy_db_schema::{
     comment::{Comment, CommentUpdateForm},
     local_site::LocalSite,
   },
-  traits::Crud,
   utils::naive_now,
 };
 use lemmy_db_views::structs::CommentView;
@@ -72,7 +71,8 @@ impl PerformCrud for EditComment {
       .language_id(data.language_id)
       .updated(Some(Some(naive_now())))
       .build();
-    let updated_comment = Comment::update(context.pool(), comment_id, &form)
+    // Keeps the previous content in the edit history
+    let updated_comment = Comment::update_with_history(context.pool(), comment_id, &form)
       .await
       .map_err(|e| LemmyError::from_error_message(e, "couldnt_update_comment"))?;
 
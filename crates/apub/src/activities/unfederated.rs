@@ -109,6 +109,8 @@ use lemmy_api_common::{
     PurgeItemResponse,
     PurgePerson,
     PurgePost,
+    RecalculateSiteAggregates,
+    RecalculateSiteAggregatesResponse,
     RegistrationApplicationResponse,
     ResolveObject,
     ResolveObjectResponse,
@@ -286,6 +288,10 @@ impl SendActivity for PurgeComment {
   type Response = PurgeItemResponse;
 }
 
+impl SendActivity for RecalculateSiteAggregates {
+  type Response = RecalculateSiteAggregatesResponse;
+}
+
 impl SendActivity for Search {
   type Response = SearchResponse;
 }
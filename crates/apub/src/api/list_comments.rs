@@ -7,7 +7,7 @@ use activitypub_federation::config::Data;
 use lemmy_api_common::{
   comment::{GetComments, GetCommentsResponse},
   context::LemmyContext,
-  utils::{check_private_instance, local_user_view_from_jwt_opt},
+  utils::{check_private_instance, is_admin, local_user_view_from_jwt_opt},
 };
 use lemmy_db_schema::{
   source::{comment::Comment, community::Community, local_site::LocalSite},
@@ -25,6 +25,7 @@ impl PerformApub for GetComments {
     let data: &GetComments = self;
     let local_user_view = local_user_view_from_jwt_opt(data.auth.as_ref(), context).await;
     let local_site = LocalSite::read(context.pool()).await?;
+    let is_admin = local_user_view.as_ref().map(|luv| is_admin(luv).is_ok());
     check_private_instance(&local_user_view, &local_site)?;
 
     let community_id = if let Some(name) = &data.community_name {
@@ -64,6 +65,7 @@ impl PerformApub for GetComments {
       .parent_path(parent_path_cloned)
       .post_id(post_id)
       .local_user(local_user.as_ref())
+      .show_removed(is_admin)
       .page(page)
       .limit(limit)
       .build()
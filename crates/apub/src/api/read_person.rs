@@ -53,7 +53,8 @@ impl PerformApub for GetPersonDetails {
 
     // You don't need to return settings for the user, since this comes back with GetSite
     // `my_user`
-    let person_view = PersonView::read(context.pool(), person_details_id).await?;
+    let my_person_id = local_user_view.as_ref().map(|l| l.person.id);
+    let person_view = PersonView::read(context.pool(), person_details_id, my_person_id).await?;
 
     let sort = data.sort;
     let page = data.page;
@@ -68,6 +69,7 @@ impl PerformApub for GetPersonDetails {
       .sort(sort)
       .saved_only(saved_only)
       .local_user(local_user.as_ref())
+      .hide_blocked(Some(true))
       .community_id(community_id)
       .is_mod_or_admin(is_admin)
       .page(page)
@@ -91,6 +93,7 @@ impl PerformApub for GetPersonDetails {
       .sort(sort.map(post_to_comment_sort_type))
       .saved_only(saved_only)
       .show_deleted_and_removed(Some(false))
+      .hide_globally_banned(Some(false))
       .community_id(community_id)
       .page(page)
       .limit(limit);
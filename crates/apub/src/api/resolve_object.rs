@@ -48,7 +48,7 @@ async fn convert_response(
   match object {
     Person(p) => {
       removed_or_deleted = p.deleted;
-      res.person = Some(PersonView::read(pool, p.id).await?)
+      res.person = Some(PersonView::read(pool, p.id, Some(user_id)).await?)
     }
     Community(c) => {
       removed_or_deleted = c.deleted || c.removed;
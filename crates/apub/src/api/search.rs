@@ -65,6 +65,7 @@ impl PerformApub for Search {
           .community_id(community_id)
           .creator_id(creator_id)
           .local_user(local_user.as_ref())
+          .hide_blocked(Some(true))
           .search_term(Some(q))
           .is_mod_or_admin(is_admin)
           .page(page)
@@ -126,6 +127,7 @@ impl PerformApub for Search {
           .community_id(community_id)
           .creator_id(creator_id)
           .local_user(local_user_.as_ref())
+          .hide_blocked(Some(true))
           .search_term(Some(q))
           .is_mod_or_admin(is_admin)
           .page(page)
@@ -4,7 +4,7 @@ use crate::{
   schema::comment_aggregates,
   utils::{functions::hot_rank, get_conn, DbPool},
 };
-use diesel::{result::Error, ExpressionMethods, QueryDsl};
+use diesel::{result::Error, sql_query, sql_types::Integer, ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 
 impl CommentAggregates {
@@ -28,24 +28,70 @@ impl CommentAggregates {
       .get_result::<Self>(conn)
       .await
   }
+
+  /// Recounts the children of a comment, its ancestors and its descendants, in case the counts
+  /// maintained by the trigger drifted.
+  pub async fn recalculate(pool: &DbPool, comment_id: CommentId) -> Result<Self, Error> {
+    let conn = &mut get_conn(pool).await?;
+
+    sql_query(
+      "update comment_aggregates ca set child_count = (
+        select count(*) from comment c
+        where c.path <@ a.path
+        and c.id != a.id
+        and c.deleted = false
+        and c.removed = false
+      )
+      from comment a, comment target
+      where ca.comment_id = a.id
+      and target.id = $1
+      and (a.path @> target.path or a.path <@ target.path)",
+    )
+    .bind::<Integer, _>(comment_id)
+    .execute(conn)
+    .await?;
+
+    comment_aggregates::table
+      .filter(comment_aggregates::comment_id.eq(comment_id))
+      .first::<Self>(conn)
+      .await
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use crate::{
     aggregates::comment_aggregates::CommentAggregates,
+    newtypes::CommentId,
+    schema::comment_aggregates,
     source::{
-      comment::{Comment, CommentInsertForm, CommentLike, CommentLikeForm},
+      comment::{Comment, CommentInsertForm, CommentLike, CommentLikeForm, CommentUpdateForm},
       community::{Community, CommunityInsertForm},
       instance::Instance,
       person::{Person, PersonInsertForm},
       post::{Post, PostInsertForm},
     },
     traits::{Crud, Likeable},
-    utils::build_db_pool_for_tests,
+    utils::{build_db_pool_for_tests, functions::hot_rank, get_conn, DbPool},
   };
+  use diesel::{ExpressionMethods, QueryDsl};
+  use diesel_async::RunQueryDsl;
   use serial_test::serial;
 
+  /// The hot rank as computed by the sql function, rather than the cached column
+  async fn live_hot_rank(pool: &DbPool, comment_id: CommentId) -> i32 {
+    let conn = &mut get_conn(pool).await.unwrap();
+    comment_aggregates::table
+      .filter(comment_aggregates::comment_id.eq(comment_id))
+      .select(hot_rank(
+        comment_aggregates::score,
+        comment_aggregates::published,
+      ))
+      .first::<i32>(conn)
+      .await
+      .unwrap()
+  }
+
   #[tokio::test]
   #[serial]
   async fn test_crud() {
@@ -123,6 +169,10 @@ mod tests {
     assert_eq!(1, comment_aggs_before_delete.score);
     assert_eq!(1, comment_aggs_before_delete.upvotes);
     assert_eq!(0, comment_aggs_before_delete.downvotes);
+    assert_eq!(
+      live_hot_rank(pool, inserted_comment.id).await,
+      comment_aggs_before_delete.hot_rank
+    );
 
     // Add a post dislike from the other person
     let comment_dislike = CommentLikeForm {
@@ -152,6 +202,10 @@ mod tests {
     assert_eq!(-1, after_like_remove.score);
     assert_eq!(0, after_like_remove.upvotes);
     assert_eq!(1, after_like_remove.downvotes);
+    assert_eq!(
+      live_hot_rank(pool, inserted_comment.id).await,
+      after_like_remove.hot_rank
+    );
 
     // Remove the parent post
     Post::delete(pool, inserted_post.id).await.unwrap();
@@ -175,4 +229,94 @@ mod tests {
 
     Instance::delete(pool, inserted_instance.id).await.unwrap();
   }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_child_count() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("thommy_child_count".into())
+      .public_key("pubkey".into())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("TIL_child_count".into())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let new_post = PostInsertForm::builder()
+      .name("A test post".into())
+      .creator_id(inserted_person.id)
+      .community_id(inserted_community.id)
+      .build();
+    let inserted_post = Post::create(pool, &new_post).await.unwrap();
+
+    let comment_form = CommentInsertForm::builder()
+      .content("A test comment".into())
+      .creator_id(inserted_person.id)
+      .post_id(inserted_post.id)
+      .build();
+
+    // A chain of four comments: root -> middle -> leaf_1 -> leaf_2
+    let root = Comment::create(pool, &comment_form, None).await.unwrap();
+    let middle = Comment::create(pool, &comment_form, Some(&root.path))
+      .await
+      .unwrap();
+    let leaf_1 = Comment::create(pool, &comment_form, Some(&middle.path))
+      .await
+      .unwrap();
+    let leaf_2 = Comment::create(pool, &comment_form, Some(&leaf_1.path))
+      .await
+      .unwrap();
+
+    let child_count = |comment_id| async move {
+      CommentAggregates::read(pool, comment_id)
+        .await
+        .unwrap()
+        .child_count
+    };
+    assert_eq!(3, child_count(root.id).await);
+    assert_eq!(2, child_count(middle.id).await);
+    assert_eq!(1, child_count(leaf_1.id).await);
+
+    // Deleting the middle comment only updates its ancestors
+    Comment::update(
+      pool,
+      middle.id,
+      &CommentUpdateForm::builder().deleted(Some(true)).build(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(2, child_count(root.id).await);
+    assert_eq!(2, child_count(middle.id).await);
+
+    // Purging a leaf updates all of the ancestors
+    Comment::delete(pool, leaf_2.id).await.unwrap();
+    assert_eq!(1, child_count(root.id).await);
+    assert_eq!(1, child_count(middle.id).await);
+    assert_eq!(0, child_count(leaf_1.id).await);
+
+    let recalculated = CommentAggregates::recalculate(pool, middle.id)
+      .await
+      .unwrap();
+    assert_eq!(1, recalculated.child_count);
+    assert_eq!(1, child_count(root.id).await);
+
+    Post::delete(pool, inserted_post.id).await.unwrap();
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
 }
@@ -4,7 +4,7 @@ use crate::{
   schema::person_aggregates,
   utils::{get_conn, DbPool},
 };
-use diesel::{result::Error, ExpressionMethods, QueryDsl};
+use diesel::{result::Error, sql_query, sql_types::Integer, ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 
 impl PersonAggregates {
@@ -15,22 +15,60 @@ impl PersonAggregates {
       .first::<Self>(conn)
       .await
   }
+
+  /// Recounts the posts, comments and read posts of a person, in case the counts maintained by
+  /// the triggers drifted. Deleted and removed content is not counted.
+  pub async fn recalculate(pool: &DbPool, person_id: PersonId) -> Result<Self, Error> {
+    let conn = &mut get_conn(pool).await?;
+
+    sql_query(
+      "update person_aggregates ua set
+        post_count = (
+          select count(*) from post p
+          where p.creator_id = ua.person_id and p.deleted = false and p.removed = false
+        ),
+        comment_count = (
+          select count(*) from comment c
+          where c.creator_id = ua.person_id and c.deleted = false and c.removed = false
+        ),
+        post_read_count = (
+          select count(*) from post_read pr where pr.person_id = ua.person_id
+        )
+      where ua.person_id = $1",
+    )
+    .bind::<Integer, _>(person_id)
+    .execute(conn)
+    .await?;
+
+    Self::read(pool, person_id).await
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use crate::{
     aggregates::person_aggregates::PersonAggregates,
+    schema::person_aggregates,
     source::{
-      comment::{Comment, CommentInsertForm, CommentLike, CommentLikeForm},
+      comment::{Comment, CommentInsertForm, CommentLike, CommentLikeForm, CommentUpdateForm},
       community::{Community, CommunityInsertForm},
       instance::Instance,
       person::{Person, PersonInsertForm},
-      post::{Post, PostInsertForm, PostLike, PostLikeForm},
+      post::{
+        Post,
+        PostInsertForm,
+        PostLike,
+        PostLikeForm,
+        PostRead,
+        PostReadForm,
+        PostUpdateForm,
+      },
     },
-    traits::{Crud, Likeable},
-    utils::build_db_pool_for_tests,
+    traits::{Crud, Likeable, Readable},
+    utils::{build_db_pool_for_tests, get_conn},
   };
+  use diesel::ExpressionMethods;
+  use diesel_async::RunQueryDsl;
   use serial_test::serial;
 
   #[tokio::test]
@@ -191,4 +229,208 @@ mod tests {
 
     Instance::delete(pool, inserted_instance.id).await.unwrap();
   }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_soft_delete() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("thommy_user_agg_del".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("TIL_user_agg_del".into())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let new_post = PostInsertForm::builder()
+      .name("A test post".into())
+      .creator_id(inserted_person.id)
+      .community_id(inserted_community.id)
+      .build();
+
+    let inserted_post = Post::create(pool, &new_post).await.unwrap();
+
+    let comment_form = CommentInsertForm::builder()
+      .content("A test comment".into())
+      .creator_id(inserted_person.id)
+      .post_id(inserted_post.id)
+      .build();
+
+    let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let post_like = PostLikeForm {
+      post_id: inserted_post.id,
+      person_id: inserted_person.id,
+      score: 1,
+    };
+    PostLike::like(pool, &post_like).await.unwrap();
+    let comment_like = CommentLikeForm {
+      comment_id: inserted_comment.id,
+      person_id: inserted_person.id,
+      post_id: inserted_post.id,
+      score: 1,
+    };
+    CommentLike::like(pool, &comment_like).await.unwrap();
+
+    let after_create = PersonAggregates::read(pool, inserted_person.id)
+      .await
+      .unwrap();
+    assert_eq!(1, after_create.post_count);
+    assert_eq!(1, after_create.post_score);
+    assert_eq!(1, after_create.comment_count);
+    assert_eq!(1, after_create.comment_score);
+
+    // Soft delete the comment and the post
+    let comment_delete_form = CommentUpdateForm::builder().deleted(Some(true)).build();
+    Comment::update(pool, inserted_comment.id, &comment_delete_form)
+      .await
+      .unwrap();
+    let post_delete_form = PostUpdateForm::builder().deleted(Some(true)).build();
+    Post::update(pool, inserted_post.id, &post_delete_form)
+      .await
+      .unwrap();
+    let after_soft_delete = PersonAggregates::read(pool, inserted_person.id)
+      .await
+      .unwrap();
+    assert_eq!(0, after_soft_delete.post_count);
+    assert_eq!(0, after_soft_delete.post_score);
+    assert_eq!(0, after_soft_delete.comment_count);
+    assert_eq!(0, after_soft_delete.comment_score);
+
+    // Restoring brings back the count and the score
+    let comment_restore_form = CommentUpdateForm::builder().deleted(Some(false)).build();
+    Comment::update(pool, inserted_comment.id, &comment_restore_form)
+      .await
+      .unwrap();
+    let after_restore = PersonAggregates::read(pool, inserted_person.id)
+      .await
+      .unwrap();
+    assert_eq!(1, after_restore.comment_count);
+    assert_eq!(1, after_restore.comment_score);
+
+    // Removing deleted content shouldn't decrement the counts again
+    let post_remove_form = PostUpdateForm::builder().removed(Some(true)).build();
+    Post::update(pool, inserted_post.id, &post_remove_form)
+      .await
+      .unwrap();
+    let after_remove = PersonAggregates::read(pool, inserted_person.id)
+      .await
+      .unwrap();
+    assert_eq!(0, after_remove.post_count);
+    assert_eq!(0, after_remove.post_score);
+
+    // Neither should purging it, while the restored comment is purged along with it
+    Post::delete(pool, inserted_post.id).await.unwrap();
+    let after_hard_delete = PersonAggregates::read(pool, inserted_person.id)
+      .await
+      .unwrap();
+    assert_eq!(0, after_hard_delete.post_count);
+    assert_eq!(0, after_hard_delete.post_score);
+    assert_eq!(0, after_hard_delete.comment_count);
+    assert_eq!(0, after_hard_delete.comment_score);
+
+    // Recalculating fixes drifted counts
+    diesel::update(person_aggregates::table)
+      .filter(person_aggregates::person_id.eq(inserted_person.id))
+      .set(person_aggregates::post_count.eq(5))
+      .execute(&mut get_conn(pool).await.unwrap())
+      .await
+      .unwrap();
+    let recalculated = PersonAggregates::recalculate(pool, inserted_person.id)
+      .await
+      .unwrap();
+    assert_eq!(0, recalculated.post_count);
+    assert_eq!(0, recalculated.comment_count);
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_post_read_count() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("thommy_user_agg_read".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("TIL_user_agg_read".into())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let mut read_forms = vec![];
+    for i in 0..4 {
+      let post_form = PostInsertForm::builder()
+        .name(format!("A test post {i}"))
+        .creator_id(inserted_person.id)
+        .community_id(inserted_community.id)
+        .build();
+      let inserted_post = Post::create(pool, &post_form).await.unwrap();
+      read_forms.push(PostReadForm {
+        post_id: inserted_post.id,
+        person_id: inserted_person.id,
+      });
+    }
+    let post_read_count = || async {
+      PersonAggregates::read(pool, inserted_person.id)
+        .await
+        .unwrap()
+        .post_read_count
+    };
+
+    for form in &read_forms[..3] {
+      PostRead::mark_as_read(pool, form).await.unwrap();
+    }
+    assert_eq!(3, post_read_count().await);
+
+    PostRead::mark_as_read(pool, &read_forms[3]).await.unwrap();
+    // Reading a post twice doesn't count
+    PostRead::mark_as_read(pool, &read_forms[3]).await.unwrap();
+    assert_eq!(4, post_read_count().await);
+
+    PostRead::mark_as_unread(pool, &read_forms[0])
+      .await
+      .unwrap();
+    assert_eq!(3, post_read_count().await);
+
+    let recalculated = PersonAggregates::recalculate(pool, inserted_person.id)
+      .await
+      .unwrap();
+    assert_eq!(3, recalculated.post_read_count);
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
 }
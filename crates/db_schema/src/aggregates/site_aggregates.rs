@@ -3,7 +3,7 @@ use crate::{
   schema::site_aggregates,
   utils::{get_conn, DbPool},
 };
-use diesel::result::Error;
+use diesel::{result::Error, sql_query};
 use diesel_async::RunQueryDsl;
 
 impl SiteAggregates {
@@ -11,12 +11,40 @@ impl SiteAggregates {
     let conn = &mut get_conn(pool).await?;
     site_aggregates::table.first::<Self>(conn).await
   }
+
+  /// Recounts the local users, posts, comments and communities, in case the counts maintained by
+  /// the triggers drifted.
+  pub async fn recalculate_all(pool: &DbPool) -> Result<Self, Error> {
+    let conn = &mut get_conn(pool).await?;
+
+    sql_query(
+      "update site_aggregates set
+        users = (select count(*) from person where local = true),
+        posts = (
+          select count(*) from post
+          where local = true and deleted = false and removed = false
+        ),
+        comments = (
+          select count(*) from comment
+          where local = true and deleted = false and removed = false
+        ),
+        communities = (
+          select count(*) from community
+          where local = true and deleted = false and removed = false
+        )",
+    )
+    .execute(conn)
+    .await?;
+
+    site_aggregates::table.first::<Self>(conn).await
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use crate::{
     aggregates::site_aggregates::SiteAggregates,
+    schema::{person, site_aggregates},
     source::{
       comment::{Comment, CommentInsertForm},
       community::{Community, CommunityInsertForm},
@@ -26,8 +54,10 @@ mod tests {
       site::{Site, SiteInsertForm},
     },
     traits::Crud,
-    utils::build_db_pool_for_tests,
+    utils::{build_db_pool_for_tests, get_conn},
   };
+  use diesel::{dsl::count_star, ExpressionMethods, QueryDsl};
+  use diesel_async::RunQueryDsl;
   use serial_test::serial;
 
   #[tokio::test]
@@ -127,4 +157,49 @@ mod tests {
 
     Instance::delete(pool, inserted_instance.id).await.unwrap();
   }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_recalculate_all() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("thommy_site_agg_recalc".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let site_form = SiteInsertForm::builder()
+      .name("test_site".into())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_site = Site::create(pool, &site_form).await.unwrap();
+
+    // Corrupt the user count
+    let conn = &mut get_conn(pool).await.unwrap();
+    diesel::update(site_aggregates::table)
+      .set(site_aggregates::users.eq(999))
+      .execute(conn)
+      .await
+      .unwrap();
+    assert_eq!(999, SiteAggregates::read(pool).await.unwrap().users);
+
+    let local_users = person::table
+      .filter(person::local.eq(true))
+      .select(count_star())
+      .first::<i64>(conn)
+      .await
+      .unwrap();
+    let recalculated = SiteAggregates::recalculate_all(pool).await.unwrap();
+    assert_eq!(local_users, recalculated.users);
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Site::delete(pool, inserted_site.id).await.unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
 }
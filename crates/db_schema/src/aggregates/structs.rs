@@ -28,6 +28,8 @@ pub struct CommentAggregates {
   /// The total number of children in this comment branch.
   pub child_count: i32,
   pub hot_rank: i32,
+  /// How often the content of the comment was edited.
+  pub edit_count: i64,
 }
 
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
@@ -70,6 +72,8 @@ pub struct PersonAggregates {
   pub post_score: i64,
   pub comment_count: i64,
   pub comment_score: i64,
+  /// The number of posts the person has read.
+  pub post_read_count: i64,
 }
 
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
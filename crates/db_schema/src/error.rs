@@ -0,0 +1,119 @@
+use diesel::result::{DatabaseErrorKind, Error};
+use std::fmt::{Display, Formatter};
+
+/// A database error, with the cases that API handlers need to tell apart split out.
+#[derive(Debug)]
+pub enum LemmyDbError {
+  NotFound,
+  /// Contains the name of the violated constraint
+  UniqueConstraintViolation(String),
+  /// Contains the name of the violated constraint
+  ForeignKeyViolation(String),
+  Other(Error),
+}
+
+impl From<Error> for LemmyDbError {
+  fn from(error: Error) -> Self {
+    match error {
+      Error::NotFound => LemmyDbError::NotFound,
+      Error::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+        LemmyDbError::UniqueConstraintViolation(
+          info.constraint_name().unwrap_or_default().to_string(),
+        )
+      }
+      Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) => {
+        LemmyDbError::ForeignKeyViolation(info.constraint_name().unwrap_or_default().to_string())
+      }
+      _ => LemmyDbError::Other(error),
+    }
+  }
+}
+
+impl Display for LemmyDbError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      LemmyDbError::NotFound => write!(f, "Record not found"),
+      LemmyDbError::UniqueConstraintViolation(constraint) => {
+        write!(f, "Unique constraint violation: {constraint}")
+      }
+      LemmyDbError::ForeignKeyViolation(constraint) => {
+        write!(f, "Foreign key violation: {constraint}")
+      }
+      LemmyDbError::Other(error) => error.fmt(f),
+    }
+  }
+}
+
+impl std::error::Error for LemmyDbError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      LemmyDbError::Other(error) => Some(error),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    error::LemmyDbError,
+    newtypes::{CommentId, PersonId, PostId},
+    schema::instance,
+    source::comment::{Comment, CommentInsertForm},
+    traits::Crud,
+    utils::{build_db_pool_for_tests, get_conn},
+  };
+  use diesel::{insert_into, ExpressionMethods};
+  use diesel_async::RunQueryDsl;
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_error_variants() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let not_found = Comment::read(pool, CommentId(-1)).await.unwrap_err();
+    assert!(matches!(
+      LemmyDbError::from(not_found),
+      LemmyDbError::NotFound
+    ));
+
+    let comment_form = CommentInsertForm::builder()
+      .content("A comment on a missing post".into())
+      .creator_id(PersonId(-1))
+      .post_id(PostId(-1))
+      .build();
+    let foreign_key = Comment::create(pool, &comment_form, None)
+      .await
+      .unwrap_err();
+    assert!(matches!(
+      LemmyDbError::from(foreign_key),
+      LemmyDbError::ForeignKeyViolation(_)
+    ));
+
+    let conn = &mut get_conn(pool).await.unwrap();
+    let instance_form = instance::domain.eq("duplicate_domain.tld");
+    insert_into(instance::table)
+      .values(instance_form)
+      .execute(conn)
+      .await
+      .unwrap();
+    let unique = insert_into(instance::table)
+      .values(instance_form)
+      .execute(conn)
+      .await
+      .unwrap_err();
+    match LemmyDbError::from(unique) {
+      LemmyDbError::UniqueConstraintViolation(constraint) => {
+        assert_eq!("instance_domain_key", constraint)
+      }
+      e => panic!("Expected a unique constraint violation, got {e:?}"),
+    }
+
+    diesel::delete(instance::table)
+      .filter(instance::domain.eq("duplicate_domain.tld"))
+      .execute(conn)
+      .await
+      .unwrap();
+  }
+}
@@ -1,7 +1,7 @@
 use crate::{
   diesel::JoinOnDsl,
-  newtypes::{CommunityId, InstanceId, LanguageId, LocalUserId, SiteId},
-  schema::{local_site, site, site_language},
+  newtypes::{CommunityId, InstanceId, LanguageId, LocalUserId, PersonId, SiteId},
+  schema::{local_site, local_user, local_user_language, site, site_language},
   source::{
     actor_language::{
       CommunityLanguage,
@@ -20,8 +20,10 @@ use diesel::{
   delete,
   dsl::{count, exists},
   insert_into,
+  pg::Pg,
   result::Error,
   select,
+  sql_types,
   ExpressionMethods,
   QueryDsl,
 };
@@ -36,6 +38,23 @@ use tokio::sync::OnceCell;
 pub const UNDETERMINED_ID: LanguageId = LanguageId(0);
 
 impl LocalUserLanguage {
+  /// Subquery selecting the languages enabled by the local user of the given person, to be used
+  /// with `eq_any` on a language_id column. Matches nothing for remote persons.
+  pub fn enabled_for_person(
+    for_person_id: PersonId,
+  ) -> local_user_language::BoxedQuery<'static, Pg, sql_types::Integer> {
+    local_user_language::table
+      .select(local_user_language::language_id)
+      .filter(
+        local_user_language::local_user_id.eq_any(
+          local_user::table
+            .filter(local_user::person_id.eq(for_person_id))
+            .select(local_user::id),
+        ),
+      )
+      .into_boxed()
+  }
+
   pub async fn read(
     pool: &DbPool,
     for_local_user_id: LocalUserId,
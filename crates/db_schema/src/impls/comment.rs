@@ -1,8 +1,13 @@
 use crate::{
   newtypes::{CommentId, DbUrl, PersonId},
-  schema::comment::dsl::{ap_id, comment, content, creator_id, deleted, path, removed, updated},
+  schema::{
+    comment::dsl::{ap_id, comment, content, creator_id, deleted, id, path, removed, updated},
+    comment_edit_history,
+  },
   source::comment::{
     Comment,
+    CommentEditHistory,
+    CommentEditHistoryForm,
     CommentInsertForm,
     CommentLike,
     CommentLikeForm,
@@ -13,12 +18,7 @@ use crate::{
   traits::{Crud, Likeable, Saveable},
   utils::{get_conn, naive_now, DbPool, DELETED_REPLACEMENT_TEXT},
 };
-use diesel::{
-  dsl::{insert_into, sql_query},
-  result::Error,
-  ExpressionMethods,
-  QueryDsl,
-};
+use diesel::{dsl::insert_into, result::Error, ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 use diesel_ltree::Ltree;
 use url::Url;
@@ -52,6 +52,81 @@ impl Comment {
       .await
   }
 
+  /// Marks many comments as deleted at once, for cleaning up spam. Returns the number of newly
+  /// deleted comments.
+  pub async fn batch_delete(pool: &DbPool, comment_ids: &[CommentId]) -> Result<usize, Error> {
+    let conn = &mut get_conn(pool).await?;
+    diesel::update(
+      comment
+        .filter(id.eq_any(comment_ids))
+        .filter(deleted.eq(false)),
+    )
+    .set((deleted.eq(true), updated.eq(naive_now())))
+    .execute(conn)
+    .await
+  }
+
+  /// Deletes many comments from the database at once. Returns the number of purged comments.
+  pub async fn purge_batch(pool: &DbPool, comment_ids: &[CommentId]) -> Result<usize, Error> {
+    let conn = &mut get_conn(pool).await?;
+    diesel::delete(comment.filter(id.eq_any(comment_ids)))
+      .execute(conn)
+      .await
+  }
+
+  /// Updates a comment in one transaction. If the form changes the content, the previous content
+  /// is kept in the edit history.
+  pub async fn update_with_history(
+    pool: &DbPool,
+    comment_id: CommentId,
+    form: &CommentUpdateForm,
+  ) -> Result<Self, Error> {
+    let conn = &mut get_conn(pool).await?;
+
+    conn
+      .build_transaction()
+      .run(|conn| {
+        Box::pin(async move {
+          let old_content = comment
+            .find(comment_id)
+            .select(content)
+            .for_update()
+            .first::<String>(conn)
+            .await?;
+
+          if form.content.as_ref().is_some_and(|c| c != &old_content) {
+            let history_form = CommentEditHistoryForm {
+              comment_id,
+              content: old_content,
+            };
+            insert_into(comment_edit_history::table)
+              .values(history_form)
+              .execute(conn)
+              .await?;
+          }
+
+          diesel::update(comment.find(comment_id))
+            .set(form)
+            .get_result::<Self>(conn)
+            .await
+        }) as _
+      })
+      .await
+  }
+
+  /// The previous versions of a comment, oldest first.
+  pub async fn get_edit_history(
+    pool: &DbPool,
+    comment_id: CommentId,
+  ) -> Result<Vec<CommentEditHistory>, Error> {
+    let conn = &mut get_conn(pool).await?;
+    comment_edit_history::table
+      .filter(comment_edit_history::comment_id.eq(comment_id))
+      .order_by(comment_edit_history::id)
+      .load::<CommentEditHistory>(conn)
+      .await
+  }
+
   pub async fn create(
     pool: &DbPool,
     comment_form: &CommentInsertForm,
@@ -72,58 +147,66 @@ impl Comment {
       let comment_id = comment_insert.id;
 
       // You need to update the ltree column
-      let ltree = Ltree(if let Some(parent_path) = parent_path {
-        // The previous parent will already have 0 in it
-        // Append this comment id
-        format!("{}.{}", parent_path.0, comment_id)
-      } else {
-        // '0' is always the first path, append to that
-        format!("{}.{}", 0, comment_id)
-      });
+      let ltree = Self::child_path(parent_path, comment_id);
 
       let updated_comment = diesel::update(comment.find(comment_id))
         .set(path.eq(ltree))
         .get_result::<Self>(conn)
         .await;
 
-      // Update the child count for the parent comment_aggregates
-      // You could do this with a trigger, but since you have to do this manually anyway,
-      // you can just have it here
-      if let Some(parent_path) = parent_path {
-        // You have to update counts for all parents, not just the immediate one
-        // TODO if the performance of this is terrible, it might be better to do this as part of a
-        // scheduled query... although the counts would often be wrong.
-        //
-        // The child_count query for reference:
-        // select c.id, c.path, count(c2.id) as child_count from comment c
-        // left join comment c2 on c2.path <@ c.path and c2.path != c.path
-        // group by c.id
-
-        let path_split = parent_path.0.split('.').collect::<Vec<&str>>();
-        let parent_id = path_split.get(1);
-
-        if let Some(parent_id) = parent_id {
-          let top_parent = format!("0.{}", parent_id);
-          let update_child_count_stmt = format!(
-            "
-update comment_aggregates ca set child_count = c.child_count
-from (
-  select c.id, c.path, count(c2.id) as child_count from comment c
-  join comment c2 on c2.path <@ c.path and c2.path != c.path
-  and c.path <@ '{top_parent}'
-  group by c.id
-) as c
-where ca.comment_id = c.id"
-          );
-
-          sql_query(update_child_count_stmt).execute(conn).await?;
-        }
-      }
+      // The child counts of the parents are updated by a trigger on the path column
       updated_comment
     } else {
       inserted_comment
     }
   }
+
+  /// Creates a comment below the given parent, without having to read the parent first.
+  pub async fn create_with_parent_id(
+    pool: &DbPool,
+    comment_form: &CommentInsertForm,
+    parent_id: Option<CommentId>,
+  ) -> Result<Comment, Error> {
+    let parent_path = match parent_id {
+      Some(parent_id) => Some(Self::read_path(pool, parent_id).await?),
+      None => None,
+    };
+    Self::create(pool, comment_form, parent_path.as_ref()).await
+  }
+
+  /// Builds the path of a comment from the id of its parent.
+  pub async fn build_ltree_path(
+    pool: &DbPool,
+    parent_id: Option<CommentId>,
+    comment_id: CommentId,
+  ) -> Result<Ltree, Error> {
+    let parent_path = match parent_id {
+      Some(parent_id) => Some(Self::read_path(pool, parent_id).await?),
+      None => None,
+    };
+    Ok(Self::child_path(parent_path.as_ref(), comment_id))
+  }
+
+  async fn read_path(pool: &DbPool, comment_id: CommentId) -> Result<Ltree, Error> {
+    let conn = &mut get_conn(pool).await?;
+    comment
+      .find(comment_id)
+      .select(path)
+      .first::<Ltree>(conn)
+      .await
+  }
+
+  fn child_path(parent_path: Option<&Ltree>, comment_id: CommentId) -> Ltree {
+    Ltree(if let Some(parent_path) = parent_path {
+      // The previous parent will already have 0 in it
+      // Append this comment id
+      format!("{}.{}", parent_path.0, comment_id)
+    } else {
+      // '0' is always the first path, append to that
+      format!("{}.{}", 0, comment_id)
+    })
+  }
+
   pub async fn read_from_apub_id(pool: &DbPool, object_id: Url) -> Result<Option<Self>, Error> {
     let conn = &mut get_conn(pool).await?;
     let object_id: DbUrl = object_id.into();
@@ -244,7 +327,8 @@ impl Saveable for CommentSaved {
 #[cfg(test)]
 mod tests {
   use crate::{
-    newtypes::LanguageId,
+    aggregates::structs::{PersonAggregates, PostAggregates},
+    newtypes::{CommentId, LanguageId},
     source::{
       comment::{
         Comment,
@@ -261,7 +345,7 @@ mod tests {
       post::{Post, PostInsertForm},
     },
     traits::{Crud, Likeable, Saveable},
-    utils::build_db_pool_for_tests,
+    utils::{build_db_pool_for_tests, naive_now},
   };
   use diesel_ltree::Ltree;
   use serial_test::serial;
@@ -408,4 +492,223 @@ mod tests {
     assert_eq!(1, saved_removed);
     assert_eq!(1, num_deleted);
   }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_create_with_parent_id() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("terry_chain".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("test community chain".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let new_post = PostInsertForm::builder()
+      .name("A test post".into())
+      .creator_id(inserted_person.id)
+      .community_id(inserted_community.id)
+      .build();
+
+    let inserted_post = Post::create(pool, &new_post).await.unwrap();
+
+    let comment_form = CommentInsertForm::builder()
+      .content("A test comment".into())
+      .creator_id(inserted_person.id)
+      .post_id(inserted_post.id)
+      .build();
+
+    // A chain of five comments, each replying to the previous one
+    let mut expected_path = "0".to_string();
+    let mut parent_id = None;
+    for _ in 0..5 {
+      let inserted_comment = Comment::create_with_parent_id(pool, &comment_form, parent_id)
+        .await
+        .unwrap();
+      expected_path = format!("{}.{}", expected_path, inserted_comment.id);
+      assert_eq!(expected_path, inserted_comment.path.0);
+      assert_eq!(
+        inserted_comment.path,
+        Comment::build_ltree_path(pool, parent_id, inserted_comment.id)
+          .await
+          .unwrap()
+      );
+      parent_id = Some(inserted_comment.id);
+    }
+
+    // A missing parent is an error
+    let missing_parent =
+      Comment::create_with_parent_id(pool, &comment_form, Some(CommentId(-1))).await;
+    assert!(missing_parent.is_err());
+
+    Post::delete(pool, inserted_post.id).await.unwrap();
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Person::delete(pool, inserted_person.id).await.unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_batch_delete() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("terry_batch".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("test community batch".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let new_post = PostInsertForm::builder()
+      .name("A test post".into())
+      .creator_id(inserted_person.id)
+      .community_id(inserted_community.id)
+      .build();
+    let inserted_post = Post::create(pool, &new_post).await.unwrap();
+
+    let comment_form = CommentInsertForm::builder()
+      .content("Spam".into())
+      .creator_id(inserted_person.id)
+      .post_id(inserted_post.id)
+      .build();
+    let mut comment_ids = vec![];
+    for _ in 0..10 {
+      let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+      comment_ids.push(inserted_comment.id);
+    }
+
+    let num_deleted = Comment::batch_delete(pool, &comment_ids[..7])
+      .await
+      .unwrap();
+    assert_eq!(7, num_deleted);
+    assert!(Comment::read(pool, comment_ids[0]).await.unwrap().deleted);
+    assert!(!Comment::read(pool, comment_ids[7]).await.unwrap().deleted);
+    // The aggregates are updated by the triggers for each row
+    let person_aggregates = PersonAggregates::read(pool, inserted_person.id)
+      .await
+      .unwrap();
+    assert_eq!(3, person_aggregates.comment_count);
+    let post_aggregates = PostAggregates::read(pool, inserted_post.id).await.unwrap();
+    assert_eq!(3, post_aggregates.comments);
+    // Comments which are already deleted aren't counted again
+    let num_deleted = Comment::batch_delete(pool, &comment_ids[5..7])
+      .await
+      .unwrap();
+    assert_eq!(0, num_deleted);
+
+    let num_purged = Comment::purge_batch(pool, &comment_ids[7..]).await.unwrap();
+    assert_eq!(3, num_purged);
+    assert!(Comment::read(pool, comment_ids[7]).await.is_err());
+    let post_aggregates = PostAggregates::read(pool, inserted_post.id).await.unwrap();
+    assert_eq!(0, post_aggregates.comments);
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_edit_history() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("terry_edit".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("test community edit".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let new_post = PostInsertForm::builder()
+      .name("A test post".into())
+      .creator_id(inserted_person.id)
+      .community_id(inserted_community.id)
+      .build();
+    let inserted_post = Post::create(pool, &new_post).await.unwrap();
+
+    let comment_form = CommentInsertForm::builder()
+      .content("First version".into())
+      .creator_id(inserted_person.id)
+      .post_id(inserted_post.id)
+      .build();
+    let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+    assert!(Comment::get_edit_history(pool, inserted_comment.id)
+      .await
+      .unwrap()
+      .is_empty());
+
+    let edit_form = |new_content: &str| {
+      CommentUpdateForm::builder()
+        .content(Some(new_content.into()))
+        .updated(Some(Some(naive_now())))
+        .build()
+    };
+    Comment::update_with_history(pool, inserted_comment.id, &edit_form("Second version"))
+      .await
+      .unwrap();
+    let updated_comment =
+      Comment::update_with_history(pool, inserted_comment.id, &edit_form("Third version"))
+        .await
+        .unwrap();
+    assert_eq!("Third version", updated_comment.content);
+    assert!(updated_comment.updated.is_some());
+    // Unchanged content doesn't add a version
+    Comment::update_with_history(pool, inserted_comment.id, &edit_form("Third version"))
+      .await
+      .unwrap();
+
+    let history = Comment::get_edit_history(pool, inserted_comment.id)
+      .await
+      .unwrap();
+    let previous_versions: Vec<_> = history.iter().map(|h| h.content.as_str()).collect();
+    assert_eq!(vec!["First version", "Second version"], previous_versions);
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
 }
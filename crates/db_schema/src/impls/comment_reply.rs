@@ -64,6 +64,17 @@ impl CommentReply {
     .await
   }
 
+  /// Counts the unread replies of a person, without loading them.
+  pub async fn get_unread_count(pool: &DbPool, for_recipient_id: PersonId) -> Result<i64, Error> {
+    let conn = &mut get_conn(pool).await?;
+    comment_reply
+      .filter(recipient_id.eq(for_recipient_id))
+      .filter(read.eq(false))
+      .count()
+      .get_result::<i64>(conn)
+      .await
+  }
+
   pub async fn read_by_comment(pool: &DbPool, for_comment_id: CommentId) -> Result<Self, Error> {
     let conn = &mut get_conn(pool).await?;
     comment_reply
@@ -19,7 +19,15 @@ use crate::{
   utils::{functions::lower, get_conn, DbPool},
   SubscribedType,
 };
-use diesel::{dsl::insert_into, result::Error, ExpressionMethods, QueryDsl};
+use diesel::{
+  dsl::insert_into,
+  result::Error,
+  sql_query,
+  sql_types::Integer,
+  ExpressionMethods,
+  OptionalExtension,
+  QueryDsl,
+};
 use diesel_async::RunQueryDsl;
 
 #[async_trait]
@@ -140,6 +148,83 @@ impl Community {
     }
     Err(diesel::NotFound)
   }
+
+  /// Makes to_person_id the top moderator and removes from_person_id from the moderators. Fails
+  /// without changing anything if both are the same person, or if from_person_id isn't the top
+  /// moderator.
+  pub async fn transfer_ownership(
+    pool: &DbPool,
+    for_community_id: CommunityId,
+    from_person_id: PersonId,
+    to_person_id: PersonId,
+  ) -> Result<(), Error> {
+    use crate::schema::community_moderator::dsl::{
+      community_id,
+      community_moderator,
+      id,
+      person_id,
+      published,
+    };
+    if from_person_id == to_person_id {
+      return Err(Error::QueryBuilderError(
+        "Can't transfer a community to its current owner".into(),
+      ));
+    }
+    let conn = &mut get_conn(pool).await?;
+
+    conn
+      .build_transaction()
+      .run(|conn| {
+        Box::pin(async move {
+          // The moderators are ranked by the time they were added
+          let top_moderator = community_moderator
+            .filter(community_id.eq(for_community_id))
+            .order_by((published, id))
+            .select(person_id)
+            .first::<PersonId>(conn)
+            .await
+            .optional()?;
+          if top_moderator != Some(from_person_id) {
+            return Err(Error::QueryBuilderError(
+              "Only the top moderator can transfer a community".into(),
+            ));
+          }
+
+          let form = CommunityModeratorForm {
+            community_id: for_community_id,
+            person_id: to_person_id,
+          };
+          insert_into(community_moderator)
+            .values(&form)
+            .on_conflict((community_id, person_id))
+            .do_nothing()
+            .execute(conn)
+            .await?;
+
+          sql_query(
+            "update community_moderator set published = (
+              select min(published) - interval '1 second' from community_moderator
+              where community_id = $1
+            )
+            where community_id = $1 and person_id = $2",
+          )
+          .bind::<Integer, _>(for_community_id)
+          .bind::<Integer, _>(to_person_id)
+          .execute(conn)
+          .await?;
+
+          diesel::delete(
+            community_moderator
+              .filter(community_id.eq(for_community_id))
+              .filter(person_id.eq(from_person_id)),
+          )
+          .execute(conn)
+          .await?;
+          Ok(())
+        }) as _
+      })
+      .await
+  }
 }
 
 impl CommunityModerator {
@@ -178,6 +263,23 @@ impl CommunityModerator {
       .load::<CommunityId>(conn)
       .await
   }
+
+  pub async fn is_moderator(
+    pool: &DbPool,
+    for_person_id: PersonId,
+    for_community_id: CommunityId,
+  ) -> Result<bool, Error> {
+    use crate::schema::community_moderator::dsl::{community_id, community_moderator, person_id};
+    use diesel::dsl::{exists, select};
+    let conn = &mut get_conn(pool).await?;
+    select(exists(
+      community_moderator
+        .filter(person_id.eq(for_person_id))
+        .filter(community_id.eq(for_community_id)),
+    ))
+    .get_result(conn)
+    .await
+  }
 }
 
 #[async_trait]
@@ -214,6 +316,35 @@ impl Bannable for CommunityPersonBan {
   }
 }
 
+impl CommunityPersonBan {
+  /// Whether the person has a ban in the community which didn't expire yet.
+  pub async fn is_banned(
+    pool: &DbPool,
+    for_person_id: PersonId,
+    for_community_id: CommunityId,
+  ) -> Result<bool, Error> {
+    use crate::schema::community_person_ban::dsl::{
+      community_id,
+      community_person_ban,
+      expires,
+      person_id,
+    };
+    use diesel::{
+      dsl::{exists, now, select},
+      BoolExpressionMethods,
+    };
+    let conn = &mut get_conn(pool).await?;
+    select(exists(
+      community_person_ban
+        .filter(person_id.eq(for_person_id))
+        .filter(community_id.eq(for_community_id))
+        .filter(expires.is_null().or(expires.gt(now))),
+    ))
+    .get_result(conn)
+    .await
+  }
+}
+
 impl CommunityFollower {
   pub fn to_subscribed_type(follower: &Option<Self>) -> SubscribedType {
     match follower {
@@ -228,6 +359,38 @@ impl CommunityFollower {
       None => SubscribedType::NotSubscribed,
     }
   }
+
+  /// Approves a pending follow request, same as [Followable::follow_accepted].
+  pub async fn approve(
+    pool: &DbPool,
+    community_id: CommunityId,
+    person_id: PersonId,
+  ) -> Result<Self, Error> {
+    Self::follow_accepted(pool, community_id, person_id).await
+  }
+
+  /// Rejects a pending follow request by deleting it. Accepted follows are left alone.
+  pub async fn reject(
+    pool: &DbPool,
+    for_community_id: CommunityId,
+    for_person_id: PersonId,
+  ) -> Result<usize, Error> {
+    use crate::schema::community_follower::dsl::{
+      community_follower,
+      community_id,
+      pending,
+      person_id,
+    };
+    let conn = &mut get_conn(pool).await?;
+    diesel::delete(
+      community_follower
+        .filter(community_id.eq(for_community_id))
+        .filter(person_id.eq(for_person_id))
+        .filter(pending.eq(true)),
+    )
+    .execute(conn)
+    .await
+  }
 }
 
 #[async_trait]
@@ -329,6 +492,8 @@ impl ApubActor for Community {
 #[cfg(test)]
 mod tests {
   use crate::{
+    newtypes::{CommunityId, PersonId},
+    schema::community_moderator,
     source::{
       community::{
         Community,
@@ -345,10 +510,26 @@ mod tests {
       person::{Person, PersonInsertForm},
     },
     traits::{Bannable, Crud, Followable, Joinable},
-    utils::build_db_pool_for_tests,
+    utils::{build_db_pool_for_tests, get_conn, naive_now, DbPool},
+    SubscribedType,
   };
+  use chrono::Duration;
+  use diesel::{ExpressionMethods, QueryDsl};
+  use diesel_async::RunQueryDsl;
   use serial_test::serial;
 
+  /// The moderators of the community, top moderator first
+  async fn moderator_ids(pool: &DbPool, community_id: CommunityId) -> Vec<PersonId> {
+    let conn = &mut get_conn(pool).await.unwrap();
+    community_moderator::table
+      .filter(community_moderator::community_id.eq(community_id))
+      .order_by(community_moderator::published)
+      .select(community_moderator::person_id)
+      .load::<PersonId>(conn)
+      .await
+      .unwrap()
+  }
+
   #[tokio::test]
   #[serial]
   async fn test_crud() {
@@ -490,4 +671,256 @@ mod tests {
     // assert_eq!(2, loaded_count);
     assert_eq!(1, num_deleted);
   }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_pending_follow() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("bobbee_pending".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("TIL_pending".into())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    // Follows stay pending until the community accepts them
+    let community_follower_form = CommunityFollowerForm {
+      community_id: inserted_community.id,
+      person_id: inserted_person.id,
+      pending: true,
+    };
+    let pending_follower = CommunityFollower::follow(pool, &community_follower_form)
+      .await
+      .unwrap();
+    assert_eq!(
+      SubscribedType::Pending,
+      CommunityFollower::to_subscribed_type(&Some(pending_follower))
+    );
+
+    let accepted_follower =
+      CommunityFollower::follow_accepted(pool, inserted_community.id, inserted_person.id)
+        .await
+        .unwrap();
+    assert_eq!(
+      SubscribedType::Subscribed,
+      CommunityFollower::to_subscribed_type(&Some(accepted_follower))
+    );
+    assert_eq!(
+      SubscribedType::NotSubscribed,
+      CommunityFollower::to_subscribed_type(&None)
+    );
+
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Person::delete(pool, inserted_person.id).await.unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_transfer_ownership() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let mut person_ids = vec![];
+    for name in ["olga_owner", "mona_mod", "nils_new_owner"] {
+      let person_form = PersonInsertForm::builder()
+        .name(name.into())
+        .public_key("pubkey".to_string())
+        .instance_id(inserted_instance.id)
+        .build();
+      person_ids.push(Person::create(pool, &person_form).await.unwrap().id);
+    }
+    let (owner_id, mod_id, new_owner_id) = (person_ids[0], person_ids[1], person_ids[2]);
+
+    let new_community = CommunityInsertForm::builder()
+      .name("TIL_transfer".into())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+    let community_id = inserted_community.id;
+    for person_id in [owner_id, mod_id] {
+      let form = CommunityModeratorForm {
+        community_id,
+        person_id,
+      };
+      CommunityModerator::join(pool, &form).await.unwrap();
+    }
+
+    Community::transfer_ownership(pool, community_id, owner_id, new_owner_id)
+      .await
+      .unwrap();
+    assert_eq!(
+      vec![new_owner_id, mod_id],
+      moderator_ids(pool, community_id).await
+    );
+
+    // Transferring to an existing moderator moves them to the top
+    Community::transfer_ownership(pool, community_id, new_owner_id, mod_id)
+      .await
+      .unwrap();
+    assert_eq!(vec![mod_id], moderator_ids(pool, community_id).await);
+
+    // Transferring to the same person would remove them as moderator
+    let same_person = Community::transfer_ownership(pool, community_id, mod_id, mod_id).await;
+    assert!(same_person.is_err());
+    assert_eq!(vec![mod_id], moderator_ids(pool, community_id).await);
+
+    // Only the top moderator can transfer the community
+    let not_a_moderator =
+      Community::transfer_ownership(pool, community_id, owner_id, new_owner_id).await;
+    assert!(not_a_moderator.is_err());
+    assert_eq!(vec![mod_id], moderator_ids(pool, community_id).await);
+
+    Community::delete(pool, community_id).await.unwrap();
+    for person_id in person_ids {
+      Person::delete(pool, person_id).await.unwrap();
+    }
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_is_moderator() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let person_form = PersonInsertForm::builder()
+      .name("mod_check".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &person_form).await.unwrap();
+
+    let admin_form = PersonInsertForm::builder()
+      .name("admin_check".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .admin(Some(true))
+      .build();
+    let inserted_admin = Person::create(pool, &admin_form).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("TIL_is_mod".into())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let form = CommunityModeratorForm {
+      community_id: inserted_community.id,
+      person_id: inserted_person.id,
+    };
+    CommunityModerator::join(pool, &form).await.unwrap();
+    assert!(
+      CommunityModerator::is_moderator(pool, inserted_person.id, inserted_community.id)
+        .await
+        .unwrap()
+    );
+    assert!(
+      !CommunityModerator::is_moderator(pool, inserted_admin.id, inserted_community.id)
+        .await
+        .unwrap()
+    );
+
+    CommunityModerator::leave(pool, &form).await.unwrap();
+    assert!(
+      !CommunityModerator::is_moderator(pool, inserted_person.id, inserted_community.id)
+        .await
+        .unwrap()
+    );
+
+    assert!(Person::is_admin(pool, inserted_admin.id).await.unwrap());
+    assert!(!Person::is_admin(pool, inserted_person.id).await.unwrap());
+
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Person::delete(pool, inserted_admin.id).await.unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_is_banned() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let person_form = PersonInsertForm::builder()
+      .name("ban_check".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &person_form).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("TIL_is_banned".into())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let is_banned = || async {
+      CommunityPersonBan::is_banned(pool, inserted_person.id, inserted_community.id)
+        .await
+        .unwrap()
+    };
+    let ban = |expires| CommunityPersonBanForm {
+      community_id: inserted_community.id,
+      person_id: inserted_person.id,
+      expires: Some(expires),
+    };
+
+    assert!(!is_banned().await);
+
+    CommunityPersonBan::ban(pool, &ban(None)).await.unwrap();
+    assert!(is_banned().await);
+
+    let tomorrow = naive_now() + Duration::days(1);
+    CommunityPersonBan::ban(pool, &ban(Some(tomorrow)))
+      .await
+      .unwrap();
+    assert!(is_banned().await);
+
+    let yesterday = naive_now() - Duration::days(1);
+    CommunityPersonBan::ban(pool, &ban(Some(yesterday)))
+      .await
+      .unwrap();
+    assert!(!is_banned().await);
+
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
 }
@@ -1,4 +1,5 @@
 use crate::{
+  newtypes::{CommunityId, PersonId},
   schema::community_block::dsl::{community_block, community_id, person_id},
   source::community_block::{CommunityBlock, CommunityBlockForm},
   traits::Blockable,
@@ -7,6 +8,24 @@ use crate::{
 use diesel::{dsl::insert_into, result::Error, ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 
+impl CommunityBlock {
+  pub async fn is_blocking(
+    pool: &DbPool,
+    for_person_id: PersonId,
+    for_community_id: CommunityId,
+  ) -> Result<bool, Error> {
+    use diesel::dsl::{exists, select};
+    let conn = &mut get_conn(pool).await?;
+    select(exists(
+      community_block
+        .filter(person_id.eq(for_person_id))
+        .filter(community_id.eq(for_community_id)),
+    ))
+    .get_result(conn)
+    .await
+  }
+}
+
 #[async_trait]
 impl Blockable for CommunityBlock {
   type Form = CommunityBlockForm;
@@ -31,3 +50,70 @@ impl Blockable for CommunityBlock {
     .await
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    source::{
+      community::{Community, CommunityInsertForm},
+      community_block::{CommunityBlock, CommunityBlockForm},
+      instance::Instance,
+      person::{Person, PersonInsertForm},
+    },
+    traits::{Blockable, Crud},
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_is_blocking() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("community_blocker".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("blocked_community".into())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let community_block_form = CommunityBlockForm {
+      person_id: inserted_person.id,
+      community_id: inserted_community.id,
+    };
+    CommunityBlock::block(pool, &community_block_form)
+      .await
+      .unwrap();
+    assert!(
+      CommunityBlock::is_blocking(pool, inserted_person.id, inserted_community.id)
+        .await
+        .unwrap()
+    );
+
+    CommunityBlock::unblock(pool, &community_block_form)
+      .await
+      .unwrap();
+    assert!(
+      !CommunityBlock::is_blocking(pool, inserted_person.id, inserted_community.id)
+        .await
+        .unwrap()
+    );
+
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Person::delete(pool, inserted_person.id).await.unwrap();
+  }
+}
@@ -0,0 +1,98 @@
+use crate::{
+  schema::instance_block::dsl::{instance_block, instance_id, person_id},
+  source::instance_block::{InstanceBlock, InstanceBlockForm},
+  traits::Blockable,
+  utils::{get_conn, DbPool},
+};
+use diesel::{dsl::insert_into, result::Error, ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+
+#[async_trait]
+impl Blockable for InstanceBlock {
+  type Form = InstanceBlockForm;
+  async fn block(pool: &DbPool, instance_block_form: &Self::Form) -> Result<Self, Error> {
+    let conn = &mut get_conn(pool).await?;
+    insert_into(instance_block)
+      .values(instance_block_form)
+      .on_conflict((person_id, instance_id))
+      .do_update()
+      .set(instance_block_form)
+      .get_result::<Self>(conn)
+      .await
+  }
+  async fn unblock(pool: &DbPool, instance_block_form: &Self::Form) -> Result<usize, Error> {
+    let conn = &mut get_conn(pool).await?;
+    diesel::delete(
+      instance_block
+        .filter(person_id.eq(instance_block_form.person_id))
+        .filter(instance_id.eq(instance_block_form.instance_id)),
+    )
+    .execute(conn)
+    .await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    source::{
+      instance::Instance,
+      instance_block::{InstanceBlock, InstanceBlockForm},
+      person::{Person, PersonInsertForm},
+    },
+    traits::{Blockable, Crud},
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_block_and_unblock() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+    let blocked_instance = Instance::read_or_create(pool, "blocked_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("instance_blocker".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let instance_block_form = InstanceBlockForm {
+      person_id: inserted_person.id,
+      instance_id: blocked_instance.id,
+    };
+    let inserted_block = InstanceBlock::block(pool, &instance_block_form)
+      .await
+      .unwrap();
+
+    let expected_block = InstanceBlock {
+      id: inserted_block.id,
+      person_id: inserted_person.id,
+      instance_id: blocked_instance.id,
+      published: inserted_block.published,
+    };
+    assert_eq!(expected_block, inserted_block);
+
+    // Blocking twice is a no-op
+    let reblocked = InstanceBlock::block(pool, &instance_block_form)
+      .await
+      .unwrap();
+    assert_eq!(inserted_block.id, reblocked.id);
+
+    let num_deleted = InstanceBlock::unblock(pool, &instance_block_form)
+      .await
+      .unwrap();
+    assert_eq!(1, num_deleted);
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Instance::delete(pool, blocked_instance.id).await.unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+}
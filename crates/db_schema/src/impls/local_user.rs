@@ -1,22 +1,31 @@
 use crate::{
   newtypes::LocalUserId,
-  schema::local_user::dsl::{
-    accepted_application,
-    email,
-    email_verified,
-    local_user,
-    password_encrypted,
-    validator_time,
+  schema::{
+    email_verification,
+    local_user::dsl::{
+      accepted_application,
+      email,
+      email_verified,
+      local_user,
+      password_encrypted,
+      validator_time,
+    },
   },
   source::{
     actor_language::{LocalUserLanguage, SiteLanguage},
-    local_user::{LocalUser, LocalUserInsertForm, LocalUserUpdateForm},
+    email_verification::{EmailVerification, EmailVerificationForm},
+    local_user::{LocalUser, LocalUserInsertForm, LocalUserSettingsForm, LocalUserUpdateForm},
   },
   traits::Crud,
   utils::{get_conn, naive_now, DbPool},
 };
 use bcrypt::{hash, DEFAULT_COST};
-use diesel::{dsl::insert_into, result::Error, ExpressionMethods, QueryDsl};
+use diesel::{
+  dsl::{insert_into, now, IntervalDsl},
+  result::Error,
+  ExpressionMethods,
+  QueryDsl,
+};
 use diesel_async::RunQueryDsl;
 
 impl LocalUser {
@@ -37,6 +46,74 @@ impl LocalUser {
       .await
   }
 
+  /// Sets a new, unverified email, and replaces any pending verification with the given token.
+  pub async fn change_email(
+    pool: &DbPool,
+    local_user_id: LocalUserId,
+    new_email: &str,
+    verification_token: &str,
+  ) -> Result<Self, Error> {
+    let conn = &mut get_conn(pool).await?;
+    let form = EmailVerificationForm {
+      local_user_id,
+      email: new_email.to_string(),
+      verification_token: verification_token.to_string(),
+    };
+
+    conn
+      .build_transaction()
+      .run(|conn| {
+        Box::pin(async move {
+          diesel::delete(
+            email_verification::table.filter(email_verification::local_user_id.eq(local_user_id)),
+          )
+          .execute(conn)
+          .await?;
+          insert_into(email_verification::table)
+            .values(&form)
+            .execute(conn)
+            .await?;
+
+          diesel::update(local_user.find(local_user_id))
+            .set((email.eq(&form.email), email_verified.eq(false)))
+            .get_result::<Self>(conn)
+            .await
+        }) as _
+      })
+      .await
+  }
+
+  /// Marks the email of the token as verified and removes the token. Tokens expire after a week.
+  pub async fn verify_email(pool: &DbPool, token: &str) -> Result<Self, Error> {
+    let conn = &mut get_conn(pool).await?;
+
+    conn
+      .build_transaction()
+      .run(|conn| {
+        Box::pin(async move {
+          let verification = email_verification::table
+            .filter(email_verification::verification_token.eq(token))
+            .filter(email_verification::published.gt(now - 7.days()))
+            .first::<EmailVerification>(conn)
+            .await?;
+
+          diesel::delete(
+            email_verification::table
+              .filter(email_verification::local_user_id.eq(verification.local_user_id)),
+          )
+          .execute(conn)
+          .await?;
+
+          // The email is set as well, in case it was changed without change_email
+          diesel::update(local_user.find(verification.local_user_id))
+            .set((email.eq(verification.email), email_verified.eq(true)))
+            .get_result::<Self>(conn)
+            .await
+        }) as _
+      })
+      .await
+  }
+
   pub async fn set_all_users_email_verified(pool: &DbPool) -> Result<Vec<Self>, Error> {
     let conn = &mut get_conn(pool).await?;
     diesel::update(local_user)
@@ -55,6 +132,23 @@ impl LocalUser {
       .await
   }
 
+  /// Updates only the preferences which are set in the form.
+  pub async fn update_settings(
+    pool: &DbPool,
+    local_user_id: LocalUserId,
+    form: &LocalUserSettingsForm,
+  ) -> Result<Self, Error> {
+    // avoid error "There are no changes to save. This query cannot be built"
+    if form.is_empty() {
+      return Self::read(pool, local_user_id).await;
+    }
+    let conn = &mut get_conn(pool).await?;
+    diesel::update(local_user.find(local_user_id))
+      .set(form)
+      .get_result::<Self>(conn)
+      .await
+  }
+
   pub async fn is_email_taken(pool: &DbPool, email_: &str) -> Result<bool, Error> {
     use diesel::dsl::{exists, select};
     let conn = &mut get_conn(pool).await?;
@@ -64,6 +158,22 @@ impl LocalUser {
   }
 }
 
+impl LocalUserSettingsForm {
+  fn is_empty(&self) -> bool {
+    self.show_nsfw.is_none()
+      && self.theme.is_none()
+      && self.default_sort_type.is_none()
+      && self.default_listing_type.is_none()
+      && self.interface_language.is_none()
+      && self.show_avatars.is_none()
+      && self.send_notifications_to_email.is_none()
+      && self.show_bot_accounts.is_none()
+      && self.show_scores.is_none()
+      && self.show_read_posts.is_none()
+      && self.show_new_post_notifs.is_none()
+  }
+}
+
 #[async_trait]
 impl Crud for LocalUser {
   type InsertForm = LocalUserInsertForm;
@@ -115,3 +225,130 @@ impl Crud for LocalUser {
       .await
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    source::{
+      instance::Instance,
+      local_user::{LocalUser, LocalUserInsertForm, LocalUserSettingsForm},
+      person::{Person, PersonInsertForm},
+    },
+    traits::Crud,
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_change_email() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("emil_email".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let local_user_form = LocalUserInsertForm::builder()
+      .person_id(inserted_person.id)
+      .password_encrypted("123456".to_string())
+      .email(Some("emil@example.com".into()))
+      .email_verified(Some(true))
+      .build();
+    let inserted_local_user = LocalUser::create(pool, &local_user_form).await.unwrap();
+
+    let changed = LocalUser::change_email(
+      pool,
+      inserted_local_user.id,
+      "emil@example.org",
+      "first_token",
+    )
+    .await
+    .unwrap();
+    assert_eq!(Some("emil@example.org".to_string()), changed.email);
+    assert!(!changed.email_verified);
+
+    // Changing again replaces the pending token
+    LocalUser::change_email(
+      pool,
+      inserted_local_user.id,
+      "emil@example.net",
+      "second_token",
+    )
+    .await
+    .unwrap();
+    assert!(LocalUser::verify_email(pool, "first_token").await.is_err());
+
+    let verified = LocalUser::verify_email(pool, "second_token").await.unwrap();
+    assert_eq!(inserted_local_user.id, verified.id);
+    assert_eq!(Some("emil@example.net".to_string()), verified.email);
+    assert!(verified.email_verified);
+
+    // Tokens can only be used once
+    assert!(LocalUser::verify_email(pool, "second_token").await.is_err());
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_update_settings() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("sett_settings".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let local_user_form = LocalUserInsertForm::builder()
+      .person_id(inserted_person.id)
+      .password_encrypted("123456".to_string())
+      .theme(Some("darkly".into()))
+      .show_bot_accounts(Some(false))
+      .build();
+    let inserted_local_user = LocalUser::create(pool, &local_user_form).await.unwrap();
+    assert!(!inserted_local_user.show_nsfw);
+
+    let settings_form = LocalUserSettingsForm::builder()
+      .show_nsfw(Some(true))
+      .build();
+    let updated = LocalUser::update_settings(pool, inserted_local_user.id, &settings_form)
+      .await
+      .unwrap();
+    assert!(updated.show_nsfw);
+    // Everything else is unchanged
+    assert_eq!(
+      LocalUser {
+        show_nsfw: true,
+        ..inserted_local_user.clone()
+      },
+      updated
+    );
+
+    // An empty form changes nothing
+    let unchanged = LocalUser::update_settings(
+      pool,
+      inserted_local_user.id,
+      &LocalUserSettingsForm::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(updated, unchanged);
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+}
@@ -1,12 +1,11 @@
 use crate::{
   newtypes::{CommunityId, DbUrl, PersonId},
   schema::{instance, local_user, person, person_follower},
-  source::person::{
-    Person,
-    PersonFollower,
-    PersonFollowerForm,
-    PersonInsertForm,
-    PersonUpdateForm,
+  source::{
+    comment_reply::CommentReply,
+    person::{Person, PersonFollower, PersonFollowerForm, PersonInsertForm, PersonUpdateForm},
+    person_mention::PersonMention,
+    private_message::PrivateMessage,
   },
   traits::{ApubActor, Crud, Followable},
   utils::{functions::lower, get_conn, naive_now, DbPool},
@@ -92,6 +91,23 @@ impl Person {
       .get_result::<Self>(conn)
       .await
   }
+
+  /// Counts the unread replies, mentions and private messages of a person, for notification badges.
+  pub async fn get_total_unread_count(pool: &DbPool, person_id: PersonId) -> Result<i64, Error> {
+    let replies = CommentReply::get_unread_count(pool, person_id).await?;
+    let mentions = PersonMention::get_unread_count(pool, person_id).await?;
+    let private_messages = PrivateMessage::get_unread_count(pool, person_id).await?;
+    Ok(replies + mentions + private_messages)
+  }
+
+  pub async fn is_admin(pool: &DbPool, person_id: PersonId) -> Result<bool, Error> {
+    let conn = &mut get_conn(pool).await?;
+    person::table
+      .find(person_id)
+      .select(person::admin)
+      .first::<bool>(conn)
+      .await
+  }
 }
 
 pub fn is_banned(banned_: bool, expires: Option<chrono::NaiveDateTime>) -> bool {
@@ -199,8 +215,14 @@ impl PersonFollower {
 mod tests {
   use crate::{
     source::{
+      comment::{Comment, CommentInsertForm},
+      comment_reply::{CommentReply, CommentReplyInsertForm, CommentReplyUpdateForm},
+      community::{Community, CommunityInsertForm},
       instance::Instance,
       person::{Person, PersonFollower, PersonFollowerForm, PersonInsertForm, PersonUpdateForm},
+      person_mention::{PersonMention, PersonMentionInsertForm},
+      post::{Post, PostInsertForm},
+      private_message::{PrivateMessage, PrivateMessageInsertForm},
     },
     traits::{Crud, Followable},
     utils::build_db_pool_for_tests,
@@ -306,4 +328,119 @@ mod tests {
     let unfollow = PersonFollower::unfollow(pool, &follow_form).await.unwrap();
     assert_eq!(1, unfollow);
   }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_unread_counts() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let person_form = PersonInsertForm::builder()
+      .name("holly_unread".into())
+      .public_key("nada".to_owned())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &person_form).await.unwrap();
+
+    let recipient_form = PersonInsertForm::builder()
+      .name("holly_unread_recipient".into())
+      .public_key("nada".to_owned())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_recipient = Person::create(pool, &recipient_form).await.unwrap();
+
+    let community_form = CommunityInsertForm::builder()
+      .name("holly_unread_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &community_form).await.unwrap();
+
+    let post_form = PostInsertForm::builder()
+      .name("A test post".into())
+      .creator_id(inserted_person.id)
+      .community_id(inserted_community.id)
+      .build();
+    let inserted_post = Post::create(pool, &post_form).await.unwrap();
+
+    // Two replies, a mention and a private message for the recipient
+    let mut reply_ids = vec![];
+    for _ in 0..2 {
+      let comment_form = CommentInsertForm::builder()
+        .content("A test comment".into())
+        .creator_id(inserted_person.id)
+        .post_id(inserted_post.id)
+        .build();
+      let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+      let reply_form = CommentReplyInsertForm {
+        recipient_id: inserted_recipient.id,
+        comment_id: inserted_comment.id,
+        read: None,
+      };
+      reply_ids.push(CommentReply::create(pool, &reply_form).await.unwrap().id);
+
+      let mention_form = PersonMentionInsertForm {
+        recipient_id: inserted_recipient.id,
+        comment_id: inserted_comment.id,
+        read: Some(reply_ids.len() > 1),
+      };
+      PersonMention::create(pool, &mention_form).await.unwrap();
+    }
+    let private_message_form = PrivateMessageInsertForm::builder()
+      .content("A test private message".into())
+      .creator_id(inserted_person.id)
+      .recipient_id(inserted_recipient.id)
+      .build();
+    PrivateMessage::create(pool, &private_message_form)
+      .await
+      .unwrap();
+
+    // Mark one of the replies as read
+    let reply_update_form = CommentReplyUpdateForm { read: Some(true) };
+    CommentReply::update(pool, reply_ids[0], &reply_update_form)
+      .await
+      .unwrap();
+
+    let recipient_id = inserted_recipient.id;
+    assert_eq!(
+      1,
+      CommentReply::get_unread_count(pool, recipient_id)
+        .await
+        .unwrap()
+    );
+    assert_eq!(
+      1,
+      PersonMention::get_unread_count(pool, recipient_id)
+        .await
+        .unwrap()
+    );
+    assert_eq!(
+      1,
+      PrivateMessage::get_unread_count(pool, recipient_id)
+        .await
+        .unwrap()
+    );
+    assert_eq!(
+      3,
+      Person::get_total_unread_count(pool, recipient_id)
+        .await
+        .unwrap()
+    );
+    assert_eq!(
+      0,
+      Person::get_total_unread_count(pool, inserted_person.id)
+        .await
+        .unwrap()
+    );
+
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Person::delete(pool, inserted_recipient.id).await.unwrap();
+  }
 }
@@ -21,6 +21,32 @@ impl PersonBlock {
       .first::<Self>(conn)
       .await
   }
+
+  pub async fn is_blocking(
+    pool: &DbPool,
+    for_person_id: PersonId,
+    for_target_id: PersonId,
+  ) -> Result<bool, Error> {
+    use diesel::dsl::{exists, select};
+    let conn = &mut get_conn(pool).await?;
+    select(exists(
+      person_block
+        .filter(person_id.eq(for_person_id))
+        .filter(target_id.eq(for_target_id)),
+    ))
+    .get_result(conn)
+    .await
+  }
+
+  /// Whether the recipient has blocked the sender, ie whether messages from the sender to the
+  /// recipient should be rejected.
+  pub async fn is_blocked_by(
+    pool: &DbPool,
+    sender_id: PersonId,
+    recipient_id: PersonId,
+  ) -> Result<bool, Error> {
+    Self::is_blocking(pool, recipient_id, sender_id).await
+  }
 }
 
 #[async_trait]
@@ -47,3 +73,99 @@ impl Blockable for PersonBlock {
     .await
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    source::{
+      instance::Instance,
+      person::{Person, PersonInsertForm},
+      person_block::{PersonBlock, PersonBlockForm},
+    },
+    traits::{Blockable, Crud},
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_is_blocking() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let mut person_ids = vec![];
+    for name in ["person_blocker", "person_blocked"] {
+      let person_form = PersonInsertForm::builder()
+        .name(name.into())
+        .public_key("pubkey".to_string())
+        .instance_id(inserted_instance.id)
+        .build();
+      person_ids.push(Person::create(pool, &person_form).await.unwrap().id);
+    }
+
+    let person_block_form = PersonBlockForm {
+      person_id: person_ids[0],
+      target_id: person_ids[1],
+    };
+    PersonBlock::block(pool, &person_block_form).await.unwrap();
+    assert!(PersonBlock::is_blocking(pool, person_ids[0], person_ids[1])
+      .await
+      .unwrap());
+    // Blocks are one way
+    assert!(
+      !PersonBlock::is_blocking(pool, person_ids[1], person_ids[0])
+        .await
+        .unwrap()
+    );
+
+    PersonBlock::unblock(pool, &person_block_form)
+      .await
+      .unwrap();
+    assert!(
+      !PersonBlock::is_blocking(pool, person_ids[0], person_ids[1])
+        .await
+        .unwrap()
+    );
+
+    for person_id in person_ids {
+      Person::delete(pool, person_id).await.unwrap();
+    }
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_is_blocked_by() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let mut person_ids = vec![];
+    for name in ["person_blocked_by_a", "person_blocked_by_b"] {
+      let person_form = PersonInsertForm::builder()
+        .name(name.into())
+        .public_key("pubkey".to_string())
+        .instance_id(inserted_instance.id)
+        .build();
+      person_ids.push(Person::create(pool, &person_form).await.unwrap().id);
+    }
+    let (a, b) = (person_ids[0], person_ids[1]);
+
+    let person_block_form = PersonBlockForm {
+      person_id: a,
+      target_id: b,
+    };
+    PersonBlock::block(pool, &person_block_form).await.unwrap();
+    // A blocked B, so B can't message A, but A can still message B
+    assert!(PersonBlock::is_blocked_by(pool, b, a).await.unwrap());
+    assert!(!PersonBlock::is_blocked_by(pool, a, b).await.unwrap());
+
+    for person_id in person_ids {
+      Person::delete(pool, person_id).await.unwrap();
+    }
+  }
+}
@@ -63,6 +63,17 @@ impl PersonMention {
     .await
   }
 
+  /// Counts the unread mentions of a person, without loading them.
+  pub async fn get_unread_count(pool: &DbPool, for_recipient_id: PersonId) -> Result<i64, Error> {
+    let conn = &mut get_conn(pool).await?;
+    person_mention
+      .filter(recipient_id.eq(for_recipient_id))
+      .filter(read.eq(false))
+      .count()
+      .get_result::<i64>(conn)
+      .await
+  }
+
   pub async fn read_by_comment_and_person(
     pool: &DbPool,
     for_comment_id: CommentId,
@@ -30,7 +30,14 @@ use crate::{
   utils::{get_conn, naive_now, DbPool, DELETED_REPLACEMENT_TEXT, FETCH_LIMIT_MAX},
 };
 use ::url::Url;
-use diesel::{dsl::insert_into, result::Error, ExpressionMethods, QueryDsl, TextExpressionMethods};
+use diesel::{
+  dsl::insert_into,
+  result::Error,
+  ExpressionMethods,
+  QueryDsl,
+  SelectableHelper,
+  TextExpressionMethods,
+};
 use diesel_async::RunQueryDsl;
 
 #[async_trait]
@@ -40,7 +47,11 @@ impl Crud for Post {
   type IdType = PostId;
   async fn read(pool: &DbPool, post_id: PostId) -> Result<Self, Error> {
     let conn = &mut get_conn(pool).await?;
-    post.find(post_id).first::<Self>(conn).await
+    post
+      .find(post_id)
+      .select(Self::as_select())
+      .first::<Self>(conn)
+      .await
   }
 
   async fn delete(pool: &DbPool, post_id: PostId) -> Result<usize, Error> {
@@ -55,6 +66,7 @@ impl Crud for Post {
       .on_conflict(ap_id)
       .do_update()
       .set(form)
+      .returning(Self::as_returning())
       .get_result::<Self>(conn)
       .await
   }
@@ -67,6 +79,7 @@ impl Crud for Post {
     let conn = &mut get_conn(pool).await?;
     diesel::update(post.find(post_id))
       .set(new_post)
+      .returning(Self::as_returning())
       .get_result::<Self>(conn)
       .await
   }
@@ -85,6 +98,7 @@ impl Post {
       .then_order_by(featured_community.desc())
       .then_order_by(published.desc())
       .limit(FETCH_LIMIT_MAX)
+      .select(Self::as_select())
       .load::<Self>(conn)
       .await
   }
@@ -101,6 +115,7 @@ impl Post {
       .filter(featured_community.eq(true))
       .then_order_by(published.desc())
       .limit(FETCH_LIMIT_MAX)
+      .select(Self::as_select())
       .load::<Self>(conn)
       .await
   }
@@ -119,6 +134,7 @@ impl Post {
         deleted.eq(true),
         updated.eq(naive_now()),
       ))
+      .returning(Self::as_returning())
       .get_results::<Self>(conn)
       .await
   }
@@ -140,6 +156,7 @@ impl Post {
 
     update
       .set((removed.eq(new_removed), updated.eq(naive_now())))
+      .returning(Self::as_returning())
       .get_results::<Self>(conn)
       .await
   }
@@ -154,6 +171,7 @@ impl Post {
     Ok(
       post
         .filter(ap_id.eq(object_id))
+        .select(Self::as_select())
         .first::<Post>(conn)
         .await
         .ok()
@@ -171,6 +189,7 @@ impl Post {
     post
       .filter(creator_id.eq(for_creator_id))
       .filter(url.like(pictrs_search))
+      .select(Self::as_select())
       .load::<Self>(conn)
       .await
   }
@@ -192,6 +211,7 @@ impl Post {
       url.eq::<Option<String>>(None),
       thumbnail_url.eq::<Option<String>>(None),
     ))
+    .returning(Self::as_returning())
     .get_results::<Self>(conn)
     .await
   }
@@ -205,6 +225,7 @@ impl Post {
     post
       .filter(community_id.eq(for_community_id))
       .filter(url.like(pictrs_search))
+      .select(Self::as_select())
       .load::<Self>(conn)
       .await
   }
@@ -226,6 +247,7 @@ impl Post {
       url.eq::<Option<String>>(None),
       thumbnail_url.eq::<Option<String>>(None),
     ))
+    .returning(Self::as_returning())
     .get_results::<Self>(conn)
     .await
   }
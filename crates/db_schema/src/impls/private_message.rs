@@ -1,6 +1,6 @@
 use crate::{
   newtypes::{DbUrl, PersonId, PrivateMessageId},
-  schema::private_message::dsl::{ap_id, private_message, read, recipient_id},
+  schema::private_message::dsl::{ap_id, deleted, private_message, read, recipient_id},
   source::private_message::{PrivateMessage, PrivateMessageInsertForm, PrivateMessageUpdateForm},
   traits::Crud,
   utils::{get_conn, DbPool},
@@ -69,6 +69,18 @@ impl PrivateMessage {
     .await
   }
 
+  /// Counts the unread, non-deleted private messages of a person, without loading them.
+  pub async fn get_unread_count(pool: &DbPool, for_recipient_id: PersonId) -> Result<i64, Error> {
+    let conn = &mut get_conn(pool).await?;
+    private_message
+      .filter(recipient_id.eq(for_recipient_id))
+      .filter(read.eq(false))
+      .filter(deleted.eq(false))
+      .count()
+      .get_result::<i64>(conn)
+      .await
+  }
+
   pub async fn read_from_apub_id(
     pool: &DbPool,
     object_id: Url,
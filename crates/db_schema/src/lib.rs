@@ -22,6 +22,8 @@ extern crate async_trait;
 
 pub mod aggregates;
 #[cfg(feature = "full")]
+pub mod error;
+#[cfg(feature = "full")]
 pub mod impls;
 pub mod newtypes;
 #[cfg(feature = "full")]
@@ -63,6 +65,9 @@ pub enum SortType {
   TopHour,
   TopSixHour,
   TopTwelveHour,
+  TopThreeYears,
+  /// The most relevant posts first, for full text searches. Other listings use Hot instead
+  BestMatch,
 }
 
 #[derive(EnumString, Display, Debug, Serialize, Deserialize, Clone, Copy)]
@@ -92,6 +97,8 @@ pub enum ListingType {
   Local,
   /// Content only from communities you've subscribed to.
   Subscribed,
+  /// Content only from communities you moderate.
+  ModeratedBy,
 }
 
 #[derive(EnumString, Display, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
@@ -125,6 +132,32 @@ pub enum SearchType {
   Url,
 }
 
+#[derive(EnumString, Display, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(TS))]
+#[cfg_attr(feature = "full", ts(export))]
+/// The type of a post, based on its url.
+pub enum PostType {
+  /// Posts with a url, including images.
+  Link,
+  /// Posts without a url.
+  Text,
+  /// Posts with a url pointing directly to an image.
+  Image,
+}
+
+#[derive(EnumString, Display, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(TS))]
+#[cfg_attr(feature = "full", ts(export))]
+/// Which posts to show, based on whether they or their community are marked NSFW.
+pub enum NsfwFilter {
+  /// Show all posts.
+  ShowAll,
+  /// Hide NSFW posts.
+  SafeOnly,
+  /// Only show NSFW posts.
+  NsfwOnly,
+}
+
 #[derive(EnumString, Display, Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
 #[cfg_attr(feature = "full", derive(TS))]
 #[cfg_attr(feature = "full", ts(export))]
@@ -12,6 +12,10 @@ pub mod sql_types {
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "sort_type_enum"))]
     pub struct SortTypeEnum;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tsvector", schema = "pg_catalog"))]
+    pub struct Tsvector;
 }
 
 diesel::table! {
@@ -96,6 +100,16 @@ diesel::table! {
         published -> Timestamp,
         child_count -> Int4,
         hot_rank -> Int4,
+        edit_count -> Int8,
+    }
+}
+
+diesel::table! {
+    comment_edit_history (id) {
+        id -> Int4,
+        comment_id -> Int4,
+        content -> Text,
+        edited_at -> Timestamp,
     }
 }
 
@@ -307,6 +321,15 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    instance_block (id) {
+        id -> Int4,
+        person_id -> Int4,
+        instance_id -> Int4,
+        published -> Timestamp,
+    }
+}
+
 diesel::table! {
     language (id) {
         id -> Int4,
@@ -579,6 +602,7 @@ diesel::table! {
         post_score -> Int8,
         comment_count -> Int8,
         comment_score -> Int8,
+        post_read_count -> Int8,
     }
 }
 
@@ -630,6 +654,9 @@ diesel::table! {
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Tsvector;
+
     post (id) {
         id -> Int4,
         #[max_length = 200]
@@ -655,6 +682,7 @@ diesel::table! {
         language_id -> Int4,
         featured_community -> Bool,
         featured_local -> Bool,
+        search_vector -> Tsvector,
     }
 }
 
@@ -835,6 +863,7 @@ diesel::joinable!(comment -> language (language_id));
 diesel::joinable!(comment -> person (creator_id));
 diesel::joinable!(comment -> post (post_id));
 diesel::joinable!(comment_aggregates -> comment (comment_id));
+diesel::joinable!(comment_edit_history -> comment (comment_id));
 diesel::joinable!(comment_like -> comment (comment_id));
 diesel::joinable!(comment_like -> person (person_id));
 diesel::joinable!(comment_like -> post (post_id));
@@ -860,6 +889,8 @@ diesel::joinable!(custom_emoji_keyword -> custom_emoji (custom_emoji_id));
 diesel::joinable!(email_verification -> local_user (local_user_id));
 diesel::joinable!(federation_allowlist -> instance (instance_id));
 diesel::joinable!(federation_blocklist -> instance (instance_id));
+diesel::joinable!(instance_block -> instance (instance_id));
+diesel::joinable!(instance_block -> person (person_id));
 diesel::joinable!(local_site -> site (site_id));
 diesel::joinable!(local_site_rate_limit -> local_site (local_site_id));
 diesel::joinable!(local_user -> person (person_id));
@@ -916,6 +947,7 @@ diesel::allow_tables_to_appear_in_same_query!(
     admin_purge_post,
     comment,
     comment_aggregates,
+    comment_edit_history,
     comment_like,
     comment_reply,
     comment_report,
@@ -933,6 +965,7 @@ diesel::allow_tables_to_appear_in_same_query!(
     federation_allowlist,
     federation_blocklist,
     instance,
+    instance_block,
     language,
     local_site,
     local_site_rate_limit,
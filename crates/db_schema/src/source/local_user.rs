@@ -103,3 +103,22 @@ pub struct LocalUserUpdateForm {
   pub totp_2fa_secret: Option<Option<String>>,
   pub totp_2fa_url: Option<Option<String>>,
 }
+
+#[derive(Clone, Default, TypedBuilder)]
+#[builder(field_defaults(default))]
+#[cfg_attr(feature = "full", derive(AsChangeset))]
+#[cfg_attr(feature = "full", diesel(table_name = local_user))]
+/// The display preferences of a local user. Fields which are None are left unchanged.
+pub struct LocalUserSettingsForm {
+  pub show_nsfw: Option<bool>,
+  pub theme: Option<String>,
+  pub default_sort_type: Option<SortType>,
+  pub default_listing_type: Option<ListingType>,
+  pub interface_language: Option<String>,
+  pub show_avatars: Option<bool>,
+  pub send_notifications_to_email: Option<bool>,
+  pub show_bot_accounts: Option<bool>,
+  pub show_scores: Option<bool>,
+  pub show_read_posts: Option<bool>,
+  pub show_new_post_notifs: Option<bool>,
+}
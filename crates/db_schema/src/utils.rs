@@ -91,6 +91,15 @@ pub fn limit_and_offset(
   Ok((limit, offset))
 }
 
+/// The pages to fetch from each source of a list which is merged in memory, as (page, limit)
+/// pairs. Any of the sources could fill the whole requested page, so all of their rows up to the
+/// end of it are needed, fetched in pages of at most the maximum limit.
+pub fn merged_pages(limit: i64, offset: i64) -> impl Iterator<Item = (i64, i64)> {
+  let fetch_limit = (offset + limit).min(FETCH_LIMIT_MAX);
+  let last_page = (offset + limit + fetch_limit - 1) / fetch_limit;
+  (1..=last_page).map(move |page| (page, fetch_limit))
+}
+
 pub fn limit_and_offset_unlimited(page: Option<i64>, limit: Option<i64>) -> (i64, i64) {
   let limit = limit.unwrap_or(FETCH_LIMIT_DEFAULT);
   let offset = limit * (page.unwrap_or(1) - 1);
@@ -249,7 +258,7 @@ pub fn naive_now() -> NaiveDateTime {
 
 pub fn post_to_comment_sort_type(sort: SortType) -> CommentSortType {
   match sort {
-    SortType::Active | SortType::Hot => CommentSortType::Hot,
+    SortType::Active | SortType::Hot | SortType::BestMatch => CommentSortType::Hot,
     SortType::New | SortType::NewComments | SortType::MostComments => CommentSortType::New,
     SortType::Old => CommentSortType::Old,
     SortType::TopHour
@@ -259,6 +268,7 @@ pub fn post_to_comment_sort_type(sort: SortType) -> CommentSortType {
     | SortType::TopAll
     | SortType::TopWeek
     | SortType::TopYear
+    | SortType::TopThreeYears
     | SortType::TopMonth => CommentSortType::Top,
   }
 }
@@ -320,6 +330,16 @@ mod tests {
     );
   }
 
+  #[test]
+  fn test_merged_pages() {
+    assert_eq!(vec![(1, 10)], merged_pages(10, 0).collect::<Vec<_>>());
+    assert_eq!(vec![(1, 30)], merged_pages(10, 20).collect::<Vec<_>>());
+    assert_eq!(
+      vec![(1, 50), (2, 50)],
+      merged_pages(20, 40).collect::<Vec<_>>()
+    );
+  }
+
   #[test]
   fn test_email() {
     assert!(is_email_regex("gush@gmail.com"));
@@ -7,6 +7,7 @@ use diesel::{
   JoinOnDsl,
   NullableExpressionMethods,
   QueryDsl,
+  SelectableHelper,
 };
 use diesel_async::RunQueryDsl;
 use lemmy_db_schema::{
@@ -79,7 +80,7 @@ impl CommentReportView {
       .select((
         comment_report::all_columns,
         comment::all_columns,
-        post::all_columns,
+        Post::as_select(),
         community::all_columns,
         person::all_columns,
         person_alias_1.fields(person::all_columns),
@@ -193,7 +194,7 @@ impl<'a> CommentReportQuery<'a> {
       .select((
         comment_report::all_columns,
         comment::all_columns,
-        post::all_columns,
+        Post::as_select(),
         community::all_columns,
         person::all_columns,
         person_alias_1.fields(person::all_columns),
@@ -478,6 +479,7 @@ mod tests {
         published: agg.published,
         child_count: 0,
         hot_rank: 1728,
+        edit_count: 0,
       },
       my_vote: None,
       resolver: None,
@@ -1,17 +1,25 @@
-use crate::structs::CommentView;
+use crate::{
+  post_view::{post_type_filter, TopNPerPartition},
+  structs::CommentView,
+};
 use diesel::{
+  dsl::{now, sql, IntervalDsl},
+  expression::SqlLiteral,
   result::Error,
+  sql_types,
   BoolExpressionMethods,
   ExpressionMethods,
   JoinOnDsl,
   NullableExpressionMethods,
   PgTextExpressionMethods,
   QueryDsl,
+  SelectableHelper,
 };
 use diesel_async::RunQueryDsl;
 use diesel_ltree::{nlevel, subpath, Ltree, LtreeExtensions};
 use lemmy_db_schema::{
   aggregates::structs::CommentAggregates,
+  error::LemmyDbError,
   newtypes::{CommentId, CommunityId, LocalUserId, PersonId, PostId},
   schema::{
     comment,
@@ -21,13 +29,18 @@ use lemmy_db_schema::{
     community,
     community_block,
     community_follower,
+    community_moderator,
     community_person_ban,
+    instance_block,
     local_user_language,
     person,
     person_block,
+    person_follower,
     post,
+    post_aggregates,
   },
   source::{
+    actor_language::LocalUserLanguage,
     comment::{Comment, CommentSaved},
     community::{Community, CommunityFollower, CommunityPersonBan},
     local_user::LocalUser,
@@ -35,11 +48,13 @@ use lemmy_db_schema::{
     person_block::PersonBlock,
     post::Post,
   },
-  traits::JoinView,
+  traits::{Crud, JoinView},
   utils::{fuzzy_search, get_conn, limit_and_offset_unlimited, DbPool},
   CommentSortType,
   ListingType,
+  PostType,
 };
+use tracing::warn;
 use typed_builder::TypedBuilder;
 
 type CommentViewTuple = (
@@ -53,32 +68,55 @@ type CommentViewTuple = (
   Option<CommentSaved>,
   Option<PersonBlock>,
   Option<i16>,
+  Option<i64>,
+  bool,
 );
 
+/// Counts the distinct creators in the thread of the top-level ancestor comment
+const THREAD_PARTICIPANTS_SQL: &str = "(SELECT COUNT(DISTINCT c.creator_id) FROM comment c \
+  WHERE c.path <@ subpath(comment.path, 0, 2))";
+
+/// Strips the matrix user id of the creator unless the viewer is allowed to see it
+fn apply_matrix_user_id_visibility(creator: &mut Person, visible: bool) -> Option<String> {
+  if !visible {
+    creator.matrix_user_id = None;
+  }
+  creator.matrix_user_id.clone()
+}
+
+fn thread_participants_select(include: bool) -> SqlLiteral<sql_types::Nullable<sql_types::BigInt>> {
+  if include {
+    sql(THREAD_PARTICIPANTS_SQL)
+  } else {
+    sql("NULL")
+  }
+}
+
 impl CommentView {
   pub async fn read(
     pool: &DbPool,
     comment_id: CommentId,
     my_person_id: Option<PersonId>,
-  ) -> Result<Self, Error> {
+  ) -> Result<Self, LemmyDbError> {
+    Self::read_many(pool, vec![comment_id], my_person_id)
+      .await?
+      .pop()
+      .ok_or(LemmyDbError::NotFound)
+  }
+
+  /// Reads the comments with these ids, ordered by path.
+  async fn read_many(
+    pool: &DbPool,
+    comment_ids: Vec<CommentId>,
+    my_person_id: Option<PersonId>,
+  ) -> Result<Vec<Self>, LemmyDbError> {
     let conn = &mut get_conn(pool).await?;
 
     // The left join below will return None in this case
     let person_id_join = my_person_id.unwrap_or(PersonId(-1));
 
-    let (
-      comment,
-      creator,
-      post,
-      community,
-      counts,
-      creator_banned_from_community,
-      follower,
-      saved,
-      creator_blocked,
-      comment_like,
-    ) = comment::table
-      .find(comment_id)
+    let res = comment::table
+      .filter(comment::id.eq_any(comment_ids))
       .inner_join(person::table)
       .inner_join(post::table)
       .inner_join(community::table.on(post::community_id.eq(community::id)))
@@ -111,6 +149,14 @@ impl CommentView {
             .and(person_block::person_id.eq(person_id_join)),
         ),
       )
+      .left_join(
+        person_follower::table.on(
+          comment::creator_id
+            .eq(person_follower::person_id)
+            .and(person_follower::follower_id.eq(person_id_join))
+            .and(person_follower::pending.eq(false)),
+        ),
+      )
       .left_join(
         comment_like::table.on(
           comment::id
@@ -121,7 +167,7 @@ impl CommentView {
       .select((
         comment::all_columns,
         person::all_columns,
-        post::all_columns,
+        Post::as_select(),
         community::all_columns,
         comment_aggregates::all_columns,
         community_person_ban::all_columns.nullable(),
@@ -129,206 +175,542 @@ impl CommentView {
         comment_saved::all_columns.nullable(),
         person_block::all_columns.nullable(),
         comment_like::score.nullable(),
+        thread_participants_select(my_person_id.is_some()),
+        // The matrix user id is only visible to the creator and their followers
+        person::id
+          .eq(person_id_join)
+          .or(person_follower::follower_id.nullable().is_not_null()),
       ))
-      .first::<CommentViewTuple>(conn)
+      .order_by(comment::path)
+      .load::<CommentViewTuple>(conn)
       .await?;
 
-    // If a person is given, then my_vote, if None, should be 0, not null
-    // Necessary to differentiate between other person's votes
-    let my_vote = if my_person_id.is_some() && comment_like.is_none() {
-      Some(0)
-    } else {
-      comment_like
-    };
-
-    Ok(CommentView {
-      comment,
-      post,
-      creator,
-      community,
-      counts,
-      creator_banned_from_community: creator_banned_from_community.is_some(),
-      subscribed: CommunityFollower::to_subscribed_type(&follower),
-      saved: saved.is_some(),
-      creator_blocked: creator_blocked.is_some(),
-      my_vote,
-    })
+    Ok(
+      res
+        .into_iter()
+        .map(|tuple| {
+          let mut comment_view = Self::from_tuple(tuple);
+          // If a person is given, then my_vote, if None, should be 0, not null
+          // Necessary to differentiate between other person's votes
+          if my_person_id.is_some() && comment_view.my_vote.is_none() {
+            comment_view.my_vote = Some(0);
+          }
+          comment_view
+        })
+        .collect(),
+    )
+  }
+
+  /// Reads a comment together with up to `depth` of its ancestors, root first and the comment
+  /// itself last.
+  pub async fn read_with_context(
+    pool: &DbPool,
+    comment_id: CommentId,
+    depth: u32,
+    my_person_id: Option<PersonId>,
+  ) -> Result<Vec<Self>, LemmyDbError> {
+    let path = {
+      let conn = &mut get_conn(pool).await?;
+      comment::table
+        .find(comment_id)
+        .select(comment::path)
+        .first::<Ltree>(conn)
+        .await?
+    };
+
+    // The path starts with 0, followed by the ids of the ancestors and the comment itself
+    let mut ids = path
+      .0
+      .split('.')
+      .skip(1)
+      .filter_map(|id| id.parse::<i32>().ok())
+      .map(CommentId)
+      .collect::<Vec<CommentId>>();
+    let first_id = ids.len().saturating_sub(depth as usize + 1);
+
+    Self::read_many(pool, ids.split_off(first_id), my_person_id).await
   }
 }
 
-#[derive(TypedBuilder)]
+#[derive(TypedBuilder, Clone)]
 #[builder(field_defaults(default))]
 pub struct CommentQuery<'a> {
   #[builder(!default)]
   pool: &'a DbPool,
   listing_type: Option<ListingType>,
   sort: Option<CommentSortType>,
+  /// Breaks ties of the primary sort. Hot isn't supported, and falls back to New
+  sort_secondary: Option<CommentSortType>,
+  /// Breaks the remaining ties by id, newest first (oldest first for Old), so that identical
+  /// queries return the same order. On by default, and always on with after_id
+  sort_stable: Option<bool>,
   community_id: Option<CommunityId>,
   post_id: Option<PostId>,
+  /// Only returns comments on these posts. Combine with top_n_per_post to preview several posts
+  /// in a single query
+  post_ids: Option<Vec<PostId>>,
   parent_path: Option<Ltree>,
   creator_id: Option<PersonId>,
+  /// Only returns content from these creators. Merged with creator_id if both are set, an empty
+  /// list without creator_id matches nothing
+  creator_ids: Option<Vec<PersonId>>,
+  /// Hides content from these creators, independent of the viewer's person blocks
+  exclude_person_ids: Option<Vec<PersonId>>,
   local_user: Option<&'a LocalUser>,
   search_term: Option<String>,
+  /// Only returns comments whose creator's name or display name matches this
+  creator_name_search: Option<String>,
+  /// Only returns comments by site admins
+  creator_admin_only: Option<bool>,
+  /// Set with apply_language_filter
+  #[builder(setter(skip))]
+  language_filter_person_id: Option<PersonId>,
+  /// Excludes comments by users banned from the site, which is the default. Moderation code which
+  /// must still see them sets this to false
+  hide_globally_banned: Option<bool>,
+  /// Only returns comments on posts of this type
+  post_type: Option<PostType>,
+  /// Comments on locked posts are included unless this is false
+  show_comments_on_locked_posts: Option<bool>,
+  /// Only returns comments in communities hosted on this instance, regardless of the creator
+  only_from_local_communities: Option<bool>,
+  /// Only returns comments on posts with at least this score
+  min_post_score: Option<i64>,
+  /// Only returns comments with at least this cached hot rank, to hide old or unpopular ones
+  min_hot_rank: Option<i32>,
+  /// Only returns comments which are at least this many days old
+  min_age_days: Option<i32>,
+  /// Only returns comments which are at most this many days old
+  max_age_days: Option<i32>,
+  /// Comments on nsfw posts or in nsfw communities are included unless this is false
+  show_nsfw: Option<bool>,
+  /// Zeroes the score, upvotes and downvotes of the comments when false, for blind voting. The hot
+  /// rank is zeroed too, as it's derived from the score. The viewer's own vote is still returned
+  show_scores: Option<bool>,
+  /// Comments by bot accounts are included unless this is false. Defaults to the setting of the
+  /// local user
+  show_bot_accounts: Option<bool>,
+  /// Only returns comments which received an award. Awards don't exist yet, so setting this is
+  /// an error for now
+  only_awarded: Option<bool>,
+  /// Only returns reported comments with unresolved reports, in communities the viewer moderates
+  has_unresolved_reports: Option<bool>,
+  /// Only returns reported comments, in communities the viewer moderates
+  has_any_reports: Option<bool>,
   saved_only: Option<bool>,
   show_deleted_and_removed: Option<bool>,
+  /// Removed comments are hidden from logged in users, unless they moderate the community
+  show_removed: Option<bool>,
   page: Option<i64>,
   limit: Option<i64>,
+  /// Keyset pagination: only returns the comments which come after this one. New and Old compare
+  /// (published, id), while Top and Hot compare (score, id) and (hot_rank, id). Can't be combined
+  /// with sort_secondary or max_depth
+  after_id: Option<CommentId>,
   max_depth: Option<i32>,
+  /// Only returns comments between these depths (inclusive), where top level comments are 1
+  between_depths: Option<(i32, i32)>,
+  /// Only returns the descendants of the comment with this path which are exactly this many
+  /// levels below it, for example 1 for its direct children
+  parent_path_ancestor: Option<(Ltree, i32)>,
+  /// Limits the results to the n highest scored comments of each post
+  top_n_per_post: Option<i32>,
+  /// Computes thread_participants even for logged out users
+  include_thread_stats: Option<bool>,
 }
 
 impl<'a> CommentQuery<'a> {
-  pub async fn list(self) -> Result<Vec<CommentView>, Error> {
+  /// A copy of the query with the same filters, but another sort and no pagination. Useful to list
+  /// several sorts of the same comments.
+  pub fn reset_sort(&self, sort: Option<CommentSortType>) -> Self {
+    Self {
+      sort,
+      sort_secondary: None,
+      ..self.reset_pagination(None, None)
+    }
+  }
+
+  /// A copy of the query with the same filters and sort, but another page.
+  pub fn reset_pagination(&self, page: Option<i64>, limit: Option<i64>) -> Self {
+    Self {
+      page,
+      limit,
+      after_id: None,
+      ..self.clone()
+    }
+  }
+
+  /// Fills in the comment listing preferences stored for a local user: show_nsfw,
+  /// show_bot_accounts, show_scores and the enabled languages. Options which were set on the
+  /// builder are kept. This doesn't attach the local user itself, which is still needed for votes,
+  /// saves and blocks.
+  pub async fn apply_local_user_settings(
+    mut self,
+    local_user_id: LocalUserId,
+  ) -> Result<Self, LemmyDbError> {
+    let local_user = LocalUser::read(self.pool, local_user_id).await?;
+    self.show_nsfw.get_or_insert(local_user.show_nsfw);
+    self
+      .show_bot_accounts
+      .get_or_insert(local_user.show_bot_accounts);
+    self.show_scores.get_or_insert(local_user.show_scores);
+    self
+      .language_filter_person_id
+      .get_or_insert(local_user.person_id);
+    Ok(self)
+  }
+
+  /// Only returns comments in the languages enabled by the local user of this person. Unlike
+  /// passing the local user, this doesn't affect votes, saves or blocks.
+  pub fn apply_language_filter(mut self, person_id: PersonId) -> Self {
+    self.language_filter_person_id = Some(person_id);
+    self
+  }
+
+  pub async fn list(self) -> Result<Vec<CommentView>, LemmyDbError> {
+    // Nothing can match an empty list of creators, so skip the query
+    if self.creator_id.is_none() && matches!(&self.creator_ids, Some(ids) if ids.is_empty()) {
+      return Ok(vec![]);
+    }
+
+    if self.only_awarded.unwrap_or(false) {
+      return Err(Error::QueryBuilderError("Awards feature not enabled".into()).into());
+    }
+
+    // The secondary sort key isn't part of the cursor, pages would skip or repeat comments
+    if self.after_id.is_some() && self.sort_secondary.is_some() {
+      return Err(
+        Error::QueryBuilderError("after_id can't be combined with sort_secondary".into()).into(),
+      );
+    }
+
+    // Tree fetches are ordered by the parent path first and aren't limited
+    if self.after_id.is_some() && self.max_depth.is_some() {
+      return Err(
+        Error::QueryBuilderError("after_id can't be combined with max_depth".into()).into(),
+      );
+    }
+
     let conn = &mut get_conn(self.pool).await?;
 
     // The left join below will return None in this case
     let person_id_join = self.local_user.map(|l| l.person_id).unwrap_or(PersonId(-1));
     let local_user_id_join = self.local_user.map(|l| l.id).unwrap_or(LocalUserId(-1));
 
-    let mut query = comment::table
-      .inner_join(person::table)
-      .inner_join(post::table)
-      .inner_join(community::table.on(post::community_id.eq(community::id)))
-      .inner_join(comment_aggregates::table)
-      .left_join(
-        community_person_ban::table.on(
-          community::id
-            .eq(community_person_ban::community_id)
-            .and(community_person_ban::person_id.eq(comment::creator_id)),
-        ),
-      )
-      .left_join(
-        community_follower::table.on(
-          post::community_id
-            .eq(community_follower::community_id)
-            .and(community_follower::person_id.eq(person_id_join)),
-        ),
-      )
-      .left_join(
-        comment_saved::table.on(
-          comment::id
-            .eq(comment_saved::comment_id)
-            .and(comment_saved::person_id.eq(person_id_join)),
-        ),
-      )
-      .left_join(
-        person_block::table.on(
-          comment::creator_id
-            .eq(person_block::target_id)
-            .and(person_block::person_id.eq(person_id_join)),
-        ),
-      )
-      .left_join(
-        community_block::table.on(
-          community::id
-            .eq(community_block::community_id)
-            .and(community_block::person_id.eq(person_id_join)),
-        ),
-      )
-      .left_join(
-        comment_like::table.on(
-          comment::id
-            .eq(comment_like::comment_id)
-            .and(comment_like::person_id.eq(person_id_join)),
-        ),
-      )
-      .left_join(
-        local_user_language::table.on(
-          comment::language_id
-            .eq(local_user_language::language_id)
-            .and(local_user_language::local_user_id.eq(local_user_id_join)),
-        ),
-      )
-      .select((
-        comment::all_columns,
-        person::all_columns,
-        post::all_columns,
-        community::all_columns,
-        comment_aggregates::all_columns,
-        community_person_ban::all_columns.nullable(),
-        community_follower::all_columns.nullable(),
-        comment_saved::all_columns.nullable(),
-        person_block::all_columns.nullable(),
-        comment_like::score.nullable(),
-      ))
-      .into_boxed();
+    // A max depth means its a tree fetch, which only goes this deep
+    let depth_limit = self.max_depth.map(|max_depth| {
+      if let Some(parent_path) = self.parent_path.as_ref() {
+        parent_path.0.split('.').count() as i32 + max_depth
+      } else {
+        // Add one because of root "0"
+        max_depth + 1
+      }
+    });
+
+    // Builds the joined query with all filters applied. Called a second time for features which
+    // need the filtered comments in a subquery, like top_n_per_post
+    let filtered_query = || {
+      let mut query = comment::table
+        .inner_join(person::table)
+        .inner_join(post::table)
+        .inner_join(community::table.on(post::community_id.eq(community::id)))
+        .inner_join(comment_aggregates::table)
+        .left_join(
+          community_person_ban::table.on(
+            community::id
+              .eq(community_person_ban::community_id)
+              .and(community_person_ban::person_id.eq(comment::creator_id)),
+          ),
+        )
+        .left_join(
+          community_follower::table.on(
+            post::community_id
+              .eq(community_follower::community_id)
+              .and(community_follower::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          comment_saved::table.on(
+            comment::id
+              .eq(comment_saved::comment_id)
+              .and(comment_saved::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          person_block::table.on(
+            comment::creator_id
+              .eq(person_block::target_id)
+              .and(person_block::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          community_block::table.on(
+            community::id
+              .eq(community_block::community_id)
+              .and(community_block::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          instance_block::table.on(
+            community::instance_id
+              .eq(instance_block::instance_id)
+              .and(instance_block::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          person_follower::table.on(
+            comment::creator_id
+              .eq(person_follower::person_id)
+              .and(person_follower::follower_id.eq(person_id_join))
+              .and(person_follower::pending.eq(false)),
+          ),
+        )
+        .left_join(
+          comment_like::table.on(
+            comment::id
+              .eq(comment_like::comment_id)
+              .and(comment_like::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          community_moderator::table.on(
+            post::community_id
+              .eq(community_moderator::community_id)
+              .and(community_moderator::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          local_user_language::table.on(
+            comment::language_id
+              .eq(local_user_language::language_id)
+              .and(local_user_language::local_user_id.eq(local_user_id_join)),
+          ),
+        )
+        .left_join(post_aggregates::table.on(post::id.eq(post_aggregates::post_id)))
+        .select((
+          comment::all_columns,
+          person::all_columns,
+          Post::as_select(),
+          community::all_columns,
+          comment_aggregates::all_columns,
+          community_person_ban::all_columns.nullable(),
+          community_follower::all_columns.nullable(),
+          comment_saved::all_columns.nullable(),
+          person_block::all_columns.nullable(),
+          comment_like::score.nullable(),
+          thread_participants_select(
+            self.local_user.is_some() || self.include_thread_stats.unwrap_or(false),
+          ),
+          // The matrix user id is only visible to the creator and their followers
+          person::id
+            .eq(person_id_join)
+            .or(person_follower::follower_id.nullable().is_not_null()),
+        ))
+        .into_boxed();
+
+      let mut creator_ids = self.creator_ids.clone().unwrap_or_default();
+      creator_ids.extend(self.creator_id);
+      if !creator_ids.is_empty() {
+        query = query.filter(comment::creator_id.eq_any(creator_ids));
+      };
 
-    if let Some(creator_id) = self.creator_id {
-      query = query.filter(comment::creator_id.eq(creator_id));
-    };
+      if let Some(exclude_person_ids) = &self.exclude_person_ids {
+        query = query.filter(comment::creator_id.ne_all(exclude_person_ids.clone()));
+      };
 
-    if let Some(post_id) = self.post_id {
-      query = query.filter(comment::post_id.eq(post_id));
-    };
+      if let Some(post_id) = self.post_id {
+        query = query.filter(comment::post_id.eq(post_id));
+      };
 
-    if let Some(parent_path) = self.parent_path.as_ref() {
-      query = query.filter(comment::path.contained_by(parent_path));
-    };
+      if let Some(post_ids) = &self.post_ids {
+        query = query.filter(comment::post_id.eq_any(post_ids.clone()));
+      };
 
-    if let Some(search_term) = self.search_term {
-      query = query.filter(comment::content.ilike(fuzzy_search(&search_term)));
-    };
+      if let Some(parent_path) = self.parent_path.as_ref() {
+        query = query.filter(comment::path.contained_by(parent_path));
+      };
 
-    if let Some(community_id) = self.community_id {
-      query = query.filter(post::community_id.eq(community_id));
-    }
+      if let Some(search_term) = &self.search_term {
+        query = query.filter(comment::content.ilike(fuzzy_search(search_term)));
+      };
 
-    if let Some(listing_type) = self.listing_type {
-      match listing_type {
-        ListingType::Subscribed => {
-          query = query.filter(community_follower::person_id.is_not_null())
-        } // TODO could be this: and(community_follower::person_id.eq(person_id_join)),
-        ListingType::Local => {
-          query = query.filter(community::local.eq(true)).filter(
-            community::hidden
-              .eq(false)
-              .or(community_follower::person_id.eq(person_id_join)),
-          )
-        }
-        ListingType::All => {
-          query = query.filter(
-            community::hidden
-              .eq(false)
-              .or(community_follower::person_id.eq(person_id_join)),
-          )
+      if let Some(creator_name_search) = &self.creator_name_search {
+        let searcher = fuzzy_search(creator_name_search);
+        query = query.filter(
+          person::name
+            .ilike(searcher.clone())
+            .or(person::display_name.ilike(searcher)),
+        );
+      }
+
+      if self.creator_admin_only.unwrap_or(false) {
+        query = query.filter(person::admin.eq(true));
+      }
+
+      if let Some(person_id) = self.language_filter_person_id {
+        query = query
+          .filter(comment::language_id.eq_any(LocalUserLanguage::enabled_for_person(person_id)));
+      }
+
+      if self.hide_globally_banned.unwrap_or(true) {
+        query = query.filter(person::banned.eq(false).or(person::ban_expires.lt(now)));
+      }
+
+      if let Some(community_id) = self.community_id {
+        query = query.filter(post::community_id.eq(community_id));
+      }
+
+      if let Some(post_type) = self.post_type {
+        query = query.filter(post_type_filter(post_type));
+      }
+
+      if !self.show_comments_on_locked_posts.unwrap_or(true) {
+        query = query.filter(post::locked.eq(false));
+      }
+
+      if self.only_from_local_communities.unwrap_or(false) {
+        query = query.filter(community::local.eq(true));
+      }
+
+      if !self.show_nsfw.unwrap_or(true) {
+        query = query
+          .filter(post::nsfw.eq(false))
+          .filter(community::nsfw.eq(false));
+      }
+
+      if let Some(min_post_score) = self.min_post_score {
+        query = query.filter(post_aggregates::score.ge(min_post_score));
+      }
+
+      if let Some(min_hot_rank) = self.min_hot_rank {
+        query = query.filter(comment_aggregates::hot_rank.ge(min_hot_rank));
+      }
+
+      if let Some(min_age_days) = self.min_age_days {
+        query = query.filter(comment::published.le(now - min_age_days.days()));
+      }
+
+      if let Some(max_age_days) = self.max_age_days {
+        query = query.filter(comment::published.ge(now - max_age_days.days()));
+      }
+
+      let has_unresolved_reports = self.has_unresolved_reports.unwrap_or(false);
+      let has_any_reports = self.has_any_reports.unwrap_or(false);
+      if has_unresolved_reports || has_any_reports {
+        // Don't leak the existence of reports to anyone but the community moderators
+        query = query.filter(community_moderator::person_id.is_not_null());
+      }
+      if has_unresolved_reports {
+        query = query.filter(sql::<sql_types::Bool>(
+          "EXISTS (SELECT 1 FROM comment_report cr \
+            WHERE cr.comment_id = comment.id AND cr.resolved = false)",
+        ));
+      }
+      if has_any_reports {
+        query = query.filter(sql::<sql_types::Bool>(
+          "EXISTS (SELECT 1 FROM comment_report cr WHERE cr.comment_id = comment.id)",
+        ));
+      }
+
+      if let Some(listing_type) = self.listing_type {
+        match listing_type {
+          ListingType::Subscribed => {
+            query = query.filter(community_follower::person_id.is_not_null())
+          } // TODO could be this: and(community_follower::person_id.eq(person_id_join)),
+          ListingType::Local => {
+            query = query.filter(community::local.eq(true)).filter(
+              community::hidden
+                .eq(false)
+                .or(community_follower::person_id.eq(person_id_join)),
+            )
+          }
+          ListingType::All => {
+            query = query.filter(
+              community::hidden
+                .eq(false)
+                .or(community_follower::person_id.eq(person_id_join)),
+            )
+          }
+          // The moderator join is already restricted to the viewer
+          ListingType::ModeratedBy => {
+            query = query.filter(community_moderator::person_id.is_not_null())
+          }
         }
       }
-    }
 
-    if self.saved_only.unwrap_or(false) {
-      query = query.filter(comment_saved::comment_id.is_not_null());
-    }
+      if self.saved_only.unwrap_or(false) {
+        query = query.filter(comment_saved::comment_id.is_not_null());
+      }
 
-    if !self.show_deleted_and_removed.unwrap_or(true) {
-      query = query.filter(comment::deleted.eq(false));
-      query = query.filter(comment::removed.eq(false));
-    }
+      if !self.show_deleted_and_removed.unwrap_or(true) {
+        query = query.filter(comment::deleted.eq(false));
+        query = query.filter(comment::removed.eq(false));
+      }
 
-    if !self.local_user.map(|l| l.show_bot_accounts).unwrap_or(true) {
-      query = query.filter(person::bot_account.eq(false));
-    };
+      // Logged out users still get the removed comments, so they can be shown as tombstones
+      if self.local_user.is_some() && !self.show_removed.unwrap_or(false) {
+        query = query.filter(
+          comment::removed
+            .eq(false)
+            .or(community_moderator::person_id.is_not_null()),
+        );
+      }
+
+      if !self
+        .show_bot_accounts
+        .or(self.local_user.map(|l| l.show_bot_accounts))
+        .unwrap_or(true)
+      {
+        query = query.filter(person::bot_account.eq(false));
+      };
 
-    if self.local_user.is_some() {
-      // Filter out the rows with missing languages
-      query = query.filter(local_user_language::language_id.is_not_null());
+      if self.local_user.is_some() {
+        // Filter out the rows with missing languages
+        query = query.filter(local_user_language::language_id.is_not_null());
 
-      // Don't show blocked communities or persons
-      if self.post_id.is_none() {
-        query = query.filter(community_block::person_id.is_null());
+        // Don't show blocked communities, instances or persons
+        if self.post_id.is_none() {
+          query = query.filter(community_block::person_id.is_null());
+          query = query.filter(instance_block::person_id.is_null());
+        }
+        query = query.filter(person_block::person_id.is_null());
       }
-      query = query.filter(person_block::person_id.is_null());
-    }
 
-    // A Max depth given means its a tree fetch
-    let (limit, offset) = if let Some(max_depth) = self.max_depth {
-      let depth_limit = if let Some(parent_path) = self.parent_path.as_ref() {
-        parent_path.0.split('.').count() as i32 + max_depth
+      if let Some((min_depth, max_depth)) = self.between_depths {
         // Add one because of root "0"
-      } else {
-        max_depth + 1
-      };
+        query = query.filter(nlevel(comment::path).between(min_depth + 1, max_depth + 1));
+      }
+
+      if let Some((ancestor_path, depth)) = &self.parent_path_ancestor {
+        let level = ancestor_path.0.split('.').count() as i32 + depth;
+        query = query
+          .filter(comment::path.contained_by(ancestor_path.clone()))
+          .filter(nlevel(comment::path).eq(level));
+      }
 
-      query = query.filter(nlevel(comment::path).le(depth_limit));
+      if let Some(depth_limit) = depth_limit {
+        query = query.filter(nlevel(comment::path).le(depth_limit));
+      }
+
+      query
+    };
+
+    let mut query = filtered_query();
 
+    if let Some(top_n_per_post) = self.top_n_per_post {
+      // Window functions aren't supported by diesel, so rank the filtered comments in a subquery.
+      // This way comments which are hidden anyway don't take up any of the slots
+      let ranked = filtered_query().select((
+        comment::id,
+        sql::<sql_types::BigInt>(
+          "ROW_NUMBER() OVER (PARTITION BY comment.post_id \
+            ORDER BY comment_aggregates.score DESC, comment.published DESC)",
+        ),
+      ));
+      query = query.filter(TopNPerPartition::new("comment.id", ranked, top_n_per_post));
+    }
+
+    let (limit, offset) = if depth_limit.is_some() {
       // Always order by the parent path first
       query = query.order_by(subpath(comment::path, 0, -1));
 
@@ -345,13 +727,53 @@ impl<'a> CommentQuery<'a> {
       limit_and_offset_unlimited(self.page, self.limit)
     };
 
-    query = match self.sort.unwrap_or(CommentSortType::Hot) {
+    let sort = self.sort.unwrap_or(CommentSortType::Hot);
+
+    if let Some(after_id) = self.after_id {
+      // The same columns as in the order by below. In the subquery they refer to the after_id row
+      let (column, op) = match sort {
+        CommentSortType::New => ("comment.published", "<"),
+        CommentSortType::Old => ("comment.published", ">"),
+        CommentSortType::Top => ("comment_aggregates.score", "<"),
+        CommentSortType::Hot => ("comment_aggregates.hot_rank", "<"),
+      };
+      query = query.filter(
+        sql::<sql_types::Bool>(&format!(
+          "({column}, comment.id) {op} (SELECT {column}, comment.id FROM comment \
+          INNER JOIN comment_aggregates ON comment_aggregates.comment_id = comment.id \
+          WHERE comment.id = "
+        ))
+        .bind::<sql_types::Integer, _>(after_id)
+        .sql(")"),
+      );
+    }
+
+    query = match sort {
       CommentSortType::Hot => query.then_order_by(comment_aggregates::hot_rank.desc()),
       CommentSortType::New => query.then_order_by(comment::published.desc()),
       CommentSortType::Old => query.then_order_by(comment::published.asc()),
       CommentSortType::Top => query.order_by(comment_aggregates::score.desc()),
     };
 
+    query = match self.sort_secondary {
+      Some(CommentSortType::New) => query.then_order_by(comment::published.desc()),
+      Some(CommentSortType::Old) => query.then_order_by(comment::published.asc()),
+      Some(CommentSortType::Top) => query.then_order_by(comment_aggregates::score.desc()),
+      Some(CommentSortType::Hot) => {
+        warn!("Hot is not supported as a secondary comment sort, using New instead");
+        query.then_order_by(comment::published.desc())
+      }
+      None => query,
+    };
+
+    // Break the remaining ties by id, so that the after_id cursor is stable
+    if self.sort_stable.unwrap_or(true) || self.after_id.is_some() {
+      query = match sort {
+        CommentSortType::Old => query.then_order_by(comment::id.asc()),
+        _ => query.then_order_by(comment::id.desc()),
+      };
+    }
+
     // Note: deleted and removed comments are done on the front side
     let res = query
       .limit(limit)
@@ -359,62 +781,99 @@ impl<'a> CommentQuery<'a> {
       .load::<CommentViewTuple>(conn)
       .await?;
 
-    Ok(res.into_iter().map(CommentView::from_tuple).collect())
+    let show_scores = self.show_scores.unwrap_or(true);
+    Ok(
+      res
+        .into_iter()
+        .map(CommentView::from_tuple)
+        .map(|mut comment_view| {
+          if !show_scores {
+            comment_view.counts.score = 0;
+            comment_view.counts.upvotes = 0;
+            comment_view.counts.downvotes = 0;
+            comment_view.counts.hot_rank = 0;
+          }
+          comment_view
+        })
+        .collect(),
+    )
   }
 }
 
 impl JoinView for CommentView {
   type JoinTuple = CommentViewTuple;
-  fn from_tuple(a: Self::JoinTuple) -> Self {
+  fn from_tuple(mut a: Self::JoinTuple) -> Self {
+    let creator_matrix_user_id = apply_matrix_user_id_visibility(&mut a.1, a.11);
     Self {
       comment: a.0,
+      creator_avatar: a.1.avatar.clone(),
+      creator_matrix_user_id,
       creator: a.1,
       post: a.2,
       community: a.3,
       counts: a.4,
+      creator_ban_expires: a.5.as_ref().and_then(|b| b.expires),
       creator_banned_from_community: a.5.is_some(),
       subscribed: CommunityFollower::to_subscribed_type(&a.6),
       saved: a.7.is_some(),
       creator_blocked: a.8.is_some(),
       my_vote: a.9,
+      thread_participants: a.10,
     }
   }
 }
 
 #[cfg(test)]
 mod tests {
-  use crate::comment_view::{
-    Comment,
-    CommentQuery,
-    CommentSortType,
-    CommentView,
-    Community,
-    DbPool,
-    LocalUser,
-    Person,
-    PersonBlock,
-    Post,
+  use crate::{
+    comment_view::{
+      Comment,
+      CommentQuery,
+      CommentSortType,
+      CommentView,
+      Community,
+      DbPool,
+      LocalUser,
+      Person,
+      PersonBlock,
+      Post,
+    },
+    structs::PostView,
   };
+  use chrono::Duration;
+  use diesel::ExpressionMethods;
+  use diesel_async::RunQueryDsl;
   use lemmy_db_schema::{
     aggregates::structs::CommentAggregates,
     impls::actor_language::UNDETERMINED_ID,
-    newtypes::LanguageId,
+    newtypes::{CommentId, DbUrl, LanguageId},
+    schema::{comment_aggregates, post_aggregates},
     source::{
       actor_language::LocalUserLanguage,
-      comment::{CommentInsertForm, CommentLike, CommentLikeForm},
-      community::CommunityInsertForm,
+      comment::{CommentInsertForm, CommentLike, CommentLikeForm, CommentUpdateForm},
+      comment_report::{CommentReport, CommentReportForm},
+      community::{
+        CommunityInsertForm,
+        CommunityModerator,
+        CommunityModeratorForm,
+        CommunityPersonBan,
+        CommunityPersonBanForm,
+      },
       instance::Instance,
       language::Language,
-      local_user::LocalUserInsertForm,
-      person::PersonInsertForm,
+      local_user::{LocalUserInsertForm, LocalUserUpdateForm},
+      person::{PersonFollower, PersonFollowerForm, PersonInsertForm},
       person_block::PersonBlockForm,
       post::PostInsertForm,
     },
-    traits::{Blockable, Crud, Likeable},
-    utils::build_db_pool_for_tests,
+    traits::{Bannable, Blockable, Crud, Followable, Joinable, Likeable, Reportable},
+    utils::{build_db_pool_for_tests, get_conn, naive_now},
+    ListingType,
+    PostType,
     SubscribedType,
   };
   use serial_test::serial;
+  use url::Url;
 
   struct Data {
     inserted_instance: Instance,
@@ -597,6 +1056,8 @@ mod tests {
 
     let mut expected_comment_view_with_person = expected_comment_view_no_person.clone();
     expected_comment_view_with_person.my_vote = Some(1);
+    // Timmy and sara both commented in the thread
+    expected_comment_view_with_person.thread_participants = Some(2);
 
     let read_comment_views_no_person = CommentQuery::builder()
       .pool(pool)
@@ -640,6 +1101,22 @@ mod tests {
 
     // Make sure block set the creator blocked
     assert!(read_comment_from_blocked_person.creator_blocked);
+    assert_eq!(
+      Some(2),
+      read_comment_from_blocked_person.thread_participants
+    );
+
+    let read_comment_views_with_stats = CommentQuery::builder()
+      .pool(pool)
+      .post_id(Some(data.inserted_post.id))
+      .include_thread_stats(Some(true))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert!(read_comment_views_with_stats
+      .iter()
+      .all(|c| c.thread_participants == Some(2)));
 
     cleanup(data, pool).await;
   }
@@ -722,25 +1199,186 @@ mod tests {
 
   #[tokio::test]
   #[serial]
-  async fn test_languages() {
+  async fn test_between_depths() {
     let pool = &build_db_pool_for_tests().await;
     let data = init_data(pool).await;
 
-    // by default, user has all languages enabled and should see all comments
-    // (except from blocked user)
-    let all_languages = CommentQuery::builder()
+    let list_between_depths = |min_depth, max_depth| async move {
+      let mut contents = CommentQuery::builder()
+        .pool(pool)
+        .post_id(Some(data.inserted_post.id))
+        .between_depths(Some((min_depth, max_depth)))
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|c| c.comment.content)
+        .collect::<Vec<String>>();
+      contents.sort();
+      contents
+    };
+
+    // Only the second nesting level
+    let second_level = list_between_depths(2, 2).await;
+    assert_eq!(2, second_level.len());
+    assert!(second_level[0].starts_with("Comment 1"));
+    assert_eq!("Comment 2", second_level[1]);
+
+    let second_and_third_level = list_between_depths(2, 3).await;
+    assert_eq!(4, second_and_third_level.len());
+    assert!(!second_and_third_level.contains(&"Comment 0".to_string()));
+    assert!(!second_and_third_level.contains(&"Comment 5".to_string()));
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_parent_path_ancestor() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let list_descendants = |depth| {
+      let path = data.inserted_comment_0.path.clone();
+      async move {
+        let mut contents = CommentQuery::builder()
+          .pool(pool)
+          .parent_path_ancestor(Some((path, depth)))
+          .build()
+          .list()
+          .await
+          .unwrap()
+          .into_iter()
+          .map(|c| c.comment.content)
+          .collect::<Vec<String>>();
+        contents.sort();
+        contents
+      }
+    };
+
+    // Only the direct children, not the grandchildren
+    let children = list_descendants(1).await;
+    assert_eq!(2, children.len());
+    assert!(children[0].starts_with("Comment 1"));
+    assert_eq!("Comment 2", children[1]);
+
+    let grandchildren = list_descendants(2).await;
+    assert_eq!(2, grandchildren.len());
+    assert!(grandchildren[0].starts_with("Comment 3"));
+    assert!(grandchildren[1].starts_with("Comment 4"));
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_sort_secondary() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let list_top_then = |sort_secondary| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .post_id(Some(data.inserted_post.id))
+        .sort(Some(CommentSortType::Top))
+        .sort_secondary(Some(sort_secondary))
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|c| c.counts.score == 0)
+        .map(|c| c.comment.id)
+        .collect::<Vec<CommentId>>()
+    };
+
+    // All comments except comment 0 have a score of zero, so only the secondary sort orders them
+    let top_then_new = list_top_then(CommentSortType::New).await;
+    let top_then_old = list_top_then(CommentSortType::Old).await;
+    assert_eq!(5, top_then_new.len());
+    assert_eq!(data.inserted_comment_1.id, top_then_old[0]);
+    assert_eq!(
+      top_then_new,
+      top_then_old.into_iter().rev().collect::<Vec<CommentId>>()
+    );
+
+    // Hot falls back to newest first
+    let top_then_hot = list_top_then(CommentSortType::Hot).await;
+    assert_eq!(top_then_new, top_then_hot);
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_vote_counts_without_viewer() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // Timmy already upvoted comment 0, sara downvotes it
+    let comment_dislike_form = CommentLikeForm {
+      comment_id: data.inserted_comment_0.id,
+      post_id: data.inserted_post.id,
+      person_id: data.inserted_person_2.id,
+      score: -1,
+    };
+    CommentLike::like(pool, &comment_dislike_form)
+      .await
+      .unwrap();
+
+    // The vote counts are part of the aggregates, so they don't need a viewer
+    let comments = CommentQuery::builder()
       .pool(pool)
-      .local_user(Some(&data.inserted_local_user))
+      .post_id(Some(data.inserted_post.id))
       .build()
       .list()
       .await
       .unwrap();
-    assert_eq!(5, all_languages.len());
+    let comment_0 = comments
+      .iter()
+      .find(|c| c.comment.id == data.inserted_comment_0.id)
+      .unwrap();
+    assert_eq!(1, comment_0.counts.upvotes);
+    assert_eq!(1, comment_0.counts.downvotes);
+    assert_eq!(0, comment_0.counts.score);
+    assert_eq!(None, comment_0.my_vote);
+
+    // The breakdown adds up to the net score for every comment
+    for c in &comments {
+      assert_eq!(
+        c.counts.downvotes * 2,
+        c.counts.upvotes + c.counts.downvotes - c.counts.score
+      );
+    }
 
-    // change user lang to finnish, should only show one post in finnish and one undetermined
-    let finnish_id = Language::read_id_from_code(pool, Some("fi"))
+    CommentLike::remove(pool, data.inserted_person_2.id, data.inserted_comment_0.id)
       .await
-      .unwrap()
+      .unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_languages() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // by default, user has all languages enabled and should see all comments
+    // (except from blocked user)
+    let all_languages = CommentQuery::builder()
+      .pool(pool)
+      .local_user(Some(&data.inserted_local_user))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(5, all_languages.len());
+
+    // change user lang to finnish, should only show one post in finnish and one undetermined
+    let finnish_id = Language::read_id_from_code(pool, Some("fi"))
+      .await
+      .unwrap()
       .unwrap();
     LocalUserLanguage::update(pool, vec![finnish_id], data.inserted_local_user.id)
       .await
@@ -778,6 +1416,1576 @@ mod tests {
     cleanup(data, pool).await;
   }
 
+  #[tokio::test]
+  #[serial]
+  async fn test_apply_language_filter() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let post_form = PostInsertForm::builder()
+      .name("A multilingual post".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(data.inserted_community.id)
+      .build();
+    let inserted_post = Post::create(pool, &post_form).await.unwrap();
+
+    let mut language_ids = vec![];
+    for code in ["en", "de", "fr"] {
+      let language_id = Language::read_id_from_code(pool, Some(code))
+        .await
+        .unwrap()
+        .unwrap();
+      let comment_form = CommentInsertForm::builder()
+        .content(format!("A comment in {code}"))
+        .creator_id(data.inserted_person_2.id)
+        .post_id(inserted_post.id)
+        .language_id(Some(language_id))
+        .build();
+      Comment::create(pool, &comment_form, None).await.unwrap();
+      language_ids.push(language_id);
+    }
+    LocalUserLanguage::update(
+      pool,
+      vec![language_ids[0], language_ids[1]],
+      data.inserted_local_user.id,
+    )
+    .await
+    .unwrap();
+
+    let filtered = CommentQuery::builder()
+      .pool(pool)
+      .post_id(Some(inserted_post.id))
+      .build()
+      .apply_language_filter(data.inserted_person.id)
+      .list()
+      .await
+      .unwrap();
+    let mut filtered_languages: Vec<_> = filtered.iter().map(|c| c.comment.language_id).collect();
+    filtered_languages.sort_by_key(|l| l.0);
+    let mut expected = vec![language_ids[0], language_ids[1]];
+    expected.sort_by_key(|l| l.0);
+    assert_eq!(expected, filtered_languages);
+
+    // Persons without a local user have no language settings, so nothing matches
+    let remote = CommentQuery::builder()
+      .pool(pool)
+      .post_id(Some(inserted_post.id))
+      .build()
+      .apply_language_filter(data.inserted_person_2.id)
+      .list()
+      .await
+      .unwrap();
+    assert!(remote.is_empty());
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_top_n_per_post() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // A busy post with 10 comments of which the newest two are deleted and removed, and a quiet
+    // one with 2
+    let mut post_ids = vec![];
+    for (name, comment_count) in [("busy post", 10), ("quiet post", 2)] {
+      let post_form = PostInsertForm::builder()
+        .name(name.into())
+        .creator_id(data.inserted_person.id)
+        .community_id(data.inserted_community.id)
+        .build();
+      let inserted_post = Post::create(pool, &post_form).await.unwrap();
+      for i in 0..comment_count {
+        let comment_form = CommentInsertForm::builder()
+          .content(format!("{name} comment {i}"))
+          .creator_id(data.inserted_person.id)
+          .post_id(inserted_post.id)
+          .build();
+        let comment = Comment::create(pool, &comment_form, None).await.unwrap();
+        if name == "busy post" && i >= 8 {
+          let update_form = CommentUpdateForm::builder()
+            .deleted(Some(i == 8))
+            .removed(Some(i == 9))
+            .build();
+          Comment::update(pool, comment.id, &update_form)
+            .await
+            .unwrap();
+        }
+      }
+      post_ids.push(inserted_post.id);
+    }
+
+    let top_comments = CommentQuery::builder()
+      .pool(pool)
+      .community_id(Some(data.inserted_community.id))
+      .show_deleted_and_removed(Some(false))
+      .top_n_per_post(Some(3))
+      .limit(Some(50))
+      .build()
+      .list()
+      .await
+      .unwrap();
+
+    let count_for_post = |post_id| top_comments.iter().filter(|c| c.post.id == post_id).count();
+    // Hidden comments don't take up any of the slots
+    assert_eq!(3, count_for_post(post_ids[0]));
+    assert!(top_comments
+      .iter()
+      .all(|c| !c.comment.deleted && !c.comment.removed));
+    assert_eq!(2, count_for_post(post_ids[1]));
+    assert_eq!(3, count_for_post(data.inserted_post.id));
+
+    for post_id in post_ids {
+      Post::delete(pool, post_id).await.unwrap();
+    }
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_post_ids() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let mut post_ids = vec![];
+    for name in ["first post", "second post", "third post"] {
+      let post_form = PostInsertForm::builder()
+        .name(name.into())
+        .creator_id(data.inserted_person.id)
+        .community_id(data.inserted_community.id)
+        .build();
+      let inserted_post = Post::create(pool, &post_form).await.unwrap();
+      // The newest comment of each post is by sara, who is blocked by timmy
+      for i in 0..5 {
+        let creator_id = if i == 4 {
+          data.inserted_person_2.id
+        } else {
+          data.inserted_person.id
+        };
+        let comment_form = CommentInsertForm::builder()
+          .content(format!("{name} comment {i}"))
+          .creator_id(creator_id)
+          .post_id(inserted_post.id)
+          .build();
+        Comment::create(pool, &comment_form, None).await.unwrap();
+      }
+      post_ids.push(inserted_post.id);
+    }
+
+    let previews = CommentQuery::builder()
+      .pool(pool)
+      .post_ids(Some(post_ids.clone()))
+      .local_user(Some(&data.inserted_local_user))
+      .top_n_per_post(Some(2))
+      .build()
+      .list()
+      .await
+      .unwrap();
+
+    // The comments of the post from init_data aren't included, and the blocked comments don't
+    // take up any of the slots
+    assert_eq!(6, previews.len());
+    for post_id in &post_ids {
+      assert_eq!(2, previews.iter().filter(|c| &c.post.id == post_id).count());
+    }
+    assert!(previews
+      .iter()
+      .all(|c| c.creator.id == data.inserted_person.id));
+
+    for post_id in post_ids {
+      Post::delete(pool, post_id).await.unwrap();
+    }
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_after_id() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let post_form = PostInsertForm::builder()
+      .name("A post with many comments".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(data.inserted_community.id)
+      .build();
+    let inserted_post = Post::create(pool, &post_form).await.unwrap();
+
+    // Half of the comments are published at the same time, so the id has to break the ties
+    let same_time = chrono::Utc::now().naive_utc() - Duration::hours(1);
+    let mut comment_ids = vec![];
+    for i in 0..10 {
+      let comment_form = CommentInsertForm::builder()
+        .content(format!("comment {i}"))
+        .creator_id(data.inserted_person.id)
+        .post_id(inserted_post.id)
+        .published((i < 5).then_some(same_time))
+        .build();
+      let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+      comment_ids.push(inserted_comment.id);
+    }
+
+    // Follow the cursors until a page comes back empty
+    let paginate = |sort| {
+      let post_id = inserted_post.id;
+      async move {
+        let mut listed_ids = vec![];
+        let mut after_id = None;
+        loop {
+          let page = CommentQuery::builder()
+            .pool(pool)
+            .post_id(Some(post_id))
+            .sort(Some(sort))
+            .after_id(after_id)
+            .limit(Some(3))
+            .build()
+            .list()
+            .await
+            .unwrap();
+          let Some(last) = page.last() else {
+            return listed_ids;
+          };
+          assert!(page.len() <= 3);
+          after_id = Some(last.comment.id);
+          listed_ids.extend(page.iter().map(|c| c.comment.id));
+        }
+      }
+    };
+
+    let mut expected_old = comment_ids.clone();
+    assert_eq!(expected_old, paginate(CommentSortType::Old).await);
+
+    expected_old.reverse();
+    assert_eq!(expected_old, paginate(CommentSortType::New).await);
+
+    // Upvote one of the comments, the others are tied by score
+    let comment_like_form = CommentLikeForm {
+      comment_id: comment_ids[7],
+      post_id: inserted_post.id,
+      person_id: data.inserted_person.id,
+      score: 1,
+    };
+    CommentLike::like(pool, &comment_like_form).await.unwrap();
+
+    let mut expected_top = vec![comment_ids[7]];
+    expected_top.extend(comment_ids.iter().rev().filter(|id| id != &&comment_ids[7]));
+    assert_eq!(expected_top, paginate(CommentSortType::Top).await);
+
+    let with_secondary = CommentQuery::builder()
+      .pool(pool)
+      .post_id(Some(inserted_post.id))
+      .sort(Some(CommentSortType::Top))
+      .sort_secondary(Some(CommentSortType::Old))
+      .after_id(Some(comment_ids[7]))
+      .build()
+      .list()
+      .await;
+    assert_eq!(
+      "after_id can't be combined with sort_secondary",
+      with_secondary.unwrap_err().to_string()
+    );
+
+    let with_max_depth = CommentQuery::builder()
+      .pool(pool)
+      .post_id(Some(inserted_post.id))
+      .max_depth(Some(1))
+      .after_id(Some(comment_ids[7]))
+      .build()
+      .list()
+      .await;
+    assert_eq!(
+      "after_id can't be combined with max_depth",
+      with_max_depth.unwrap_err().to_string()
+    );
+
+    Post::delete(pool, inserted_post.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_cross_post_comments() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let url: DbUrl = Url::parse("https://example.com/cross-posted")
+      .unwrap()
+      .into();
+    let mut comment_ids = vec![];
+    for name in ["original post", "cross post"] {
+      let post_form = PostInsertForm::builder()
+        .name(name.into())
+        .url(Some(url.clone()))
+        .creator_id(data.inserted_person.id)
+        .community_id(data.inserted_community.id)
+        .build();
+      let inserted_post = Post::create(pool, &post_form).await.unwrap();
+      let comment_form = CommentInsertForm::builder()
+        .content(format!("Comment on the {name}"))
+        .creator_id(data.inserted_person.id)
+        .post_id(inserted_post.id)
+        .build();
+      comment_ids.push(Comment::create(pool, &comment_form, None).await.unwrap().id);
+    }
+
+    let post_ids = PostView::get_cross_post_ids(pool, &url).await.unwrap();
+    assert_eq!(2, post_ids.len());
+
+    let mut cross_post_comment_ids = CommentQuery::builder()
+      .pool(pool)
+      .post_ids(Some(post_ids.clone()))
+      .build()
+      .list()
+      .await
+      .unwrap()
+      .into_iter()
+      .map(|c| c.comment.id)
+      .collect::<Vec<CommentId>>();
+    cross_post_comment_ids.sort_by_key(|id| id.0);
+    assert_eq!(comment_ids, cross_post_comment_ids);
+
+    for post_id in post_ids {
+      Post::delete(pool, post_id).await.unwrap();
+    }
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_read_with_context() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let comment_form = CommentInsertForm::builder()
+      .content("A chained comment".into())
+      .creator_id(data.inserted_person.id)
+      .post_id(data.inserted_post.id)
+      .build();
+    // A chain of four comments: root -> child -> grandchild -> great grandchild
+    let mut chain = vec![];
+    let mut parent_id = None;
+    for _ in 0..4 {
+      let inserted_comment = Comment::create_with_parent_id(pool, &comment_form, parent_id)
+        .await
+        .unwrap();
+      parent_id = Some(inserted_comment.id);
+      chain.push(inserted_comment.id);
+    }
+
+    let context_ids = |depth| {
+      let comment_id = chain[3];
+      async move {
+        CommentView::read_with_context(pool, comment_id, depth, None)
+          .await
+          .unwrap()
+          .into_iter()
+          .map(|c| c.comment.id)
+          .collect::<Vec<CommentId>>()
+      }
+    };
+
+    assert_eq!(chain[1..].to_vec(), context_ids(2).await);
+    assert_eq!(chain, context_ids(10).await);
+    assert_eq!(vec![chain[3]], context_ids(0).await);
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_reset_sort() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let base = CommentQuery::builder()
+      .pool(pool)
+      .post_id(Some(data.inserted_post.id))
+      .creator_id(Some(data.inserted_person.id))
+      .sort(Some(CommentSortType::Top))
+      .page(Some(2))
+      .limit(Some(2))
+      .build();
+
+    let newest_first = base
+      .reset_sort(Some(CommentSortType::New))
+      .list()
+      .await
+      .unwrap();
+    let oldest_first = base
+      .reset_sort(Some(CommentSortType::Old))
+      .list()
+      .await
+      .unwrap();
+
+    // The creator filter is kept, the pagination is cleared
+    assert_eq!(5, newest_first.len());
+    assert!(newest_first
+      .iter()
+      .all(|c| c.creator.id == data.inserted_person.id));
+    let mut reversed = oldest_first
+      .iter()
+      .map(|c| c.comment.id)
+      .collect::<Vec<CommentId>>();
+    reversed.reverse();
+    assert_eq!(
+      reversed,
+      newest_first
+        .iter()
+        .map(|c| c.comment.id)
+        .collect::<Vec<CommentId>>()
+    );
+
+    // The base query only returns the second page
+    assert_eq!(2, base.clone().list().await.unwrap().len());
+    let all_pages = base.reset_pagination(None, None).list().await.unwrap();
+    assert_eq!(5, all_pages.len());
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_creator_admin_only() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let admin_form = PersonInsertForm::builder()
+      .name("adam_admin".into())
+      .public_key("pubkey".to_string())
+      .admin(Some(true))
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let inserted_admin = Person::create(pool, &admin_form).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("An official comment".into())
+      .creator_id(inserted_admin.id)
+      .post_id(data.inserted_post.id)
+      .build();
+    let admin_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let list_comments = |creator_admin_only| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .post_id(Some(data.inserted_post.id))
+        .creator_admin_only(Some(creator_admin_only))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    let admin_comments = list_comments(true).await;
+    assert_eq!(1, admin_comments.len());
+    assert_eq!(admin_comment.id, admin_comments[0].comment.id);
+
+    // The comments of regular users are only included without the filter
+    assert_eq!(7, list_comments(false).await.len());
+
+    Person::delete(pool, inserted_admin.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_hide_globally_banned() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let banned_form = PersonInsertForm::builder()
+      .name("barry_banned".into())
+      .public_key("pubkey".to_string())
+      .banned(Some(true))
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let inserted_banned = Person::create(pool, &banned_form).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("A comment by a banned user".into())
+      .creator_id(inserted_banned.id)
+      .post_id(data.inserted_post.id)
+      .build();
+    let banned_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    // A temporary ban which has run out doesn't hide anything
+    let expired_form = PersonInsertForm::builder()
+      .name("eddie_expired".into())
+      .public_key("pubkey".to_string())
+      .banned(Some(true))
+      .ban_expires(Some(naive_now() - Duration::days(1)))
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let inserted_expired = Person::create(pool, &expired_form).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("A comment by a formerly banned user".into())
+      .creator_id(inserted_expired.id)
+      .post_id(data.inserted_post.id)
+      .build();
+    let expired_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let list_comments = |hide_globally_banned| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .post_id(Some(data.inserted_post.id))
+        .hide_globally_banned(hide_globally_banned)
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    // Excluded by default
+    for hide_globally_banned in [None, Some(true)] {
+      let comments = list_comments(hide_globally_banned).await;
+      assert_eq!(7, comments.len());
+      assert!(comments.iter().all(|c| c.comment.id != banned_comment.id));
+      assert!(comments.iter().any(|c| c.comment.id == expired_comment.id));
+    }
+
+    let comments = list_comments(Some(false)).await;
+    assert_eq!(8, comments.len());
+    assert!(comments.iter().any(|c| c.comment.id == banned_comment.id));
+
+    Person::delete(pool, inserted_banned.id).await.unwrap();
+    Person::delete(pool, inserted_expired.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_creator_name_search() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let new_person_3 = PersonInsertForm::builder()
+      .name("tommy".into())
+      .display_name(Some("Tommy the tank".into()))
+      .public_key("pubkey".to_string())
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let inserted_person_3 = Person::create(pool, &new_person_3).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("Comment by tommy".into())
+      .creator_id(inserted_person_3.id)
+      .post_id(data.inserted_post.id)
+      .build();
+    Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let search_creator_name = |creator_name_search: &'static str| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .post_id(Some(data.inserted_post.id))
+        .creator_name_search(Some(creator_name_search.into()))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    // Timmy wrote 5 comments
+    let by_tim = search_creator_name("tim").await;
+    assert_eq!(5, by_tim.len());
+    assert!(by_tim
+      .iter()
+      .all(|c| c.creator.id == data.inserted_person.id));
+
+    // The display name is searched as well
+    let by_tank = search_creator_name("TANK").await;
+    assert_eq!(1, by_tank.len());
+    assert_eq!(inserted_person_3.id, by_tank[0].creator.id);
+
+    Person::delete(pool, inserted_person_3.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_creator_ids() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let new_person_3 = PersonInsertForm::builder()
+      .name("tara".into())
+      .public_key("pubkey".to_string())
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let inserted_person_3 = Person::create(pool, &new_person_3).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("Comment by tara".into())
+      .creator_id(inserted_person_3.id)
+      .post_id(data.inserted_post.id)
+      .build();
+    Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let list_by_creators = |creator_id, creator_ids| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .post_id(Some(data.inserted_post.id))
+        .creator_id(creator_id)
+        .creator_ids(creator_ids)
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    // Timmy wrote 5 comments, sara 1 and tara 1
+    let by_sara_and_tara = list_by_creators(
+      None,
+      Some(vec![data.inserted_person_2.id, inserted_person_3.id]),
+    )
+    .await;
+    assert_eq!(2, by_sara_and_tara.len());
+    assert!(by_sara_and_tara
+      .iter()
+      .all(|c| c.creator.id != data.inserted_person.id));
+
+    // The single creator_id is merged into the list
+    let by_timmy_and_tara = list_by_creators(
+      Some(data.inserted_person.id),
+      Some(vec![inserted_person_3.id]),
+    )
+    .await;
+    assert_eq!(6, by_timmy_and_tara.len());
+
+    assert!(list_by_creators(None, Some(vec![])).await.is_empty());
+
+    Person::delete(pool, inserted_person_3.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_exclude_person_ids() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let new_person_3 = PersonInsertForm::builder()
+      .name("tara".into())
+      .public_key("pubkey".to_string())
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let inserted_person_3 = Person::create(pool, &new_person_3).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("Comment by tara".into())
+      .creator_id(inserted_person_3.id)
+      .post_id(data.inserted_post.id)
+      .build();
+    Comment::create(pool, &comment_form, None).await.unwrap();
+
+    // Timmy wrote 5 comments, sara 1 and tara 1
+    let without_timmy_and_sara = CommentQuery::builder()
+      .pool(pool)
+      .post_id(Some(data.inserted_post.id))
+      .exclude_person_ids(Some(vec![
+        data.inserted_person.id,
+        data.inserted_person_2.id,
+      ]))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(1, without_timmy_and_sara.len());
+    assert_eq!(inserted_person_3.id, without_timmy_and_sara[0].creator.id);
+
+    Person::delete(pool, inserted_person_3.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_post_type() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // The post from init_data is a text post, add a link post with a comment
+    let link_post_form = PostInsertForm::builder()
+      .name("A link post".into())
+      .url(Some(
+        Url::parse("https://example.com/article").unwrap().into(),
+      ))
+      .creator_id(data.inserted_person.id)
+      .community_id(data.inserted_community.id)
+      .build();
+    let link_post = Post::create(pool, &link_post_form).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("Comment on a link".into())
+      .creator_id(data.inserted_person.id)
+      .post_id(link_post.id)
+      .build();
+    let link_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let list_post_type = |post_type| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .post_type(Some(post_type))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    let link_comments = list_post_type(PostType::Link).await;
+    assert_eq!(1, link_comments.len());
+    assert_eq!(link_comment.id, link_comments[0].comment.id);
+
+    let text_comments = list_post_type(PostType::Text).await;
+    assert_eq!(6, text_comments.len());
+    assert!(text_comments
+      .iter()
+      .all(|c| c.post.id == data.inserted_post.id));
+
+    assert!(list_post_type(PostType::Image).await.is_empty());
+
+    Post::delete(pool, link_post.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_show_comments_on_locked_posts() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let locked_post_form = PostInsertForm::builder()
+      .name("A locked post".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(data.inserted_community.id)
+      .locked(Some(true))
+      .build();
+    let locked_post = Post::create(pool, &locked_post_form).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("Comment before the lock".into())
+      .creator_id(data.inserted_person.id)
+      .post_id(locked_post.id)
+      .build();
+    let locked_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let list_locked = |show_locked| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .show_comments_on_locked_posts(Some(show_locked))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    let all_comments = list_locked(true).await;
+    assert_eq!(7, all_comments.len());
+    assert!(all_comments
+      .iter()
+      .any(|c| c.comment.id == locked_comment.id));
+
+    let unlocked_comments = list_locked(false).await;
+    assert_eq!(6, unlocked_comments.len());
+    assert!(unlocked_comments
+      .iter()
+      .all(|c| c.post.id == data.inserted_post.id));
+
+    Post::delete(pool, locked_post.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_only_from_local_communities() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let remote_community_form = CommunityInsertForm::builder()
+      .name("remote_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .local(Some(false))
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let remote_community = Community::create(pool, &remote_community_form)
+      .await
+      .unwrap();
+    let remote_post_form = PostInsertForm::builder()
+      .name("A remote post".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(remote_community.id)
+      .build();
+    let remote_post = Post::create(pool, &remote_post_form).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("Comment in a remote community".into())
+      .creator_id(data.inserted_person.id)
+      .post_id(remote_post.id)
+      .build();
+    let remote_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let list_local_communities = |only_local| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .only_from_local_communities(Some(only_local))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    let all_comments = list_local_communities(false).await;
+    assert!(all_comments
+      .iter()
+      .any(|c| c.comment.id == remote_comment.id));
+
+    let local_comments = list_local_communities(true).await;
+    assert_eq!(6, local_comments.len());
+    assert!(local_comments
+      .iter()
+      .all(|c| c.community.id == data.inserted_community.id));
+
+    Post::delete(pool, remote_post.id).await.unwrap();
+    Community::delete(pool, remote_community.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_listing_type_moderated_by() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let other_community_form = CommunityInsertForm::builder()
+      .name("unmoderated_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let other_community = Community::create(pool, &other_community_form)
+      .await
+      .unwrap();
+    let other_post_form = PostInsertForm::builder()
+      .name("A post in an unmoderated community".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(other_community.id)
+      .build();
+    let other_post = Post::create(pool, &other_post_form).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("Comment in an unmoderated community".into())
+      .creator_id(data.inserted_person.id)
+      .post_id(other_post.id)
+      .build();
+    Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let list_moderated = || async {
+      CommentQuery::builder()
+        .pool(pool)
+        .local_user(Some(&data.inserted_local_user))
+        .listing_type(Some(ListingType::ModeratedBy))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    // Timmy doesn't moderate any community yet
+    assert!(list_moderated().await.is_empty());
+
+    let moderator_form = CommunityModeratorForm {
+      community_id: data.inserted_community.id,
+      person_id: data.inserted_person.id,
+    };
+    CommunityModerator::join(pool, &moderator_form)
+      .await
+      .unwrap();
+    let moderated_comments = list_moderated().await;
+    assert!(!moderated_comments.is_empty());
+    assert!(moderated_comments
+      .iter()
+      .all(|c| c.community.id == data.inserted_community.id));
+
+    CommunityModerator::leave(pool, &moderator_form)
+      .await
+      .unwrap();
+    Post::delete(pool, other_post.id).await.unwrap();
+    Community::delete(pool, other_community.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_only_awarded() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let awarded = CommentQuery::builder()
+      .pool(pool)
+      .only_awarded(Some(true))
+      .build()
+      .list()
+      .await;
+    assert_eq!(
+      "Awards feature not enabled",
+      awarded.unwrap_err().to_string()
+    );
+
+    // Not asking for awards is fine
+    CommentQuery::builder()
+      .pool(pool)
+      .only_awarded(Some(false))
+      .build()
+      .list()
+      .await
+      .unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_show_nsfw() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let nsfw_community_form = CommunityInsertForm::builder()
+      .name("nsfw_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .nsfw(Some(true))
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let nsfw_community = Community::create(pool, &nsfw_community_form).await.unwrap();
+    let post_form = PostInsertForm::builder()
+      .name("A post in an nsfw community".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(nsfw_community.id)
+      .build();
+    let nsfw_post = Post::create(pool, &post_form).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("A comment in an nsfw community".into())
+      .creator_id(data.inserted_person.id)
+      .post_id(nsfw_post.id)
+      .build();
+    let nsfw_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let list_nsfw = |show_nsfw| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .show_nsfw(show_nsfw)
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    // Shown by default
+    let all_comments = list_nsfw(None).await;
+    assert!(all_comments.iter().any(|c| c.comment.id == nsfw_comment.id));
+
+    let safe_comments = list_nsfw(Some(false)).await;
+    assert_eq!(6, safe_comments.len());
+    assert!(safe_comments
+      .iter()
+      .all(|c| c.comment.id != nsfw_comment.id));
+
+    Post::delete(pool, nsfw_post.id).await.unwrap();
+    Community::delete(pool, nsfw_community.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_apply_local_user_settings() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let bot_form = PersonInsertForm::builder()
+      .name("bot_commenter".into())
+      .public_key("pubkey".to_string())
+      .bot_account(Some(true))
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let inserted_bot = Person::create(pool, &bot_form).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("A comment by a bot".into())
+      .creator_id(inserted_bot.id)
+      .post_id(data.inserted_post.id)
+      .build();
+    let bot_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let local_user_form = LocalUserUpdateForm::builder()
+      .show_bot_accounts(Some(false))
+      .show_scores(Some(false))
+      .build();
+    LocalUser::update(pool, data.inserted_local_user.id, &local_user_form)
+      .await
+      .unwrap();
+
+    let query = CommentQuery::builder()
+      .pool(pool)
+      .post_id(Some(data.inserted_post.id))
+      .build();
+    let all_comments = query.clone().list().await.unwrap();
+    assert!(all_comments.iter().any(|c| c.comment.id == bot_comment.id));
+
+    let personalized_comments = query
+      .apply_local_user_settings(data.inserted_local_user.id)
+      .await
+      .unwrap()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(all_comments.len() - 1, personalized_comments.len());
+    assert!(personalized_comments
+      .iter()
+      .all(|c| c.comment.id != bot_comment.id));
+    assert!(personalized_comments.iter().all(|c| c.counts.score == 0));
+
+    Person::delete(pool, inserted_bot.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_creator_avatar() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let avatar: DbUrl = Url::parse("https://example.com/avatar.png").unwrap().into();
+    let person_form = PersonInsertForm::builder()
+      .name("avatar_commenter".into())
+      .public_key("pubkey".to_string())
+      .avatar(Some(avatar.clone()))
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &person_form).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("A comment with an avatar".into())
+      .creator_id(inserted_person.id)
+      .post_id(data.inserted_post.id)
+      .build();
+    let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let read_comment = CommentView::read(pool, inserted_comment.id, None)
+      .await
+      .unwrap();
+    assert_eq!(Some(avatar.clone()), read_comment.creator_avatar);
+    assert_eq!(read_comment.creator.avatar, read_comment.creator_avatar);
+
+    let listed_comments = CommentQuery::builder()
+      .pool(pool)
+      .creator_id(Some(inserted_person.id))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(Some(avatar), listed_comments[0].creator_avatar);
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_creator_matrix_user_id() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let matrix_user_id = "@mia:matrix.org".to_string();
+    let person_form = PersonInsertForm::builder()
+      .name("mia_matrix".into())
+      .public_key("pubkey".to_string())
+      .matrix_user_id(Some(matrix_user_id.clone()))
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let inserted_mia = Person::create(pool, &person_form).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("Ping me on matrix".into())
+      .creator_id(inserted_mia.id)
+      .post_id(data.inserted_post.id)
+      .build();
+    let mia_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let local_user = &data.inserted_local_user;
+    let list_matrix_user_id = || async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .post_id(Some(data.inserted_post.id))
+        .local_user(Some(local_user))
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|c| c.comment.id == mia_comment.id)
+        .unwrap()
+        .creator_matrix_user_id
+    };
+
+    // Hidden from anonymous viewers and non-followers, including the creator field itself
+    let anonymous = CommentView::read(pool, mia_comment.id, None).await.unwrap();
+    assert_eq!(None, anonymous.creator_matrix_user_id);
+    assert_eq!(None, anonymous.creator.matrix_user_id);
+    let non_follower = CommentView::read(pool, mia_comment.id, Some(data.inserted_person.id))
+      .await
+      .unwrap();
+    assert_eq!(None, non_follower.creator_matrix_user_id);
+    assert_eq!(None, list_matrix_user_id().await);
+
+    // The creator always sees their own
+    let own = CommentView::read(pool, mia_comment.id, Some(inserted_mia.id))
+      .await
+      .unwrap();
+    assert_eq!(Some(matrix_user_id.clone()), own.creator_matrix_user_id);
+
+    let follower_form = PersonFollowerForm {
+      person_id: inserted_mia.id,
+      follower_id: data.inserted_person.id,
+      pending: false,
+    };
+    PersonFollower::follow(pool, &follower_form).await.unwrap();
+
+    let follower = CommentView::read(pool, mia_comment.id, Some(data.inserted_person.id))
+      .await
+      .unwrap();
+    assert_eq!(
+      Some(matrix_user_id.clone()),
+      follower.creator_matrix_user_id
+    );
+    assert_eq!(
+      Some(matrix_user_id.clone()),
+      follower.creator.matrix_user_id
+    );
+    assert_eq!(Some(matrix_user_id), list_matrix_user_id().await);
+
+    Person::delete(pool, inserted_mia.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_edit_count() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    for new_content in ["Edited once", "Edited twice"] {
+      let form = CommentUpdateForm::builder()
+        .content(Some(new_content.into()))
+        .build();
+      Comment::update_with_history(pool, data.inserted_comment_2.id, &form)
+        .await
+        .unwrap();
+    }
+
+    let read_comment = CommentView::read(pool, data.inserted_comment_2.id, None)
+      .await
+      .unwrap();
+    assert_eq!(2, read_comment.counts.edit_count);
+
+    let comments = CommentQuery::builder()
+      .pool(pool)
+      .post_id(Some(data.inserted_post.id))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    for comment in comments {
+      let expected = if comment.comment.id == data.inserted_comment_2.id {
+        2
+      } else {
+        0
+      };
+      assert_eq!(expected, comment.counts.edit_count);
+    }
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_show_scores() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    for (person_id, score) in [(data.inserted_person.id, 1), (data.inserted_person_2.id, 1)] {
+      let comment_like_form = CommentLikeForm {
+        comment_id: data.inserted_comment_2.id,
+        post_id: data.inserted_post.id,
+        person_id,
+        score,
+      };
+      CommentLike::like(pool, &comment_like_form).await.unwrap();
+    }
+
+    let local_user = &data.inserted_local_user;
+    let read_comment_2 = |show_scores| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .post_id(Some(data.inserted_post.id))
+        .local_user(Some(local_user))
+        .show_scores(Some(show_scores))
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|c| c.comment.id == data.inserted_comment_2.id)
+        .unwrap()
+    };
+
+    let with_scores = read_comment_2(true).await;
+    assert_eq!(2, with_scores.counts.score);
+    assert_eq!(2, with_scores.counts.upvotes);
+    assert!(with_scores.counts.hot_rank > 0);
+
+    let blind = read_comment_2(false).await;
+    assert_eq!(0, blind.counts.score);
+    assert_eq!(0, blind.counts.upvotes);
+    assert_eq!(0, blind.counts.downvotes);
+    assert_eq!(0, blind.counts.hot_rank);
+    assert_eq!(Some(1), blind.my_vote);
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_age_days() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let post_form = PostInsertForm::builder()
+      .name("A post with old comments".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(data.inserted_community.id)
+      .build();
+    let inserted_post = Post::create(pool, &post_form).await.unwrap();
+
+    let mut comment_ids = vec![];
+    for days in [1, 7, 30] {
+      let comment_form = CommentInsertForm::builder()
+        .content(format!("A comment from {days} days ago"))
+        .creator_id(data.inserted_person.id)
+        .post_id(inserted_post.id)
+        .published(Some(naive_now() - Duration::days(days)))
+        .build();
+      let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+      comment_ids.push(inserted_comment.id);
+    }
+
+    let list_by_age = |min_age_days, max_age_days| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .post_id(Some(inserted_post.id))
+        .min_age_days(min_age_days)
+        .max_age_days(max_age_days)
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    let week_old = list_by_age(Some(5), Some(15)).await;
+    assert_eq!(1, week_old.len());
+    assert_eq!(comment_ids[1], week_old[0].comment.id);
+
+    assert_eq!(2, list_by_age(Some(5), None).await.len());
+    assert_eq!(2, list_by_age(None, Some(15)).await.len());
+
+    Post::delete(pool, inserted_post.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_min_post_score() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let mut comment_ids = vec![];
+    for score in [10, 2] {
+      let post_form = PostInsertForm::builder()
+        .name(format!("A post with score {score}"))
+        .creator_id(data.inserted_person.id)
+        .community_id(data.inserted_community.id)
+        .build();
+      let inserted_post = Post::create(pool, &post_form).await.unwrap();
+
+      // Set the score directly, instead of creating lots of voters
+      let conn = &mut get_conn(pool).await.unwrap();
+      diesel::update(post_aggregates::table)
+        .filter(post_aggregates::post_id.eq(inserted_post.id))
+        .set(post_aggregates::score.eq(score))
+        .execute(conn)
+        .await
+        .unwrap();
+
+      let comment_form = CommentInsertForm::builder()
+        .content(format!("A comment on a post with score {score}"))
+        .creator_id(data.inserted_person.id)
+        .post_id(inserted_post.id)
+        .build();
+      let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+      comment_ids.push((inserted_post.id, inserted_comment.id));
+    }
+
+    let best_comments = CommentQuery::builder()
+      .pool(pool)
+      .min_post_score(Some(5))
+      .build()
+      .list()
+      .await
+      .unwrap();
+
+    // The comments on the post from init_data, with score 0, are excluded as well
+    assert_eq!(1, best_comments.len());
+    assert_eq!(comment_ids[0].1, best_comments[0].comment.id);
+
+    for (post_id, _) in comment_ids {
+      Post::delete(pool, post_id).await.unwrap();
+    }
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_min_hot_rank() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // Make comment 2 old and unpopular, so that its hot rank drops to 0
+    let conn = &mut get_conn(pool).await.unwrap();
+    diesel::update(comment_aggregates::table)
+      .filter(comment_aggregates::comment_id.eq(data.inserted_comment_2.id))
+      .set((
+        comment_aggregates::score.eq(-5),
+        comment_aggregates::published.eq(naive_now() - chrono::Duration::days(365)),
+      ))
+      .execute(conn)
+      .await
+      .unwrap();
+    let old_counts = CommentAggregates::update_hot_rank(pool, data.inserted_comment_2.id)
+      .await
+      .unwrap();
+    assert_eq!(0, old_counts.hot_rank);
+
+    let hot_comments = CommentQuery::builder()
+      .pool(pool)
+      .post_id(Some(data.inserted_post.id))
+      .min_hot_rank(Some(1))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(5, hot_comments.len());
+    assert!(hot_comments
+      .iter()
+      .all(|c| c.comment.id != data.inserted_comment_2.id && c.counts.hot_rank >= 1));
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_sort_stable() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let list_hot_ids = || async {
+      CommentQuery::builder()
+        .pool(pool)
+        .post_id(Some(data.inserted_post.id))
+        .sort(Some(CommentSortType::Hot))
+        .sort_stable(Some(true))
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|c| c.comment.id)
+        .collect::<Vec<_>>()
+    };
+
+    // Tie all the comments of the post
+    let mut expected_ids = list_hot_ids().await;
+    let conn = &mut get_conn(pool).await.unwrap();
+    diesel::update(comment_aggregates::table)
+      .filter(comment_aggregates::comment_id.eq_any(&expected_ids))
+      .set(comment_aggregates::hot_rank.eq(100))
+      .execute(conn)
+      .await
+      .unwrap();
+
+    let first_ids = list_hot_ids().await;
+    expected_ids.sort_by_key(|id| std::cmp::Reverse(id.0));
+    assert_eq!(expected_ids, first_ids);
+    assert_eq!(first_ids, list_hot_ids().await);
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_show_removed() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    Comment::update(
+      pool,
+      data.inserted_comment_2.id,
+      &CommentUpdateForm::builder().removed(Some(true)).build(),
+    )
+    .await
+    .unwrap();
+
+    let list_removed = |local_user, show_removed| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .post_id(Some(data.inserted_post.id))
+        .local_user(local_user)
+        .show_removed(show_removed)
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|c| c.comment.removed)
+        .count()
+    };
+
+    // Regular users don't see removed comments by default
+    assert_eq!(0, list_removed(Some(&data.inserted_local_user), None).await);
+    assert_eq!(
+      1,
+      list_removed(Some(&data.inserted_local_user), Some(true)).await
+    );
+    // Logged out users get them as tombstones
+    assert_eq!(1, list_removed(None, None).await);
+
+    // Moderators of the community see them
+    let moderator_form = CommunityModeratorForm {
+      community_id: data.inserted_community.id,
+      person_id: data.inserted_person.id,
+    };
+    CommunityModerator::join(pool, &moderator_form)
+      .await
+      .unwrap();
+    assert_eq!(1, list_removed(Some(&data.inserted_local_user), None).await);
+
+    CommunityModerator::leave(pool, &moderator_form)
+      .await
+      .unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_has_reports() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let report_form = CommentReportForm {
+      creator_id: data.inserted_person_2.id,
+      comment_id: data.inserted_comment_2.id,
+      original_comment_text: data.inserted_comment_2.content.clone(),
+      reason: "spam".into(),
+    };
+    let report = CommentReport::report(pool, &report_form).await.unwrap();
+
+    let local_user = &data.inserted_local_user;
+    let list_reported = |unresolved, any| async move {
+      CommentQuery::builder()
+        .pool(pool)
+        .local_user(Some(local_user))
+        .has_unresolved_reports(Some(unresolved))
+        .has_any_reports(Some(any))
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|c| c.comment.id)
+        .collect::<Vec<CommentId>>()
+    };
+
+    // Timmy isn't a moderator yet, so the reports stay hidden
+    assert!(list_reported(true, false).await.is_empty());
+
+    let moderator_form = CommunityModeratorForm {
+      community_id: data.inserted_community.id,
+      person_id: data.inserted_person.id,
+    };
+    CommunityModerator::join(pool, &moderator_form)
+      .await
+      .unwrap();
+    assert_eq!(
+      vec![data.inserted_comment_2.id],
+      list_reported(true, false).await
+    );
+    assert_eq!(
+      vec![data.inserted_comment_2.id],
+      list_reported(false, true).await
+    );
+
+    // Resolved reports only show up in the any reports filter
+    CommentReport::resolve(pool, report.id, data.inserted_person.id)
+      .await
+      .unwrap();
+    assert!(list_reported(true, false).await.is_empty());
+    assert_eq!(
+      vec![data.inserted_comment_2.id],
+      list_reported(false, true).await
+    );
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_distinguished() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let set_distinguished = |distinguished| async move {
+      let form = CommentUpdateForm::builder()
+        .distinguished(Some(distinguished))
+        .build();
+      Comment::update(pool, data.inserted_comment_0.id, &form)
+        .await
+        .unwrap();
+      CommentView::read(pool, data.inserted_comment_0.id, None)
+        .await
+        .unwrap()
+        .comment
+        .distinguished
+    };
+
+    assert!(set_distinguished(true).await);
+    assert!(!set_distinguished(false).await);
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_creator_ban_expires() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let ban_form = CommunityPersonBanForm {
+      community_id: data.inserted_community.id,
+      person_id: data.inserted_person_2.id,
+      expires: Some(Some(naive_now() + Duration::days(3))),
+    };
+    let ban = CommunityPersonBan::ban(pool, &ban_form).await.unwrap();
+
+    let banned_comment = CommentView::read(pool, data.inserted_comment_1.id, None)
+      .await
+      .unwrap();
+    assert!(banned_comment.creator_banned_from_community);
+    assert!(ban.expires.is_some());
+    assert_eq!(ban.expires, banned_comment.creator_ban_expires);
+
+    // Comments of creators who aren't banned have no expiry
+    let comment = CommentView::read(pool, data.inserted_comment_0.id, None)
+      .await
+      .unwrap();
+    assert_eq!(None, comment.creator_ban_expires);
+
+    cleanup(data, pool).await;
+  }
+
   async fn cleanup(data: Data, pool: &DbPool) {
     CommentLike::remove(pool, data.inserted_person.id, data.inserted_comment_0.id)
       .await
@@ -806,8 +3014,12 @@ mod tests {
       .await
       .unwrap();
     CommentView {
+      creator_avatar: None,
+      creator_matrix_user_id: None,
       creator_banned_from_community: false,
+      creator_ban_expires: None,
       my_vote: None,
+      thread_participants: None,
       subscribed: SubscribedType::NotSubscribed,
       saved: false,
       creator_blocked: false,
@@ -908,6 +3120,7 @@ mod tests {
         published: agg.published,
         child_count: 5,
         hot_rank: 1728,
+        edit_count: 0,
       },
     }
   }
@@ -1,6 +1,13 @@
 use crate::structs::LocalUserView;
-use diesel::{result::Error, BoolExpressionMethods, ExpressionMethods, JoinOnDsl, QueryDsl};
-use diesel_async::RunQueryDsl;
+use diesel::{
+  result::Error,
+  BoolExpressionMethods,
+  ExpressionMethods,
+  JoinOnDsl,
+  OptionalExtension,
+  QueryDsl,
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use lemmy_db_schema::{
   aggregates::structs::PersonAggregates,
   newtypes::{LocalUserId, PersonId},
@@ -54,6 +61,24 @@ impl LocalUserView {
     })
   }
 
+  /// Reads the local user of the person, or None without a person or if the person isn't local.
+  /// Runs on the given connection, so that it can be part of a transaction.
+  pub async fn read_local_user_conn(
+    conn: &mut AsyncPgConnection,
+    person_id: Option<PersonId>,
+  ) -> Result<Option<LocalUser>, Error> {
+    let Some(person_id) = person_id else {
+      return Ok(None);
+    };
+    local_user::table
+      .inner_join(person::table)
+      .filter(person::id.eq(person_id))
+      .select(local_user::all_columns)
+      .first::<LocalUser>(conn)
+      .await
+      .optional()
+  }
+
   pub async fn read_from_name(pool: &DbPool, name: &str) -> Result<Self, Error> {
     let conn = &mut get_conn(pool).await?;
     let (local_user, person, counts) = local_user::table
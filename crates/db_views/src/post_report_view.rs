@@ -6,6 +6,7 @@ use diesel::{
   JoinOnDsl,
   NullableExpressionMethods,
   QueryDsl,
+  SelectableHelper,
 };
 use diesel_async::RunQueryDsl;
 use lemmy_db_schema::{
@@ -92,7 +93,7 @@ impl PostReportView {
       )
       .select((
         post_report::all_columns,
-        post::all_columns,
+        Post::as_select(),
         community::all_columns,
         person::all_columns,
         person_alias_1.fields(person::all_columns),
@@ -204,7 +205,7 @@ impl<'a> PostReportQuery<'a> {
       )
       .select((
         post_report::all_columns,
-        post::all_columns,
+        Post::as_select(),
         community::all_columns,
         person::all_columns,
         person_alias_1.fields(person::all_columns),
@@ -1,29 +1,38 @@
-use crate::structs::PostView;
+use crate::structs::{LocalUserView, PostView};
 use diesel::{
   debug_query,
-  dsl::{now, IntervalDsl},
-  pg::Pg,
+  dsl::{now, sql, AssumeNotNull, IntervalDsl},
+  expression::{is_aggregate, AppearsOnTable, Expression, SqlLiteral, ValidGrouping},
+  pg::{data_types::PgInterval, Pg},
+  query_builder::{AstPass, QueryFragment, QueryId},
   result::Error,
-  sql_function,
   sql_types,
   BoolExpressionMethods,
+  BoxableExpression,
   ExpressionMethods,
   JoinOnDsl,
   NullableExpressionMethods,
+  OptionalExtension,
   PgTextExpressionMethods,
   QueryDsl,
+  QueryResult,
+  SelectableExpression,
+  SelectableHelper,
 };
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use lemmy_db_schema::{
   aggregates::structs::PostAggregates,
-  newtypes::{CommunityId, LocalUserId, PersonId, PostId},
+  newtypes::{CommunityId, DbUrl, LocalUserId, PersonId, PostId},
   schema::{
     community,
     community_block,
     community_follower,
+    community_moderator,
     community_person_ban,
+    instance_block,
     local_user_language,
     person,
+    person_aggregates,
     person_block,
     person_post_aggregates,
     post,
@@ -33,6 +42,7 @@ use lemmy_db_schema::{
     post_saved,
   },
   source::{
+    actor_language::LocalUserLanguage,
     community::{Community, CommunityFollower, CommunityPersonBan},
     local_user::LocalUser,
     person::Person,
@@ -40,13 +50,127 @@ use lemmy_db_schema::{
     post::{Post, PostRead, PostSaved},
   },
   traits::JoinView,
-  utils::{fuzzy_search, get_conn, limit_and_offset, DbPool},
+  utils::{
+    functions::{hot_rank, lower},
+    fuzzy_search,
+    get_conn,
+    limit_and_offset,
+    DbPool,
+  },
   ListingType,
+  NsfwFilter,
+  PostType,
   SortType,
 };
+use std::collections::HashMap;
 use tracing::debug;
 use typed_builder::TypedBuilder;
 
+/// Matches lowercased urls which point directly to an image
+const IMAGE_URL_PATTERN: &str = "%.(jpg|jpeg|png|gif|webp)";
+
+/// Matches the posts of this type, for any query which includes the post table
+pub(crate) fn post_type_filter<QS>(
+  post_type: PostType,
+) -> Box<dyn BoxableExpression<QS, Pg, SqlType = sql_types::Bool>>
+where
+  post::url: SelectableExpression<QS>,
+  AssumeNotNull<post::url>: SelectableExpression<QS>,
+{
+  match post_type {
+    PostType::Link => Box::new(post::url.is_not_null()),
+    PostType::Text => Box::new(post::url.is_null()),
+    PostType::Image => Box::new(
+      post::url
+        .is_not_null()
+        .and(lower(post::url.assume_not_null()).similar_to(IMAGE_URL_PATTERN)),
+    ),
+  }
+}
+
+/// The order used to rank posts within their community, matching the sort type
+fn community_rank_sql(sort: SortType) -> &'static str {
+  let top = "post_aggregates.score DESC, post_aggregates.published DESC";
+  match sort {
+    SortType::Active => "post_aggregates.hot_rank_active DESC",
+    SortType::Hot | SortType::BestMatch => "post_aggregates.hot_rank DESC",
+    SortType::New => "post_aggregates.published DESC",
+    SortType::Old => "post_aggregates.published ASC",
+    SortType::NewComments => "post_aggregates.newest_comment_time DESC",
+    SortType::MostComments => "post_aggregates.comments DESC, post_aggregates.published DESC",
+    SortType::TopAll
+    | SortType::TopYear
+    | SortType::TopThreeYears
+    | SortType::TopMonth
+    | SortType::TopWeek
+    | SortType::TopDay
+    | SortType::TopHour
+    | SortType::TopSixHour
+    | SortType::TopTwelveHour => top,
+  }
+}
+
+/// The time window of the top sorts, None for the other sorts and TopAll
+fn top_sort_interval(sort: SortType) -> Option<PgInterval> {
+  match sort {
+    SortType::TopYear => Some(1.years()),
+    SortType::TopThreeYears => Some(3.years()),
+    SortType::TopMonth => Some(1.months()),
+    SortType::TopWeek => Some(1.weeks()),
+    SortType::TopDay => Some(1.days()),
+    SortType::TopHour => Some(1.hours()),
+    SortType::TopSixHour => Some(6.hours()),
+    SortType::TopTwelveHour => Some(12.hours()),
+    _ => None,
+  }
+}
+
+/// Keeps the rows whose id is among the first `limit` rows of their partition. The `ranked`
+/// subquery selects the id and its `ROW_NUMBER()` within the partition. It should carry the
+/// filters of the listing, so that rows which are filtered out don't take up any of the slots.
+pub(crate) struct TopNPerPartition<Q> {
+  id_column: &'static str,
+  ranked: Q,
+  limit: i32,
+}
+
+impl<Q> TopNPerPartition<Q> {
+  pub(crate) fn new(id_column: &'static str, ranked: Q, limit: i32) -> Self {
+    Self {
+      id_column,
+      ranked,
+      limit,
+    }
+  }
+}
+
+impl<Q> Expression for TopNPerPartition<Q> {
+  type SqlType = sql_types::Bool;
+}
+
+impl<Q, QS> AppearsOnTable<QS> for TopNPerPartition<Q> {}
+
+impl<Q> ValidGrouping<()> for TopNPerPartition<Q> {
+  type IsAggregate = is_aggregate::Never;
+}
+
+impl<Q> QueryId for TopNPerPartition<Q> {
+  type QueryId = ();
+  const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<Q: QueryFragment<Pg>> QueryFragment<Pg> for TopNPerPartition<Q> {
+  fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+    out.push_sql(self.id_column);
+    out.push_sql(" IN (SELECT ranked.id FROM (");
+    self.ranked.walk_ast(out.reborrow())?;
+    out.push_sql(") ranked (id, rn) WHERE ranked.rn <= ");
+    out.push_bind_param::<sql_types::Integer, _>(&self.limit)?;
+    out.push_sql(")");
+    Ok(())
+  }
+}
+
 type PostViewTuple = (
   Post,
   Person,
@@ -58,10 +182,28 @@ type PostViewTuple = (
   Option<PostRead>,
   Option<PersonBlock>,
   Option<i16>,
-  i64,
+  Option<i64>,
+  Vec<CommunityId>,
+  Option<i64>,
 );
 
-sql_function!(fn coalesce(x: sql_types::Nullable<sql_types::BigInt>, y: sql_types::BigInt) -> sql_types::BigInt);
+/// The other communities where the url of the post was shared, empty for posts without a url
+const RELATED_COMMUNITY_IDS_SQL: &str = "ARRAY(SELECT DISTINCT p.community_id FROM post p \
+  WHERE p.url = post.url AND p.id != post.id AND p.community_id != post.community_id \
+  AND p.deleted = false AND p.removed = false ORDER BY p.community_id)";
+
+/// The karma of the post creator. Only queried when requested, to skip the lookup on
+/// person_aggregates otherwise
+fn creator_karma_select(include: bool) -> SqlLiteral<sql_types::Nullable<sql_types::BigInt>> {
+  if include {
+    sql(
+      "(SELECT pa.post_score + pa.comment_score FROM person_aggregates pa \
+      WHERE pa.person_id = post.creator_id)",
+    )
+  } else {
+    sql("NULL")
+  }
+}
 
 impl PostView {
   pub async fn read(
@@ -71,7 +213,15 @@ impl PostView {
     is_mod_or_admin: Option<bool>,
   ) -> Result<Self, Error> {
     let conn = &mut get_conn(pool).await?;
+    Self::read_conn(conn, post_id, my_person_id, is_mod_or_admin).await
+  }
 
+  async fn read_conn(
+    conn: &mut AsyncPgConnection,
+    post_id: PostId,
+    my_person_id: Option<PersonId>,
+    is_mod_or_admin: Option<bool>,
+  ) -> Result<Self, Error> {
     // The left join below will return None in this case
     let person_id_join = my_person_id.unwrap_or(PersonId(-1));
     let mut query = post::table
@@ -129,7 +279,7 @@ impl PostView {
         ),
       )
       .select((
-        post::all_columns,
+        Post::as_select(),
         person::all_columns,
         community::all_columns,
         community_person_ban::all_columns.nullable(),
@@ -139,10 +289,9 @@ impl PostView {
         post_read::all_columns.nullable(),
         person_block::all_columns.nullable(),
         post_like::score.nullable(),
-        coalesce(
-          post_aggregates::comments.nullable() - person_post_aggregates::read_comments.nullable(),
-          post_aggregates::comments,
-        ),
+        person_post_aggregates::read_comments.nullable(),
+        sql::<sql_types::Array<sql_types::Integer>>(RELATED_COMMUNITY_IDS_SQL),
+        creator_karma_select(false),
       ))
       .into_boxed();
 
@@ -166,7 +315,9 @@ impl PostView {
       read,
       creator_blocked,
       post_like,
-      unread_comments,
+      read_comments,
+      related_community_ids,
+      creator_karma,
     ) = query.first::<PostViewTuple>(conn).await?;
 
     // If a person is given, then my_vote, if None, should be 0, not null
@@ -177,6 +328,14 @@ impl PostView {
       post_like
     };
 
+    // Same for read_comments, if the post was never read
+    let read_comments = if my_person_id.is_some() {
+      Some(read_comments.unwrap_or(0))
+    } else {
+      read_comments
+    };
+    let unread_comments = read_comments.map(|read_comments| counts.comments - read_comments);
+
     Ok(PostView {
       post,
       creator,
@@ -188,9 +347,87 @@ impl PostView {
       read: read.is_some(),
       creator_blocked: creator_blocked.is_some(),
       my_vote,
+      read_comments,
       unread_comments,
+      related_community_ids,
+      community_moderators: vec![],
+      creator_karma,
     })
   }
+
+  /// Reads the post together with up to `limit` other posts of the same url, hottest first.
+  pub async fn read_with_duplicates(
+    pool: &DbPool,
+    post_id: PostId,
+    my_person_id: Option<PersonId>,
+    limit: i64,
+  ) -> Result<(Self, Vec<Self>), Error> {
+    let conn = &mut get_conn(pool).await?;
+    conn
+      .build_transaction()
+      .run(|conn| {
+        Box::pin(async move {
+          let post_view = Self::read_conn(conn, post_id, my_person_id, None).await?;
+          let Some(url) = post_view.post.url.clone() else {
+            return Ok((post_view, vec![]));
+          };
+
+          // Only local users have personalized post listings
+          let local_user = LocalUserView::read_local_user_conn(conn, my_person_id).await?;
+          let duplicates = PostQuery::builder()
+            .pool(pool)
+            .url_search(Some(url.to_string()))
+            .exclude_post_ids(Some(vec![post_id]))
+            .local_user(local_user.as_ref())
+            .sort(Some(SortType::Hot))
+            .limit(Some(limit))
+            .build()
+            .list_conn(conn)
+            .await?;
+          Ok((post_view, duplicates))
+        }) as _
+      })
+      .await
+  }
+
+  /// Reads the newest post of the community which isn't deleted or removed, if it has any.
+  /// Featured posts and the viewer's listing filters have no influence on which post it is.
+  pub async fn read_latest_for_community_conn(
+    conn: &mut AsyncPgConnection,
+    community_id: CommunityId,
+    my_person_id: Option<PersonId>,
+  ) -> Result<Option<Self>, Error> {
+    let latest_post_id = post::table
+      .filter(post::community_id.eq(community_id))
+      .filter(post::deleted.eq(false))
+      .filter(post::removed.eq(false))
+      .order_by((post::published.desc(), post::id.desc()))
+      .select(post::id)
+      .first::<PostId>(conn)
+      .await
+      .optional()?;
+
+    match latest_post_id {
+      Some(post_id) => Self::read_conn(conn, post_id, my_person_id, None)
+        .await
+        .map(Some),
+      None => Ok(None),
+    }
+  }
+
+  /// The ids of all posts of the url, oldest first, so the comments of cross-posts can be loaded
+  /// together.
+  pub async fn get_cross_post_ids(pool: &DbPool, url: &DbUrl) -> Result<Vec<PostId>, Error> {
+    let conn = &mut get_conn(pool).await?;
+    post::table
+      .filter(post::url.eq(url.to_string()))
+      .filter(post::deleted.eq(false))
+      .filter(post::removed.eq(false))
+      .order_by(post::published.asc())
+      .select(post::id)
+      .load::<PostId>(conn)
+      .await
+  }
 }
 
 #[derive(TypedBuilder)]
@@ -201,233 +438,550 @@ pub struct PostQuery<'a> {
   listing_type: Option<ListingType>,
   sort: Option<SortType>,
   creator_id: Option<PersonId>,
+  /// Only returns content from these creators. Merged with creator_id if both are set, an empty
+  /// list without creator_id matches nothing
+  creator_ids: Option<Vec<PersonId>>,
+  /// Hides content from these creators, independent of the viewer's person blocks
+  exclude_person_ids: Option<Vec<PersonId>>,
+  /// Hides these posts, for example the ones which were already shown. An empty list does nothing
+  exclude_post_ids: Option<Vec<PostId>>,
+  /// If set, only returns posts by local (true) or remote (false) users
+  creator_local: Option<bool>,
+  /// Only returns posts by creators with at least this many posts, to find power users
+  creator_min_post_count: Option<i64>,
+  /// Only returns posts by creators with at most this many posts, to find potential spam
+  creator_max_post_count: Option<i64>,
   community_id: Option<CommunityId>,
+  /// Only returns posts from communities the local user follows. Requires local_user
+  followed_communities_only: Option<bool>,
+  /// Set with apply_language_filter
+  #[builder(setter(skip))]
+  language_filter_person_id: Option<PersonId>,
   local_user: Option<&'a LocalUser>,
+  /// Hides the posts of persons blocked by the local user, instead of only marking them
+  hide_blocked: Option<bool>,
   search_term: Option<String>,
+  /// Matches search_term against the indexed text search vector of the title and body, which
+  /// handles word stems, instead of a substring search. Required for the BestMatch sort
+  full_text_search: Option<bool>,
   url_search: Option<String>,
+  /// Only returns posts whose url contains this term, unlike search_term which only searches the
+  /// title and body
+  url_contains: Option<String>,
   saved_only: Option<bool>,
+  /// If set, only returns posts with (true) or without (false) a thumbnail
+  has_thumbnail: Option<bool>,
+  /// Locked posts are included unless this is false
+  show_locked: Option<bool>,
+  /// Only returns locked posts, for moderation views
+  locked_only: Option<bool>,
+  /// Posts featured in their community or on the site are included unless this is false
+  show_featured: Option<bool>,
+  /// Featured posts are listed before the others unless this is false, then they are sorted like
+  /// any other post
+  featured_first: Option<bool>,
+  post_type: Option<PostType>,
+  /// Overrides the show_nsfw setting of the local user
+  nsfw_filter: Option<NsfwFilter>,
+  /// Returns at most this many posts from each community, ranked by the sort type
+  limit_per_community: Option<i32>,
+  /// Only returns posts published after this time, independent of the sort type
+  published_after: Option<chrono::NaiveDateTime>,
+  /// Only returns posts published before this time, independent of the sort type
+  published_before: Option<chrono::NaiveDateTime>,
+  /// Keyset pagination for the New and Old sorts: only returns the posts which come after this
+  /// one, using the id to break ties between posts published at the same time. With
+  /// featured_first, the cursor also covers the featured flag. Other sorts return an error
+  after_post_id: Option<PostId>,
+  /// Recalculates the hot ranks of the posts matching the filters before listing them, in the same
+  /// transaction, in case the scheduled update is behind. This writes and locks the aggregates of
+  /// every matching post from the last week, so it's only done for the Hot and Active sorts
+  refresh_hot_ranks: Option<bool>,
+  /// Fills in the moderators of each post's community, with one extra query for the whole page
+  include_community_moderators: Option<bool>,
+  /// Fills in the karma of the post creators, ie the sum of their post and comment scores
+  with_creator_aggregates: Option<bool>,
+  /// Zeroes the score, upvotes and downvotes of the posts when false, for blind voting. The hot
+  /// ranks and the creator karma are derived from scores, so they are cleared too. The viewer's
+  /// own vote is still returned
+  show_scores: Option<bool>,
   /// Used to show deleted or removed posts for admins
   is_mod_or_admin: Option<bool>,
+  /// Set with moderator_view
+  #[builder(setter(skip))]
+  show_removed_in_moderated: bool,
   page: Option<i64>,
   limit: Option<i64>,
 }
 
 impl<'a> PostQuery<'a> {
+  /// Fills in the defaults of the moderator dashboard for the local user: the posts of the
+  /// moderated communities including locked ones, newest first. Options which were set on the
+  /// builder are kept. Removed and deleted posts are shown, but only in the communities which the
+  /// local user moderates, even if the listing type is overridden.
+  pub fn moderator_view(mut self) -> Self {
+    self.listing_type.get_or_insert(ListingType::ModeratedBy);
+    self.show_removed_in_moderated = true;
+    self.show_locked.get_or_insert(true);
+    self.sort.get_or_insert(SortType::New);
+    self
+  }
+
+  /// Only returns posts in the languages enabled by the local user of this person. Unlike passing
+  /// the local user, this doesn't affect votes, saves or blocks.
+  pub fn apply_language_filter(mut self, person_id: PersonId) -> Self {
+    self.language_filter_person_id = Some(person_id);
+    self
+  }
+
   pub async fn list(self) -> Result<Vec<PostView>, Error> {
     let conn = &mut get_conn(self.pool).await?;
+    self.list_conn(conn).await
+  }
+
+  /// Like list, but runs on the given connection, so that it can be part of a transaction.
+  pub async fn list_conn(self, conn: &mut AsyncPgConnection) -> Result<Vec<PostView>, Error> {
+    // Nothing can match an empty list of creators, so skip the query
+    if self.creator_id.is_none() && matches!(&self.creator_ids, Some(ids) if ids.is_empty()) {
+      return Ok(vec![]);
+    }
 
     // The left join below will return None in this case
+    let is_logged_in = self.local_user.is_some();
     let person_id_join = self.local_user.map(|l| l.person_id).unwrap_or(PersonId(-1));
     let local_user_id_join = self.local_user.map(|l| l.id).unwrap_or(LocalUserId(-1));
 
-    let mut query = post::table
-      .inner_join(person::table)
-      .inner_join(community::table)
-      .left_join(
-        community_person_ban::table.on(
-          post::community_id
-            .eq(community_person_ban::community_id)
-            .and(community_person_ban::person_id.eq(post::creator_id)),
-        ),
-      )
-      .inner_join(post_aggregates::table)
-      .left_join(
-        community_follower::table.on(
-          post::community_id
-            .eq(community_follower::community_id)
-            .and(community_follower::person_id.eq(person_id_join)),
-        ),
-      )
-      .left_join(
-        post_saved::table.on(
-          post::id
-            .eq(post_saved::post_id)
-            .and(post_saved::person_id.eq(person_id_join)),
-        ),
-      )
-      .left_join(
-        post_read::table.on(
-          post::id
-            .eq(post_read::post_id)
-            .and(post_read::person_id.eq(person_id_join)),
-        ),
-      )
-      .left_join(
-        person_block::table.on(
-          post::creator_id
-            .eq(person_block::target_id)
-            .and(person_block::person_id.eq(person_id_join)),
-        ),
-      )
-      .left_join(
-        community_block::table.on(
-          post::community_id
-            .eq(community_block::community_id)
-            .and(community_block::person_id.eq(person_id_join)),
-        ),
-      )
-      .left_join(
-        post_like::table.on(
-          post::id
-            .eq(post_like::post_id)
-            .and(post_like::person_id.eq(person_id_join)),
-        ),
-      )
-      .left_join(
-        person_post_aggregates::table.on(
-          post::id
-            .eq(person_post_aggregates::post_id)
-            .and(person_post_aggregates::person_id.eq(person_id_join)),
-        ),
-      )
-      .left_join(
-        local_user_language::table.on(
-          post::language_id
-            .eq(local_user_language::language_id)
-            .and(local_user_language::local_user_id.eq(local_user_id_join)),
-        ),
-      )
-      .select((
-        post::all_columns,
-        person::all_columns,
-        community::all_columns,
-        community_person_ban::all_columns.nullable(),
-        post_aggregates::all_columns,
-        community_follower::all_columns.nullable(),
-        post_saved::all_columns.nullable(),
-        post_read::all_columns.nullable(),
-        person_block::all_columns.nullable(),
-        post_like::score.nullable(),
-        coalesce(
-          post_aggregates::comments.nullable() - person_post_aggregates::read_comments.nullable(),
-          post_aggregates::comments,
-        ),
-      ))
-      .into_boxed();
-
-    // Hide deleted and removed for non-admins or mods
-    // TODO This eventually needs to show posts where you are the creator
-    if !self.is_mod_or_admin.unwrap_or(false) {
-      query = query
-        .filter(community::removed.eq(false))
-        .filter(community::deleted.eq(false))
-        .filter(post::removed.eq(false))
-        .filter(post::deleted.eq(false));
+    // Checked up front, the left joins would silently match nothing without a local user
+    if !is_logged_in && self.followed_communities_only.unwrap_or(false) {
+      return Err(Error::QueryBuilderError(
+        "followed_communities_only requires a local user".into(),
+      ));
     }
-
-    if self.community_id.is_none() {
-      query = query.then_order_by(post_aggregates::featured_local.desc());
-    } else if let Some(community_id) = self.community_id {
-      query = query
-        .filter(post::community_id.eq(community_id))
-        .then_order_by(post_aggregates::featured_community.desc());
+    if !is_logged_in && self.saved_only.unwrap_or(false) {
+      return Err(Error::QueryBuilderError(
+        "saved_only requires a local user".into(),
+      ));
     }
 
-    if let Some(creator_id) = self.creator_id {
-      query = query.filter(post::creator_id.eq(creator_id));
+    let sort = self.sort.unwrap_or(SortType::Hot);
+    if self.after_post_id.is_some() && !matches!(sort, SortType::New | SortType::Old) {
+      return Err(Error::QueryBuilderError(
+        "after_post_id requires the New or Old sort".into(),
+      ));
     }
 
-    if let Some(listing_type) = self.listing_type {
-      match listing_type {
-        ListingType::Subscribed => {
-          query = query.filter(community_follower::person_id.is_not_null())
-        }
-        ListingType::Local => {
-          query = query.filter(community::local.eq(true)).filter(
-            community::hidden
-              .eq(false)
-              .or(community_follower::person_id.eq(person_id_join)),
+    let full_text_search_term = self
+      .search_term
+      .clone()
+      .filter(|_| self.full_text_search.unwrap_or(false));
+
+    // Builds the joined query with all filters applied. Called a second time for features which
+    // need the filtered posts in a subquery, like limit_per_community
+    let filtered_query = || {
+      let mut query = post::table
+        .inner_join(person::table)
+        .inner_join(community::table)
+        .left_join(
+          community_person_ban::table.on(
+            post::community_id
+              .eq(community_person_ban::community_id)
+              .and(community_person_ban::person_id.eq(post::creator_id)),
+          ),
+        )
+        .inner_join(post_aggregates::table)
+        .left_join(
+          community_follower::table.on(
+            post::community_id
+              .eq(community_follower::community_id)
+              .and(community_follower::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          post_saved::table.on(
+            post::id
+              .eq(post_saved::post_id)
+              .and(post_saved::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          post_read::table.on(
+            post::id
+              .eq(post_read::post_id)
+              .and(post_read::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          person_block::table.on(
+            post::creator_id
+              .eq(person_block::target_id)
+              .and(person_block::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          community_block::table.on(
+            post::community_id
+              .eq(community_block::community_id)
+              .and(community_block::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          instance_block::table.on(
+            community::instance_id
+              .eq(instance_block::instance_id)
+              .and(instance_block::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          post_like::table.on(
+            post::id
+              .eq(post_like::post_id)
+              .and(post_like::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          person_post_aggregates::table.on(
+            post::id
+              .eq(person_post_aggregates::post_id)
+              .and(person_post_aggregates::person_id.eq(person_id_join)),
+          ),
+        )
+        .left_join(
+          local_user_language::table.on(
+            post::language_id
+              .eq(local_user_language::language_id)
+              .and(local_user_language::local_user_id.eq(local_user_id_join)),
+          ),
+        )
+        .select((
+          Post::as_select(),
+          person::all_columns,
+          community::all_columns,
+          community_person_ban::all_columns.nullable(),
+          post_aggregates::all_columns,
+          community_follower::all_columns.nullable(),
+          post_saved::all_columns.nullable(),
+          post_read::all_columns.nullable(),
+          person_block::all_columns.nullable(),
+          post_like::score.nullable(),
+          person_post_aggregates::read_comments.nullable(),
+          sql::<sql_types::Array<sql_types::Integer>>(RELATED_COMMUNITY_IDS_SQL),
+          creator_karma_select(self.with_creator_aggregates.unwrap_or(false)),
+        ))
+        .into_boxed();
+
+      // Hide deleted and removed for non-admins or mods
+      // TODO This eventually needs to show posts where you are the creator
+      if !self.is_mod_or_admin.unwrap_or(false) {
+        query = query
+          .filter(community::removed.eq(false))
+          .filter(community::deleted.eq(false));
+        if self.show_removed_in_moderated {
+          query = query.filter(
+            post::removed.eq(false).and(post::deleted.eq(false)).or(
+              post::community_id.eq_any(
+                community_moderator::table
+                  .select(community_moderator::community_id)
+                  .filter(community_moderator::person_id.eq(person_id_join)),
+              ),
+            ),
           );
+        } else {
+          query = query
+            .filter(post::removed.eq(false))
+            .filter(post::deleted.eq(false));
         }
-        ListingType::All => {
-          query = query.filter(
-            community::hidden
-              .eq(false)
-              .or(community_follower::person_id.eq(person_id_join)),
-          )
+      }
+
+      if let Some(community_id) = self.community_id {
+        query = query.filter(post::community_id.eq(community_id));
+      }
+
+      let mut creator_ids = self.creator_ids.clone().unwrap_or_default();
+      creator_ids.extend(self.creator_id);
+      if !creator_ids.is_empty() {
+        query = query.filter(post::creator_id.eq_any(creator_ids));
+      }
+
+      if let Some(exclude_person_ids) = &self.exclude_person_ids {
+        query = query.filter(post::creator_id.ne_all(exclude_person_ids.clone()));
+      }
+
+      if let Some(exclude_post_ids) = &self.exclude_post_ids {
+        query = query.filter(post::id.ne_all(exclude_post_ids.clone()));
+      }
+
+      if let Some(creator_local) = self.creator_local {
+        query = query.filter(person::local.eq(creator_local));
+      }
+
+      if let Some(creator_min_post_count) = self.creator_min_post_count {
+        query = query.filter(
+          post::creator_id.eq_any(
+            person_aggregates::table
+              .select(person_aggregates::person_id)
+              .filter(person_aggregates::post_count.ge(creator_min_post_count)),
+          ),
+        );
+      }
+
+      if let Some(person_id) = self.language_filter_person_id {
+        query =
+          query.filter(post::language_id.eq_any(LocalUserLanguage::enabled_for_person(person_id)));
+      }
+
+      if let Some(creator_max_post_count) = self.creator_max_post_count {
+        query = query.filter(
+          post::creator_id.eq_any(
+            person_aggregates::table
+              .select(person_aggregates::person_id)
+              .filter(person_aggregates::post_count.le(creator_max_post_count)),
+          ),
+        );
+      }
+
+      if self.followed_communities_only.unwrap_or(false) {
+        query = query.filter(community_follower::person_id.eq(person_id_join));
+      }
+
+      if let Some(listing_type) = self.listing_type {
+        match listing_type {
+          ListingType::Subscribed => {
+            query = query.filter(community_follower::person_id.eq(person_id_join))
+          }
+          ListingType::Local => {
+            query = query.filter(community::local.eq(true)).filter(
+              community::hidden
+                .eq(false)
+                .or(community_follower::person_id.eq(person_id_join)),
+            );
+          }
+          ListingType::All => {
+            query = query.filter(
+              community::hidden
+                .eq(false)
+                .or(community_follower::person_id.eq(person_id_join)),
+            )
+          }
+          ListingType::ModeratedBy => {
+            query = query.filter(
+              post::community_id.eq_any(
+                community_moderator::table
+                  .select(community_moderator::community_id)
+                  .filter(community_moderator::person_id.eq(person_id_join)),
+              ),
+            )
+          }
         }
       }
-    }
 
-    if let Some(url_search) = self.url_search {
-      query = query.filter(post::url.eq(url_search));
-    }
+      if let Some(url_search) = &self.url_search {
+        query = query.filter(post::url.eq(url_search.clone()));
+      }
 
-    if let Some(search_term) = self.search_term {
-      let searcher = fuzzy_search(&search_term);
-      query = query.filter(
-        post::name
-          .ilike(searcher.clone())
-          .or(post::body.ilike(searcher)),
-      );
-    }
+      if let Some(url_contains) = &self.url_contains {
+        query = query.filter(post::url.ilike(fuzzy_search(url_contains)));
+      }
 
-    if !self.local_user.map(|l| l.show_nsfw).unwrap_or(false) {
-      query = query
-        .filter(post::nsfw.eq(false))
-        .filter(community::nsfw.eq(false));
-    };
+      if let Some(has_thumbnail) = self.has_thumbnail {
+        query = if has_thumbnail {
+          query.filter(post::thumbnail_url.is_not_null())
+        } else {
+          query.filter(post::thumbnail_url.is_null())
+        };
+      }
+
+      if !self.show_locked.unwrap_or(true) {
+        query = query.filter(post::locked.eq(false));
+      }
+
+      if self.locked_only.unwrap_or(false) {
+        query = query.filter(post::locked.eq(true));
+      }
+
+      if !self.show_featured.unwrap_or(true) {
+        query = query
+          .filter(post::featured_community.eq(false))
+          .filter(post::featured_local.eq(false));
+      }
+
+      if let Some(post_type) = self.post_type {
+        query = query.filter(post_type_filter(post_type));
+      }
+
+      if let Some(published_after) = self.published_after {
+        query = query.filter(post::published.gt(published_after));
+      }
+
+      if let Some(published_before) = self.published_before {
+        query = query.filter(post::published.lt(published_before));
+      }
+
+      if let Some(search_term) = &full_text_search_term {
+        query = query.filter(
+          sql::<sql_types::Bool>("post.search_vector @@ plainto_tsquery('english', ")
+            .bind::<sql_types::Text, _>(search_term.clone())
+            .sql(")"),
+        );
+      } else if let Some(search_term) = &self.search_term {
+        let searcher = fuzzy_search(search_term);
+        query = query.filter(
+          post::name
+            .ilike(searcher.clone())
+            .or(post::body.ilike(searcher)),
+        );
+      }
+
+      let nsfw_filter = self.nsfw_filter.unwrap_or_else(|| {
+        if self.local_user.map(|l| l.show_nsfw).unwrap_or(false) {
+          NsfwFilter::ShowAll
+        } else {
+          NsfwFilter::SafeOnly
+        }
+      });
+      match nsfw_filter {
+        NsfwFilter::ShowAll => {}
+        NsfwFilter::SafeOnly => {
+          query = query
+            .filter(post::nsfw.eq(false))
+            .filter(community::nsfw.eq(false));
+        }
+        NsfwFilter::NsfwOnly => {
+          query = query.filter(post::nsfw.eq(true).or(community::nsfw.eq(true)));
+        }
+      };
+
+      if !self.local_user.map(|l| l.show_bot_accounts).unwrap_or(true) {
+        query = query.filter(person::bot_account.eq(false));
+      };
+
+      if self.saved_only.unwrap_or(false) {
+        query = query.filter(post_saved::post_id.is_not_null());
+      }
+      // Only hide the read posts, if the saved_only is false. Otherwise ppl with the hide_read
+      // setting wont be able to see saved posts.
+      else if !self.local_user.map(|l| l.show_read_posts).unwrap_or(true) {
+        query = query.filter(post_read::post_id.is_null());
+      }
+
+      if self.local_user.is_some() {
+        // Filter out the rows with missing languages
+        query = query.filter(local_user_language::language_id.is_not_null());
+
+        // Don't show blocked communities or instances
+        query = query.filter(community_block::person_id.is_null());
+        query = query.filter(instance_block::person_id.is_null());
+
+        // Posts of blocked persons are only marked with creator_blocked, unless hidden explicitly
+        if self.hide_blocked.unwrap_or(false) {
+          query = query.filter(person_block::person_id.is_null());
+        }
+      }
 
-    if !self.local_user.map(|l| l.show_bot_accounts).unwrap_or(true) {
-      query = query.filter(person::bot_account.eq(false));
+      // The top sorts only consider the posts of their time window
+      if let Some(interval) = top_sort_interval(sort) {
+        query = query.filter(post_aggregates::published.gt(now - interval));
+      }
+
+      query
     };
 
-    if self.saved_only.unwrap_or(false) {
-      query = query.filter(post_saved::post_id.is_not_null());
-    }
-    // Only hide the read posts, if the saved_only is false. Otherwise ppl with the hide_read
-    // setting wont be able to see saved posts.
-    else if !self.local_user.map(|l| l.show_read_posts).unwrap_or(true) {
-      query = query.filter(post_read::post_id.is_null());
+    let mut query = filtered_query();
+
+    // All posts matching the filters are refreshed, so that the fresh ranks decide which posts
+    // make it onto the page. Like the scheduled update, only the last week is considered
+    let refresh_candidates = (self.refresh_hot_ranks.unwrap_or(false)
+      && matches!(sort, SortType::Hot | SortType::Active))
+    .then(|| {
+      filtered_query()
+        .filter(post_aggregates::published.gt(now - 1.weeks()))
+        .select(post::id)
+    });
+
+    if let Some(limit_per_community) = self.limit_per_community {
+      // Window functions aren't supported by diesel, so rank the filtered posts in a subquery.
+      // This way posts which are hidden anyway don't take up any of the slots
+      let ranked = filtered_query().select((
+        post::id,
+        sql::<sql_types::BigInt>(&format!(
+          "ROW_NUMBER() OVER (PARTITION BY post.community_id ORDER BY {})",
+          community_rank_sql(sort)
+        )),
+      ));
+      query = query.filter(TopNPerPartition::new(
+        "post.id",
+        ranked,
+        limit_per_community,
+      ));
     }
 
-    if self.local_user.is_some() {
-      // Filter out the rows with missing languages
-      query = query.filter(local_user_language::language_id.is_not_null());
+    let featured_first = self.featured_first.unwrap_or(true);
+    let featured_column = if self.community_id.is_some() {
+      "featured_community"
+    } else {
+      "featured_local"
+    };
+    if let Some(after_post_id) = self.after_post_id {
+      // Follows the ordering below: featured posts first if enabled, then by published and id
+      let op = if sort == SortType::Old { ">" } else { "<" };
+      let published_after = format!(
+        "(post_aggregates.published, post_aggregates.post_id) {op} (c.published, c.post_id)"
+      );
+      let after = if featured_first {
+        format!(
+          "post_aggregates.{featured_column} < c.{featured_column} \
+           OR (post_aggregates.{featured_column} = c.{featured_column} AND {published_after})"
+        )
+      } else {
+        published_after
+      };
+      query = query.filter(
+        sql::<sql_types::Bool>("EXISTS (SELECT 1 FROM post_aggregates c WHERE c.post_id = ")
+          .bind::<sql_types::Integer, _>(after_post_id)
+          .sql(&format!(" AND ({after}))")),
+      );
+    }
 
-      // Don't show blocked communities or persons
-      query = query.filter(community_block::person_id.is_null());
-      query = query.filter(person_block::person_id.is_null());
+    if featured_first {
+      query = if self.community_id.is_some() {
+        query.then_order_by(post_aggregates::featured_community.desc())
+      } else {
+        query.then_order_by(post_aggregates::featured_local.desc())
+      };
     }
 
-    query = match self.sort.unwrap_or(SortType::Hot) {
+    query = match sort {
       SortType::Active => query.then_order_by(post_aggregates::hot_rank_active.desc()),
       SortType::Hot => query.then_order_by(post_aggregates::hot_rank.desc()),
-      SortType::New => query.then_order_by(post_aggregates::published.desc()),
-      SortType::Old => query.then_order_by(post_aggregates::published.asc()),
+      SortType::BestMatch => match full_text_search_term {
+        Some(search_term) => query
+          .then_order_by(
+            sql::<sql_types::Float>("ts_rank(post.search_vector, plainto_tsquery('english', ")
+              .bind::<sql_types::Text, _>(search_term)
+              .sql("))")
+              .desc(),
+          )
+          .then_order_by(post_aggregates::published.desc()),
+        None => query.then_order_by(post_aggregates::hot_rank.desc()),
+      },
+      SortType::New => query
+        .then_order_by(post_aggregates::published.desc())
+        .then_order_by(post_aggregates::post_id.desc()),
+      SortType::Old => query
+        .then_order_by(post_aggregates::published.asc())
+        .then_order_by(post_aggregates::post_id.asc()),
       SortType::NewComments => query.then_order_by(post_aggregates::newest_comment_time.desc()),
       SortType::MostComments => query
         .then_order_by(post_aggregates::comments.desc())
         .then_order_by(post_aggregates::published.desc()),
-      SortType::TopAll => query
-        .then_order_by(post_aggregates::score.desc())
-        .then_order_by(post_aggregates::published.desc()),
-      SortType::TopYear => query
-        .filter(post_aggregates::published.gt(now - 1.years()))
-        .then_order_by(post_aggregates::score.desc())
-        .then_order_by(post_aggregates::published.desc()),
-      SortType::TopMonth => query
-        .filter(post_aggregates::published.gt(now - 1.months()))
-        .then_order_by(post_aggregates::score.desc())
-        .then_order_by(post_aggregates::published.desc()),
-      SortType::TopWeek => query
-        .filter(post_aggregates::published.gt(now - 1.weeks()))
-        .then_order_by(post_aggregates::score.desc())
-        .then_order_by(post_aggregates::published.desc()),
-      SortType::TopDay => query
-        .filter(post_aggregates::published.gt(now - 1.days()))
-        .then_order_by(post_aggregates::score.desc())
-        .then_order_by(post_aggregates::published.desc()),
-      SortType::TopHour => query
-        .filter(post_aggregates::published.gt(now - 1.hours()))
-        .then_order_by(post_aggregates::score.desc())
-        .then_order_by(post_aggregates::published.desc()),
-      SortType::TopSixHour => query
-        .filter(post_aggregates::published.gt(now - 6.hours()))
-        .then_order_by(post_aggregates::score.desc())
-        .then_order_by(post_aggregates::published.desc()),
-      SortType::TopTwelveHour => query
-        .filter(post_aggregates::published.gt(now - 12.hours()))
+      SortType::TopAll
+      | SortType::TopYear
+      | SortType::TopThreeYears
+      | SortType::TopMonth
+      | SortType::TopWeek
+      | SortType::TopDay
+      | SortType::TopHour
+      | SortType::TopSixHour
+      | SortType::TopTwelveHour => query
         .then_order_by(post_aggregates::score.desc())
         .then_order_by(post_aggregates::published.desc()),
     };
@@ -438,15 +992,87 @@ impl<'a> PostQuery<'a> {
 
     debug!("Post View Query: {:?}", debug_query::<Pg, _>(&query));
 
-    let res = query.load::<PostViewTuple>(conn).await?;
+    let res = if let Some(candidates) = refresh_candidates {
+      conn
+        .build_transaction()
+        .run(|conn| {
+          Box::pin(async move {
+            diesel::update(post_aggregates::table)
+              .filter(post_aggregates::post_id.eq_any(candidates))
+              .set((
+                post_aggregates::hot_rank
+                  .eq(hot_rank(post_aggregates::score, post_aggregates::published)),
+                post_aggregates::hot_rank_active.eq(hot_rank(
+                  post_aggregates::score,
+                  post_aggregates::newest_comment_time_necro,
+                )),
+              ))
+              .execute(conn)
+              .await?;
+            query.load::<PostViewTuple>(conn).await
+          }) as _
+        })
+        .await?
+    } else {
+      query.load::<PostViewTuple>(conn).await?
+    };
+
+    let show_scores = self.show_scores.unwrap_or(true);
+    let mut community_moderators = HashMap::<CommunityId, Vec<Person>>::new();
+    if self.include_community_moderators.unwrap_or(false) && !res.is_empty() {
+      let mut community_ids = res.iter().map(|p| p.2.id).collect::<Vec<_>>();
+      community_ids.sort_by_key(|id| id.0);
+      community_ids.dedup();
+      let moderators = community_moderator::table
+        .inner_join(person::table)
+        .filter(community_moderator::community_id.eq_any(community_ids))
+        .order_by(community_moderator::published)
+        .select((community_moderator::community_id, person::all_columns))
+        .load::<(CommunityId, Person)>(conn)
+        .await?;
+      for (community_id, moderator) in moderators {
+        community_moderators
+          .entry(community_id)
+          .or_default()
+          .push(moderator);
+      }
+    }
 
-    Ok(res.into_iter().map(PostView::from_tuple).collect())
+    Ok(
+      res
+        .into_iter()
+        .map(PostView::from_tuple)
+        .map(|mut post_view| {
+          if let Some(moderators) = community_moderators.get(&post_view.community.id) {
+            post_view.community_moderators = moderators.clone();
+          }
+          if !show_scores {
+            post_view.counts.score = 0;
+            post_view.counts.upvotes = 0;
+            post_view.counts.downvotes = 0;
+            post_view.counts.hot_rank = 0;
+            post_view.counts.hot_rank_active = 0;
+            post_view.creator_karma = None;
+          }
+          // Same as in read, logged in users get 0 instead of null for posts they didn't vote on
+          if is_logged_in && post_view.my_vote.is_none() {
+            post_view.my_vote = Some(0);
+          }
+          if is_logged_in && post_view.read_comments.is_none() {
+            post_view.read_comments = Some(0);
+            post_view.unread_comments = Some(post_view.counts.comments);
+          }
+          post_view
+        })
+        .collect(),
+    )
   }
 }
 
 impl JoinView for PostView {
   type JoinTuple = PostViewTuple;
   fn from_tuple(a: Self::JoinTuple) -> Self {
+    let unread_comments = a.10.map(|read_comments| a.4.comments - read_comments);
     Self {
       post: a.0,
       creator: a.1,
@@ -458,7 +1084,11 @@ impl JoinView for PostView {
       read: a.7.is_some(),
       creator_blocked: a.8.is_some(),
       my_vote: a.9,
-      unread_comments: a.10,
+      read_comments: a.10,
+      unread_comments,
+      related_community_ids: a.11,
+      community_moderators: vec![],
+      creator_karma: a.12,
     }
   }
 }
@@ -466,27 +1096,52 @@ impl JoinView for PostView {
 #[cfg(test)]
 mod tests {
   use crate::post_view::{PostQuery, PostView};
+  use chrono::Duration;
+  use diesel::{result::Error, ExpressionMethods, QueryDsl};
+  use diesel_async::RunQueryDsl;
   use lemmy_db_schema::{
-    aggregates::structs::PostAggregates,
+    aggregates::structs::{PersonPostAggregates, PersonPostAggregatesForm, PostAggregates},
     impls::actor_language::UNDETERMINED_ID,
-    newtypes::LanguageId,
+    newtypes::{LanguageId, PostId},
+    schema::{post, post_aggregates},
     source::{
       actor_language::LocalUserLanguage,
-      community::{Community, CommunityInsertForm},
+      comment::{Comment, CommentInsertForm, CommentLike, CommentLikeForm},
+      community::{
+        Community,
+        CommunityFollower,
+        CommunityFollowerForm,
+        CommunityInsertForm,
+        CommunityModerator,
+        CommunityModeratorForm,
+      },
       community_block::{CommunityBlock, CommunityBlockForm},
       instance::Instance,
+      instance_block::{InstanceBlock, InstanceBlockForm},
       language::Language,
       local_user::{LocalUser, LocalUserInsertForm, LocalUserUpdateForm},
       person::{Person, PersonInsertForm},
       person_block::{PersonBlock, PersonBlockForm},
-      post::{Post, PostInsertForm, PostLike, PostLikeForm, PostUpdateForm},
+      post::{
+        Post,
+        PostInsertForm,
+        PostLike,
+        PostLikeForm,
+        PostSaved,
+        PostSavedForm,
+        PostUpdateForm,
+      },
     },
-    traits::{Blockable, Crud, Likeable},
-    utils::{build_db_pool_for_tests, DbPool},
+    traits::{Blockable, Crud, Followable, Joinable, Likeable, Saveable},
+    utils::{build_db_pool_for_tests, get_conn, naive_now, DbPool, FETCH_LIMIT_MAX},
+    ListingType,
+    NsfwFilter,
+    PostType,
     SortType,
     SubscribedType,
   };
   use serial_test::serial;
+  use url::Url;
 
   struct Data {
     inserted_instance: Instance,
@@ -611,6 +1266,7 @@ mod tests {
       .sort(Some(SortType::New))
       .community_id(Some(data.inserted_community.id))
       .local_user(Some(&inserted_local_user))
+      .hide_blocked(Some(true))
       .build()
       .list()
       .await
@@ -626,12 +1282,14 @@ mod tests {
     .unwrap();
 
     let mut expected_post_listing_with_user = expected_post_view(&data, pool).await;
+    expected_post_listing_with_user.read_comments = Some(0);
+    expected_post_listing_with_user.unread_comments = Some(0);
+    expected_post_listing_with_user.my_vote = Some(0);
 
     // Should be only one person, IE the bot post, and blocked should be missing
     assert_eq!(1, read_post_listing.len());
 
     assert_eq!(expected_post_listing_with_user, read_post_listing[0]);
-    expected_post_listing_with_user.my_vote = Some(0);
     assert_eq!(
       expected_post_listing_with_user,
       post_listing_single_with_person
@@ -650,6 +1308,7 @@ mod tests {
       .sort(Some(SortType::New))
       .community_id(Some(data.inserted_community.id))
       .local_user(Some(&inserted_local_user))
+      .hide_blocked(Some(true))
       .build()
       .list()
       .await
@@ -729,21 +1388,60 @@ mod tests {
 
   #[tokio::test]
   #[serial]
-  async fn post_listing_like() {
+  async fn post_listing_block_instance() {
     let pool = &build_db_pool_for_tests().await;
     let data = init_data(pool).await;
 
-    let post_like_form = PostLikeForm {
-      post_id: data.inserted_post.id,
+    let instance_block = InstanceBlockForm {
       person_id: data.inserted_person.id,
-      score: 1,
+      instance_id: data.inserted_community.instance_id,
     };
+    InstanceBlock::block(pool, &instance_block).await.unwrap();
 
-    let inserted_post_like = PostLike::like(pool, &post_like_form).await.unwrap();
-
-    let expected_post_like = PostLike {
-      id: inserted_post_like.id,
-      post_id: data.inserted_post.id,
+    let read_post_listings_with_person_after_block = PostQuery::builder()
+      .pool(pool)
+      .sort(Some(SortType::New))
+      .community_id(Some(data.inserted_community.id))
+      .local_user(Some(&data.inserted_local_user))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    // Should be 0 posts after the instance block
+    assert_eq!(0, read_post_listings_with_person_after_block.len());
+
+    // Logged out users are unaffected
+    let read_post_listings_no_person = PostQuery::builder()
+      .pool(pool)
+      .sort(Some(SortType::New))
+      .community_id(Some(data.inserted_community.id))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(3, read_post_listings_no_person.len());
+
+    InstanceBlock::unblock(pool, &instance_block).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listing_like() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let post_like_form = PostLikeForm {
+      post_id: data.inserted_post.id,
+      person_id: data.inserted_person.id,
+      score: 1,
+    };
+
+    let inserted_post_like = PostLike::like(pool, &post_like_form).await.unwrap();
+
+    let expected_post_like = PostLike {
+      id: inserted_post_like.id,
+      post_id: data.inserted_post.id,
       person_id: data.inserted_person.id,
       published: inserted_post_like.published,
       score: 1,
@@ -761,6 +1459,8 @@ mod tests {
 
     let mut expected_post_with_upvote = expected_post_view(&data, pool).await;
     expected_post_with_upvote.my_vote = Some(1);
+    expected_post_with_upvote.read_comments = Some(0);
+    expected_post_with_upvote.unread_comments = Some(0);
     expected_post_with_upvote.counts.score = 1;
     expected_post_with_upvote.counts.upvotes = 1;
     assert_eq!(expected_post_with_upvote, post_listing_single_with_person);
@@ -778,6 +1478,7 @@ mod tests {
       .sort(Some(SortType::New))
       .community_id(Some(data.inserted_community.id))
       .local_user(Some(&inserted_local_user))
+      .hide_blocked(Some(true))
       .build()
       .list()
       .await
@@ -793,6 +1494,64 @@ mod tests {
     cleanup(data, pool).await;
   }
 
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_apply_language_filter() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let community_form = CommunityInsertForm::builder()
+      .name("language_filter".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let community = Community::create(pool, &community_form).await.unwrap();
+
+    let mut language_ids = vec![];
+    for code in ["en", "de"] {
+      let language_id = Language::read_id_from_code(pool, Some(code))
+        .await
+        .unwrap()
+        .unwrap();
+      let post_form = PostInsertForm::builder()
+        .name(format!("A post in {code}"))
+        .creator_id(data.inserted_person.id)
+        .community_id(community.id)
+        .language_id(Some(language_id))
+        .build();
+      Post::create(pool, &post_form).await.unwrap();
+      language_ids.push(language_id);
+    }
+    LocalUserLanguage::update(pool, vec![language_ids[0]], data.inserted_local_user.id)
+      .await
+      .unwrap();
+
+    let filtered = PostQuery::builder()
+      .pool(pool)
+      .community_id(Some(community.id))
+      .build()
+      .apply_language_filter(data.inserted_person.id)
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(1, filtered.len());
+    assert_eq!(language_ids[0], filtered[0].post.language_id);
+
+    // Without the filter both posts are returned
+    let unfiltered = PostQuery::builder()
+      .pool(pool)
+      .community_id(Some(community.id))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(2, unfiltered.len());
+
+    Community::delete(pool, community.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
   #[tokio::test]
   #[serial]
   async fn post_listing_person_language() {
@@ -816,6 +1575,7 @@ mod tests {
       .pool(pool)
       .sort(Some(SortType::New))
       .local_user(Some(&data.inserted_local_user))
+      .hide_blocked(Some(true))
       .build()
       .list()
       .await
@@ -836,6 +1596,7 @@ mod tests {
       .pool(pool)
       .sort(Some(SortType::New))
       .local_user(Some(&data.inserted_local_user))
+      .hide_blocked(Some(true))
       .build()
       .list()
       .await
@@ -858,6 +1619,7 @@ mod tests {
       .pool(pool)
       .sort(Some(SortType::New))
       .local_user(Some(&data.inserted_local_user))
+      .hide_blocked(Some(true))
       .build()
       .list()
       .await
@@ -894,6 +1656,7 @@ mod tests {
       .pool(pool)
       .sort(Some(SortType::New))
       .local_user(Some(&data.inserted_local_user))
+      .hide_blocked(Some(true))
       .is_mod_or_admin(Some(false))
       .build()
       .list()
@@ -907,6 +1670,7 @@ mod tests {
       .pool(pool)
       .sort(Some(SortType::New))
       .local_user(Some(&data.inserted_local_user))
+      .hide_blocked(Some(true))
       .is_mod_or_admin(Some(true))
       .build()
       .list()
@@ -918,6 +1682,1505 @@ mod tests {
     cleanup(data, pool).await;
   }
 
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_locked() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    Post::update(
+      pool,
+      data.inserted_post.id,
+      &PostUpdateForm::builder().locked(Some(true)).build(),
+    )
+    .await
+    .unwrap();
+
+    let list_locked = |show_locked, locked_only| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .show_locked(Some(show_locked))
+        .locked_only(Some(locked_only))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    assert_eq!(3, list_locked(true, false).await.len());
+
+    let unlocked = list_locked(false, false).await;
+    assert_eq!(2, unlocked.len());
+    assert!(unlocked.iter().all(|p| !p.post.locked));
+
+    let locked = list_locked(true, true).await;
+    assert_eq!(1, locked.len());
+    assert_eq!(data.inserted_post.id, locked[0].post.id);
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_show_featured() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    Post::update(
+      pool,
+      data.inserted_post.id,
+      &PostUpdateForm::builder()
+        .featured_community(Some(true))
+        .build(),
+    )
+    .await
+    .unwrap();
+
+    let list_featured = |show_featured| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .sort(Some(SortType::Hot))
+        .show_featured(Some(show_featured))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    let with_featured = list_featured(true).await;
+    assert_eq!(3, with_featured.len());
+    assert_eq!(data.inserted_post.id, with_featured[0].post.id);
+
+    let without_featured = list_featured(false).await;
+    assert_eq!(2, without_featured.len());
+    assert!(without_featured
+      .iter()
+      .all(|p| p.post.id != data.inserted_post.id));
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_after_post_id() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let community_form = CommunityInsertForm::builder()
+      .name("keyset_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let community = Community::create(pool, &community_form).await.unwrap();
+    // All posts share the same timestamp, so only the id can order them
+    let published = chrono::Utc::now().naive_utc();
+    let mut post_ids = vec![];
+    for i in 0..5 {
+      let post_form = PostInsertForm::builder()
+        .name(format!("keyset post {i}"))
+        .creator_id(data.inserted_person.id)
+        .community_id(community.id)
+        .published(Some(published))
+        .build();
+      post_ids.push(Post::create(pool, &post_form).await.unwrap().id);
+    }
+    // A featured post comes first, the cursor must not skip the others after it
+    let featured_form = PostUpdateForm::builder()
+      .featured_community(Some(true))
+      .build();
+    Post::update(pool, post_ids[1], &featured_form)
+      .await
+      .unwrap();
+
+    let list_page = |after_post_id, featured_first| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(community.id))
+        .sort(Some(SortType::New))
+        .featured_first(Some(featured_first))
+        .after_post_id(after_post_id)
+        .limit(Some(2))
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| p.post.id)
+        .collect::<Vec<PostId>>()
+    };
+    let list_all_pages = |featured_first| async move {
+      let mut pages = vec![];
+      let mut cursor = None;
+      loop {
+        let page = list_page(cursor, featured_first).await;
+        let Some(last) = page.last() else {
+          break;
+        };
+        cursor = Some(*last);
+        pages.extend(page);
+      }
+      pages
+    };
+
+    // Without featured_first, the featured post is sorted like the others
+    let mut newest_first = post_ids.clone();
+    newest_first.reverse();
+    assert_eq!(newest_first, list_all_pages(false).await);
+
+    // Every post is listed exactly once, the featured one first, then newest first
+    let featured_id = post_ids.remove(1);
+    post_ids.reverse();
+    post_ids.insert(0, featured_id);
+    assert_eq!(post_ids, list_all_pages(true).await);
+
+    let hot_sort = PostQuery::builder()
+      .pool(pool)
+      .community_id(Some(community.id))
+      .sort(Some(SortType::Hot))
+      .after_post_id(Some(featured_id))
+      .build()
+      .list()
+      .await;
+    assert!(matches!(
+      hot_sort,
+      Err(Error::QueryBuilderError(e)) if e.to_string() == "after_post_id requires the New or Old sort"
+    ));
+
+    Community::delete(pool, community.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_has_thumbnail() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let thumbnail_url: Url = Url::parse("https://example.com/thumbnail.png").unwrap();
+    Post::update(
+      pool,
+      data.inserted_post.id,
+      &PostUpdateForm::builder()
+        .thumbnail_url(Some(Some(thumbnail_url.into())))
+        .build(),
+    )
+    .await
+    .unwrap();
+
+    let with_thumbnail = PostQuery::builder()
+      .pool(pool)
+      .community_id(Some(data.inserted_community.id))
+      .has_thumbnail(Some(true))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(1, with_thumbnail.len());
+    assert_eq!(data.inserted_post.id, with_thumbnail[0].post.id);
+
+    let without_thumbnail = PostQuery::builder()
+      .pool(pool)
+      .community_id(Some(data.inserted_community.id))
+      .has_thumbnail(Some(false))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(2, without_thumbnail.len());
+    assert!(without_thumbnail
+      .iter()
+      .all(|p| p.post.thumbnail_url.is_none()));
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_published_range() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // The posts from init_data are all published now
+    let now = chrono::Utc::now().naive_utc();
+    let mut post_ids = vec![];
+    for (name, days_ago) in [("old post", 20), ("middle post", 5)] {
+      let post_form = PostInsertForm::builder()
+        .name(name.into())
+        .creator_id(data.inserted_person.id)
+        .community_id(data.inserted_community.id)
+        .published(Some(now - chrono::Duration::days(days_ago)))
+        .build();
+      post_ids.push(Post::create(pool, &post_form).await.unwrap().id);
+    }
+
+    for sort in [
+      SortType::Hot,
+      SortType::New,
+      SortType::Old,
+      SortType::TopAll,
+    ] {
+      let in_range = PostQuery::builder()
+        .pool(pool)
+        .sort(Some(sort))
+        .community_id(Some(data.inserted_community.id))
+        .published_after(Some(now - chrono::Duration::days(10)))
+        .published_before(Some(now - chrono::Duration::days(1)))
+        .build()
+        .list()
+        .await
+        .unwrap();
+      assert_eq!(1, in_range.len());
+      assert_eq!(post_ids[1], in_range[0].post.id);
+    }
+
+    for post_id in post_ids {
+      Post::delete(pool, post_id).await.unwrap();
+    }
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_top_three_years() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let now = chrono::Utc::now().naive_utc();
+    let mut post_ids = vec![];
+    for (name, years_ago) in [("two years old post", 2), ("four years old post", 4)] {
+      let post_form = PostInsertForm::builder()
+        .name(name.into())
+        .creator_id(data.inserted_person.id)
+        .community_id(data.inserted_community.id)
+        .published(Some(now - chrono::Duration::days(365 * years_ago)))
+        .build();
+      post_ids.push(Post::create(pool, &post_form).await.unwrap().id);
+    }
+
+    let post_listings = PostQuery::builder()
+      .pool(pool)
+      .sort(Some(SortType::TopThreeYears))
+      .community_id(Some(data.inserted_community.id))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    let listed_ids = post_listings
+      .iter()
+      .map(|p| p.post.id)
+      .collect::<Vec<PostId>>();
+    assert!(listed_ids.contains(&post_ids[0]));
+    assert!(!listed_ids.contains(&post_ids[1]));
+
+    for post_id in post_ids {
+      Post::delete(pool, post_id).await.unwrap();
+    }
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_post_type() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // The posts from init_data are all text posts
+    let mut post_ids = vec![];
+    for url in [
+      "https://example.com/article",
+      "https://example.com/picture.JPG",
+    ] {
+      let post_form = PostInsertForm::builder()
+        .name(url.into())
+        .url(Some(Url::parse(url).unwrap().into()))
+        .creator_id(data.inserted_person.id)
+        .community_id(data.inserted_community.id)
+        .build();
+      post_ids.push(Post::create(pool, &post_form).await.unwrap().id);
+    }
+
+    let list_post_type = |post_type| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .post_type(Some(post_type))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    // Image posts are link posts too
+    let mut links = list_post_type(PostType::Link)
+      .await
+      .into_iter()
+      .map(|p| p.post.id)
+      .collect::<Vec<_>>();
+    links.sort_by_key(|id| id.0);
+    assert_eq!(post_ids, links);
+
+    let images = list_post_type(PostType::Image).await;
+    assert_eq!(1, images.len());
+    assert_eq!(post_ids[1], images[0].post.id);
+
+    let texts = list_post_type(PostType::Text).await;
+    assert_eq!(3, texts.len());
+    assert!(texts.iter().all(|p| p.post.url.is_none()));
+
+    for post_id in post_ids {
+      Post::delete(pool, post_id).await.unwrap();
+    }
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_url_contains() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let post_form = PostInsertForm::builder()
+      .name("A link to a repository".into())
+      .url(Some(
+        Url::parse("https://github.com/LemmyNet/lemmy")
+          .unwrap()
+          .into(),
+      ))
+      .creator_id(data.inserted_person.id)
+      .community_id(data.inserted_community.id)
+      .build();
+    let github_post = Post::create(pool, &post_form).await.unwrap();
+
+    let list_url_contains = |term: &'static str| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .url_contains(Some(term.into()))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    // The posts without a url from init_data are never returned
+    let github_posts = list_url_contains("github").await;
+    assert_eq!(1, github_posts.len());
+    assert_eq!(github_post.id, github_posts[0].post.id);
+
+    // The url is searched case insensitively, but the title is not searched
+    assert_eq!(1, list_url_contains("GitHub").await.len());
+    assert!(list_url_contains("repository").await.is_empty());
+
+    Post::delete(pool, github_post.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_full_text_search() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let mut post_ids = vec![];
+    for (name, body) in [
+      ("Running shoes", Some("Runs, runners and running")),
+      ("The dog runs", None),
+    ] {
+      let post_form = PostInsertForm::builder()
+        .name(name.into())
+        .body(body.map(Into::into))
+        .creator_id(data.inserted_person.id)
+        .community_id(data.inserted_community.id)
+        .build();
+      post_ids.push(Post::create(pool, &post_form).await.unwrap().id);
+    }
+
+    let search = |full_text_search, sort| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .search_term(Some("running".into()))
+        .full_text_search(Some(full_text_search))
+        .sort(Some(sort))
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| p.post.id)
+        .collect::<Vec<_>>()
+    };
+
+    // "running" is stemmed, so it also matches "runs"
+    let full_text_ids = search(true, SortType::New).await;
+    assert_eq!(2, full_text_ids.len());
+    assert!(full_text_ids.contains(&post_ids[1]));
+    let substring_ids = search(false, SortType::New).await;
+    assert_eq!(vec![post_ids[0]], substring_ids);
+
+    // The post which mentions running the most comes first, even though it's older
+    let best_match_ids = search(true, SortType::BestMatch).await;
+    assert_eq!(post_ids, best_match_ids);
+
+    for post_id in post_ids {
+      Post::delete(pool, post_id).await.unwrap();
+    }
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_unread_comments() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let comment_form = CommentInsertForm::builder()
+      .content("A test comment".into())
+      .creator_id(data.inserted_person.id)
+      .post_id(data.inserted_post.id)
+      .build();
+    for _ in 0..3 {
+      Comment::create(pool, &comment_form, None).await.unwrap();
+    }
+
+    // Never read yet, so all comments are unread
+    let post_view = PostView::read(
+      pool,
+      data.inserted_post.id,
+      Some(data.inserted_person.id),
+      None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(Some(0), post_view.read_comments);
+    assert_eq!(Some(3), post_view.unread_comments);
+
+    let person_post_agg_form = PersonPostAggregatesForm {
+      person_id: data.inserted_person.id,
+      post_id: data.inserted_post.id,
+      read_comments: 3,
+      ..PersonPostAggregatesForm::default()
+    };
+    PersonPostAggregates::upsert(pool, &person_post_agg_form)
+      .await
+      .unwrap();
+    for _ in 0..2 {
+      Comment::create(pool, &comment_form, None).await.unwrap();
+    }
+
+    let post_view = PostView::read(
+      pool,
+      data.inserted_post.id,
+      Some(data.inserted_person.id),
+      None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(Some(3), post_view.read_comments);
+    assert_eq!(Some(2), post_view.unread_comments);
+
+    let post_listings = PostQuery::builder()
+      .pool(pool)
+      .community_id(Some(data.inserted_community.id))
+      .local_user(Some(&data.inserted_local_user))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    let listed_post = post_listings
+      .iter()
+      .find(|p| p.post.id == data.inserted_post.id)
+      .unwrap();
+    assert_eq!(Some(2), listed_post.unread_comments);
+
+    // Logged out users get no read counts
+    let post_view = PostView::read(pool, data.inserted_post.id, None, None)
+      .await
+      .unwrap();
+    assert_eq!(None, post_view.read_comments);
+    assert_eq!(None, post_view.unread_comments);
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_exclude_post_ids() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let community_form = CommunityInsertForm::builder()
+      .name("exclude_posts_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let community = Community::create(pool, &community_form).await.unwrap();
+    let mut post_ids = vec![];
+    for i in 0..5 {
+      let post_form = PostInsertForm::builder()
+        .name(format!("post {i}"))
+        .creator_id(data.inserted_person.id)
+        .community_id(community.id)
+        .build();
+      post_ids.push(Post::create(pool, &post_form).await.unwrap().id);
+    }
+
+    let list_excluding = |exclude_post_ids| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(community.id))
+        .exclude_post_ids(Some(exclude_post_ids))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    let remaining = list_excluding(post_ids[..3].to_vec()).await;
+    assert_eq!(2, remaining.len());
+    assert!(remaining.iter().all(|p| post_ids[3..].contains(&p.post.id)));
+
+    assert_eq!(5, list_excluding(vec![]).await.len());
+
+    Community::delete(pool, community.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_exclude_person_ids() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // The community has posts by tegan, the bot and the blocked person
+    let post_listings = PostQuery::builder()
+      .pool(pool)
+      .community_id(Some(data.inserted_community.id))
+      .exclude_person_ids(Some(vec![
+        data.inserted_bot.id,
+        data.inserted_blocked_person.id,
+      ]))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(1, post_listings.len());
+    assert_eq!(data.inserted_post.id, post_listings[0].post.id);
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_followed_communities_only() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // Three communities with a post each, of which tegan follows the second
+    let mut community_ids = vec![];
+    for name in ["followed_a", "followed_b", "followed_c"] {
+      let community_form = CommunityInsertForm::builder()
+        .name(name.to_string())
+        .title("nada".to_owned())
+        .public_key("pubkey".to_string())
+        .instance_id(data.inserted_instance.id)
+        .build();
+      let community = Community::create(pool, &community_form).await.unwrap();
+      let post_form = PostInsertForm::builder()
+        .name(format!("{name} post"))
+        .creator_id(data.inserted_person.id)
+        .community_id(community.id)
+        .build();
+      Post::create(pool, &post_form).await.unwrap();
+      community_ids.push(community.id);
+    }
+    let follower_form = CommunityFollowerForm {
+      community_id: community_ids[1],
+      person_id: data.inserted_person.id,
+      pending: false,
+    };
+    CommunityFollower::follow(pool, &follower_form)
+      .await
+      .unwrap();
+
+    let followed_posts = PostQuery::builder()
+      .pool(pool)
+      .local_user(Some(&data.inserted_local_user))
+      .followed_communities_only(Some(true))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(1, followed_posts.len());
+    assert_eq!(community_ids[1], followed_posts[0].community.id);
+
+    // A local user is required
+    let without_user = PostQuery::builder()
+      .pool(pool)
+      .followed_communities_only(Some(true))
+      .build()
+      .list()
+      .await;
+    assert!(without_user.is_err());
+
+    for community_id in community_ids {
+      Community::delete(pool, community_id).await.unwrap();
+    }
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_saved_only() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let saved_form = PostSavedForm {
+      post_id: data.inserted_post.id,
+      person_id: data.inserted_person.id,
+    };
+    PostSaved::save(pool, &saved_form).await.unwrap();
+
+    let saved_posts = PostQuery::builder()
+      .pool(pool)
+      .local_user(Some(&data.inserted_local_user))
+      .saved_only(Some(true))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(1, saved_posts.len());
+    assert_eq!(data.inserted_post.id, saved_posts[0].post.id);
+    assert!(saved_posts[0].saved);
+
+    // A local user is required
+    let without_user = PostQuery::builder()
+      .pool(pool)
+      .saved_only(Some(true))
+      .build()
+      .list()
+      .await;
+    assert!(matches!(
+      without_user,
+      Err(Error::QueryBuilderError(e)) if e.to_string() == "saved_only requires a local user"
+    ));
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_nsfw_filter() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let nsfw_community_form = CommunityInsertForm::builder()
+      .name("nsfw_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(data.inserted_instance.id)
+      .nsfw(Some(true))
+      .build();
+    let nsfw_community = Community::create(pool, &nsfw_community_form).await.unwrap();
+
+    // A nsfw post in a safe community, and a safe post in a nsfw community
+    let nsfw_post_form = PostInsertForm::builder()
+      .name("nsfw post".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(data.inserted_community.id)
+      .nsfw(Some(true))
+      .build();
+    let nsfw_post = Post::create(pool, &nsfw_post_form).await.unwrap();
+    let nsfw_community_post_form = PostInsertForm::builder()
+      .name("post in nsfw community".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(nsfw_community.id)
+      .build();
+    let nsfw_community_post = Post::create(pool, &nsfw_community_post_form).await.unwrap();
+
+    let list_nsfw_filter = |nsfw_filter| async move {
+      let mut post_ids = PostQuery::builder()
+        .pool(pool)
+        .nsfw_filter(Some(nsfw_filter))
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|p| [data.inserted_community.id, nsfw_community.id].contains(&p.community.id))
+        .map(|p| p.post.id)
+        .collect::<Vec<PostId>>();
+      post_ids.sort_by_key(|id| id.0);
+      post_ids
+    };
+
+    // The community has 3 safe posts from init_data
+    assert_eq!(5, list_nsfw_filter(NsfwFilter::ShowAll).await.len());
+    let safe_only = list_nsfw_filter(NsfwFilter::SafeOnly).await;
+    assert_eq!(3, safe_only.len());
+    assert!(!safe_only.contains(&nsfw_post.id));
+    assert_eq!(
+      vec![nsfw_post.id, nsfw_community_post.id],
+      list_nsfw_filter(NsfwFilter::NsfwOnly).await
+    );
+
+    Post::delete(pool, nsfw_post.id).await.unwrap();
+    Community::delete(pool, nsfw_community.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_creator_blocked() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // The posts of blocked persons are shown by default, but marked
+    let post_listings = PostQuery::builder()
+      .pool(pool)
+      .community_id(Some(data.inserted_community.id))
+      .local_user(Some(&data.inserted_local_user))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    let blocked_post = post_listings
+      .iter()
+      .find(|p| p.creator.id == data.inserted_blocked_person.id)
+      .unwrap();
+    assert!(blocked_post.creator_blocked);
+    assert!(post_listings
+      .iter()
+      .filter(|p| p.creator.id != data.inserted_blocked_person.id)
+      .all(|p| !p.creator_blocked));
+
+    let hidden_blocked = PostQuery::builder()
+      .pool(pool)
+      .community_id(Some(data.inserted_community.id))
+      .local_user(Some(&data.inserted_local_user))
+      .hide_blocked(Some(true))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(post_listings.len() - 1, hidden_blocked.len());
+    assert!(hidden_blocked.iter().all(|p| !p.creator_blocked));
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_creator_ids() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let list_by_creators = |creator_ids| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .creator_ids(Some(creator_ids))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    assert!(list_by_creators(vec![]).await.is_empty());
+
+    // Tegan and the bot wrote one post each, the blocked person another one
+    let by_tegan_and_bot =
+      list_by_creators(vec![data.inserted_person.id, data.inserted_bot.id]).await;
+    assert_eq!(2, by_tegan_and_bot.len());
+    assert!(by_tegan_and_bot
+      .iter()
+      .all(|p| p.creator.id != data.inserted_blocked_person.id));
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_creator_local() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let remote_person_form = PersonInsertForm::builder()
+      .name("remote_rita".into())
+      .public_key("pubkey".to_string())
+      .instance_id(data.inserted_instance.id)
+      .local(Some(false))
+      .build();
+    let remote_person = Person::create(pool, &remote_person_form).await.unwrap();
+    let post_form = PostInsertForm::builder()
+      .name("remote post".into())
+      .creator_id(remote_person.id)
+      .community_id(data.inserted_community.id)
+      .build();
+    let remote_post = Post::create(pool, &post_form).await.unwrap();
+
+    let list_creator_local = |creator_local| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .creator_local(Some(creator_local))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    let local_posts = list_creator_local(true).await;
+    assert_eq!(3, local_posts.len());
+    assert!(local_posts.iter().all(|p| p.creator.local));
+
+    let remote_posts = list_creator_local(false).await;
+    assert_eq!(1, remote_posts.len());
+    assert_eq!(remote_post.id, remote_posts[0].post.id);
+
+    Person::delete(pool, remote_person.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_limit_per_community() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // A busy community with 10 posts, and a quieter one with 5 of which the newest two are
+    // deleted and removed
+    let mut community_ids = vec![];
+    for (name, post_count) in [("busy_community", 10), ("quiet_community", 5)] {
+      let community_form = CommunityInsertForm::builder()
+        .name(name.to_string())
+        .title("nada".to_owned())
+        .public_key("pubkey".to_string())
+        .instance_id(data.inserted_instance.id)
+        .build();
+      let community = Community::create(pool, &community_form).await.unwrap();
+      for i in 0..post_count {
+        let post_form = PostInsertForm::builder()
+          .name(format!("{name} post {i}"))
+          .creator_id(data.inserted_person.id)
+          .community_id(community.id)
+          .build();
+        let post = Post::create(pool, &post_form).await.unwrap();
+        if name == "quiet_community" && i >= 3 {
+          let update_form = PostUpdateForm::builder()
+            .deleted(Some(i == 3))
+            .removed(Some(i == 4))
+            .build();
+          Post::update(pool, post.id, &update_form).await.unwrap();
+        }
+      }
+      community_ids.push(community.id);
+    }
+
+    let post_listings = PostQuery::builder()
+      .pool(pool)
+      .sort(Some(SortType::New))
+      .limit_per_community(Some(3))
+      .build()
+      .list()
+      .await
+      .unwrap();
+
+    for community_id in &community_ids {
+      let community_posts = post_listings
+        .iter()
+        .filter(|p| &p.community.id == community_id)
+        .collect::<Vec<&PostView>>();
+      // Hidden posts don't take up any of the slots
+      assert_eq!(3, community_posts.len());
+    }
+    let names = |community_id| {
+      post_listings
+        .iter()
+        .filter(|p| p.community.id == community_id)
+        .map(|p| p.post.name.clone())
+        .collect::<Vec<_>>()
+    };
+    // The newest visible posts of each community are kept
+    assert_eq!(
+      vec![
+        "busy_community post 9",
+        "busy_community post 8",
+        "busy_community post 7"
+      ],
+      names(community_ids[0])
+    );
+    assert_eq!(
+      vec![
+        "quiet_community post 2",
+        "quiet_community post 1",
+        "quiet_community post 0"
+      ],
+      names(community_ids[1])
+    );
+
+    for community_id in community_ids {
+      Community::delete(pool, community_id).await.unwrap();
+    }
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_moderated_by() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // Tegan moderates two new communities, but not the one from init_data
+    let mut moderated_ids = vec![];
+    for name in ["moderated_community_1", "moderated_community_2"] {
+      let community_form = CommunityInsertForm::builder()
+        .name(name.to_string())
+        .title("nada".to_owned())
+        .public_key("pubkey".to_string())
+        .instance_id(data.inserted_instance.id)
+        .build();
+      let community = Community::create(pool, &community_form).await.unwrap();
+      let moderator_form = CommunityModeratorForm {
+        community_id: community.id,
+        person_id: data.inserted_person.id,
+      };
+      CommunityModerator::join(pool, &moderator_form)
+        .await
+        .unwrap();
+      let post_form = PostInsertForm::builder()
+        .name(format!("{name} post"))
+        .creator_id(data.inserted_person.id)
+        .community_id(community.id)
+        .build();
+      Post::create(pool, &post_form).await.unwrap();
+      moderated_ids.push(community.id);
+    }
+
+    let post_listings = PostQuery::builder()
+      .pool(pool)
+      .local_user(Some(&data.inserted_local_user))
+      .listing_type(Some(ListingType::ModeratedBy))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(2, post_listings.len());
+    assert!(post_listings
+      .iter()
+      .all(|p| moderated_ids.contains(&p.community.id)));
+
+    for community_id in moderated_ids {
+      Community::delete(pool, community_id).await.unwrap();
+    }
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_refresh_hot_ranks() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // A two day old post, which would only make the first page with a stale rank
+    let post_form = PostInsertForm::builder()
+      .name("An old post".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(data.inserted_community.id)
+      .published(Some(naive_now() - Duration::days(2)))
+      .build();
+    let old_post = Post::create(pool, &post_form).await.unwrap();
+
+    // Make the hot ranks of the community stale
+    let conn = &mut get_conn(pool).await.unwrap();
+    diesel::update(post_aggregates::table)
+      .filter(
+        post_aggregates::post_id.eq_any(
+          post::table
+            .select(post::id)
+            .filter(post::community_id.eq(data.inserted_community.id)),
+        ),
+      )
+      .set(post_aggregates::hot_rank.eq(0))
+      .execute(conn)
+      .await
+      .unwrap();
+    diesel::update(post_aggregates::table)
+      .filter(post_aggregates::post_id.eq(old_post.id))
+      .set(post_aggregates::hot_rank.eq(100_000))
+      .execute(conn)
+      .await
+      .unwrap();
+
+    let list_posts = |sort, refresh_hot_ranks, limit| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .sort(Some(sort))
+        .refresh_hot_ranks(Some(refresh_hot_ranks))
+        .limit(limit)
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+    let list_hot = |refresh_hot_ranks, limit| list_posts(SortType::Hot, refresh_hot_ranks, limit);
+
+    let stale = list_hot(false, Some(1)).await;
+    assert_eq!(old_post.id, stale[0].post.id);
+
+    // Sorts which don't use the hot ranks leave them alone
+    list_posts(SortType::New, true, None).await;
+    let still_stale = list_hot(false, Some(1)).await;
+    assert_eq!(old_post.id, still_stale[0].post.id);
+
+    // The fresh ranks decide which posts make it onto the page
+    let refreshed = list_hot(true, Some(1)).await;
+    assert_eq!(1, refreshed.len());
+    assert_ne!(old_post.id, refreshed[0].post.id);
+    assert!(refreshed[0].counts.hot_rank > 0);
+
+    // The refreshed ranks of all posts were saved, not only those of the page
+    let all_posts = list_hot(false, None).await;
+    assert!(all_posts.iter().all(|p| p.counts.hot_rank < 100_000));
+    assert!(all_posts
+      .iter()
+      .filter(|p| p.post.id != old_post.id)
+      .all(|p| p.counts.hot_rank > 0));
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_moderator_view() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let community_form = CommunityInsertForm::builder()
+      .name("moderator_view_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let community = Community::create(pool, &community_form).await.unwrap();
+    let moderator_form = CommunityModeratorForm {
+      community_id: community.id,
+      person_id: data.inserted_person.id,
+    };
+    CommunityModerator::join(pool, &moderator_form)
+      .await
+      .unwrap();
+    let post_form = PostInsertForm::builder()
+      .name("A removed post".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(community.id)
+      .removed(Some(true))
+      .build();
+    let removed_post = Post::create(pool, &post_form).await.unwrap();
+
+    let moderated_by = PostQuery::builder()
+      .pool(pool)
+      .local_user(Some(&data.inserted_local_user))
+      .listing_type(Some(ListingType::ModeratedBy))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert!(moderated_by.is_empty());
+
+    let moderator_view = PostQuery::builder()
+      .pool(pool)
+      .local_user(Some(&data.inserted_local_user))
+      .build()
+      .moderator_view()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(1, moderator_view.len());
+    assert_eq!(removed_post.id, moderator_view[0].post.id);
+
+    // Options set on the builder override the defaults
+    let community_listing = PostQuery::builder()
+      .pool(pool)
+      .local_user(Some(&data.inserted_local_user))
+      .listing_type(Some(ListingType::All))
+      .community_id(Some(data.inserted_community.id))
+      .build()
+      .moderator_view()
+      .list()
+      .await
+      .unwrap();
+    assert!(community_listing
+      .iter()
+      .all(|p| p.community.id == data.inserted_community.id));
+    assert!(!community_listing.is_empty());
+
+    // Removed posts of communities which aren't moderated stay hidden
+    let post_form = PostInsertForm::builder()
+      .name("A removed post elsewhere".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(data.inserted_community.id)
+      .removed(Some(true))
+      .build();
+    let unmoderated_post = Post::create(pool, &post_form).await.unwrap();
+    let all_listing = PostQuery::builder()
+      .pool(pool)
+      .local_user(Some(&data.inserted_local_user))
+      .listing_type(Some(ListingType::All))
+      .build()
+      .moderator_view()
+      .list()
+      .await
+      .unwrap();
+    assert!(all_listing.iter().any(|p| p.post.id == removed_post.id));
+    assert!(all_listing.iter().all(|p| p.post.id != unmoderated_post.id));
+
+    Community::delete(pool, community.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_read_with_duplicates() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let url = Url::parse("https://example.com/cross-posted").unwrap();
+    let mut post_ids = vec![];
+    for name in ["original post", "cross post"] {
+      let post_form = PostInsertForm::builder()
+        .name(name.into())
+        .url(Some(url.clone().into()))
+        .creator_id(data.inserted_person.id)
+        .community_id(data.inserted_community.id)
+        .build();
+      post_ids.push(Post::create(pool, &post_form).await.unwrap().id);
+    }
+
+    let (post_view, duplicates) =
+      PostView::read_with_duplicates(pool, post_ids[0], Some(data.inserted_person.id), 10)
+        .await
+        .unwrap();
+    assert_eq!(post_ids[0], post_view.post.id);
+    assert_eq!(1, duplicates.len());
+    assert_eq!(post_ids[1], duplicates[0].post.id);
+
+    // The maximum limit still works, and persons without a local user see the plain listing
+    let (_, bot_duplicates) = PostView::read_with_duplicates(
+      pool,
+      post_ids[0],
+      Some(data.inserted_bot.id),
+      FETCH_LIMIT_MAX,
+    )
+    .await
+    .unwrap();
+    assert_eq!(duplicates.len(), bot_duplicates.len());
+
+    // Posts without a url have no duplicates
+    let (_, no_duplicates) = PostView::read_with_duplicates(pool, data.inserted_post.id, None, 10)
+      .await
+      .unwrap();
+    assert!(no_duplicates.is_empty());
+
+    for post_id in post_ids {
+      Post::delete(pool, post_id).await.unwrap();
+    }
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_creator_post_count() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // The creators from init_data wrote one post each
+    let prolific_person_form = PersonInsertForm::builder()
+      .name("paula_prolific".into())
+      .public_key("pubkey".to_string())
+      .instance_id(data.inserted_instance.id)
+      .build();
+    let prolific_person = Person::create(pool, &prolific_person_form).await.unwrap();
+    for i in 0..10 {
+      let post_form = PostInsertForm::builder()
+        .name(format!("post {i}"))
+        .creator_id(prolific_person.id)
+        .community_id(data.inserted_community.id)
+        .build();
+      Post::create(pool, &post_form).await.unwrap();
+    }
+
+    let list_by_post_count = |creator_min_post_count, creator_max_post_count| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .creator_min_post_count(creator_min_post_count)
+        .creator_max_post_count(creator_max_post_count)
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    let by_power_users = list_by_post_count(Some(5), None).await;
+    assert_eq!(10, by_power_users.len());
+    assert!(by_power_users
+      .iter()
+      .all(|p| p.creator.id == prolific_person.id));
+
+    let by_new_users = list_by_post_count(None, Some(1)).await;
+    assert_eq!(3, by_new_users.len());
+    assert!(by_new_users
+      .iter()
+      .all(|p| p.creator.id != prolific_person.id));
+
+    Person::delete(pool, prolific_person.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_my_vote_default() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    // Nobody voted on the post
+    let read_my_vote = |my_person_id| async move {
+      PostView::read(pool, data.inserted_post.id, my_person_id, None)
+        .await
+        .unwrap()
+        .my_vote
+    };
+    assert_eq!(Some(0), read_my_vote(Some(data.inserted_person.id)).await);
+    assert_eq!(None, read_my_vote(None).await);
+
+    let list_my_votes = |local_user| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .local_user(local_user)
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| p.my_vote)
+        .collect::<Vec<Option<i16>>>()
+    };
+    let logged_in = list_my_votes(Some(&data.inserted_local_user)).await;
+    assert!(!logged_in.is_empty());
+    assert!(logged_in.iter().all(|v| v == &Some(0)));
+    let logged_out = list_my_votes(None).await;
+    assert!(!logged_out.is_empty());
+    assert!(logged_out.iter().all(Option::is_none));
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_community_moderators() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    for person_id in [data.inserted_person.id, data.inserted_blocked_person.id] {
+      let moderator_form = CommunityModeratorForm {
+        community_id: data.inserted_community.id,
+        person_id,
+      };
+      CommunityModerator::join(pool, &moderator_form)
+        .await
+        .unwrap();
+    }
+
+    let list = |include_community_moderators| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .include_community_moderators(Some(include_community_moderators))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    let with_moderators = list(true).await;
+    assert!(!with_moderators.is_empty());
+    for post_view in with_moderators {
+      let moderator_ids: Vec<_> = post_view
+        .community_moderators
+        .iter()
+        .map(|p| p.id)
+        .collect();
+      assert_eq!(
+        vec![data.inserted_person.id, data.inserted_blocked_person.id],
+        moderator_ids
+      );
+    }
+
+    assert!(list(false)
+      .await
+      .iter()
+      .all(|p| p.community_moderators.is_empty()));
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_show_scores() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    for person_id in [data.inserted_person.id, data.inserted_blocked_person.id] {
+      let post_like_form = PostLikeForm {
+        post_id: data.inserted_post.id,
+        person_id,
+        score: 1,
+      };
+      PostLike::like(pool, &post_like_form).await.unwrap();
+    }
+
+    let local_user = &data.inserted_local_user;
+    let read_post = |show_scores| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .community_id(Some(data.inserted_community.id))
+        .local_user(Some(local_user))
+        .show_scores(Some(show_scores))
+        .with_creator_aggregates(Some(true))
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|p| p.post.id == data.inserted_post.id)
+        .unwrap()
+    };
+
+    let with_scores = read_post(true).await;
+    assert_eq!(2, with_scores.counts.score);
+    assert_eq!(2, with_scores.counts.upvotes);
+    assert!(with_scores.counts.hot_rank > 0);
+    assert!(with_scores.creator_karma.is_some());
+
+    let blind = read_post(false).await;
+    assert_eq!(0, blind.counts.score);
+    assert_eq!(0, blind.counts.upvotes);
+    assert_eq!(0, blind.counts.downvotes);
+    assert_eq!(0, blind.counts.hot_rank);
+    assert_eq!(0, blind.counts.hot_rank_active);
+    assert_eq!(None, blind.creator_karma);
+    assert_eq!(Some(1), blind.my_vote);
+
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_creator_karma() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let comment_form = CommentInsertForm::builder()
+      .content("A comment to vote on".into())
+      .creator_id(data.inserted_person.id)
+      .post_id(data.inserted_post.id)
+      .build();
+    let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let post_like_form = PostLikeForm {
+      post_id: data.inserted_post.id,
+      person_id: data.inserted_blocked_person.id,
+      score: 1,
+    };
+    PostLike::like(pool, &post_like_form).await.unwrap();
+    let comment_like_form = CommentLikeForm {
+      comment_id: inserted_comment.id,
+      post_id: data.inserted_post.id,
+      person_id: data.inserted_blocked_person.id,
+      score: 1,
+    };
+    CommentLike::like(pool, &comment_like_form).await.unwrap();
+
+    let new_post_form = PostInsertForm::builder()
+      .name("A new post".into())
+      .creator_id(data.inserted_person.id)
+      .community_id(data.inserted_community.id)
+      .build();
+    let new_post = Post::create(pool, &new_post_form).await.unwrap();
+
+    let list = |with_creator_aggregates| async move {
+      PostQuery::builder()
+        .pool(pool)
+        .creator_id(Some(data.inserted_person.id))
+        .with_creator_aggregates(Some(with_creator_aggregates))
+        .build()
+        .list()
+        .await
+        .unwrap()
+    };
+
+    let with_karma = list(true).await;
+    assert!(with_karma.iter().any(|p| p.post.id == new_post.id));
+    assert!(with_karma.iter().all(|p| p.creator_karma == Some(2)));
+
+    assert!(list(false).await.iter().all(|p| p.creator_karma.is_none()));
+
+    Post::delete(pool, new_post.id).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn post_listings_related_communities() {
+    let pool = &build_db_pool_for_tests().await;
+    let data = init_data(pool).await;
+
+    let mut community_ids = vec![data.inserted_community.id];
+    for name in ["related_community_1", "related_community_2"] {
+      let community_form = CommunityInsertForm::builder()
+        .name(name.to_string())
+        .title("nada".to_owned())
+        .public_key("pubkey".to_string())
+        .instance_id(data.inserted_instance.id)
+        .build();
+      community_ids.push(Community::create(pool, &community_form).await.unwrap().id);
+    }
+
+    let url = Url::parse("https://example.com/shared-link").unwrap();
+    let mut post_ids = vec![];
+    for community_id in &community_ids {
+      let post_form = PostInsertForm::builder()
+        .name("A shared link".into())
+        .url(Some(url.clone().into()))
+        .creator_id(data.inserted_person.id)
+        .community_id(*community_id)
+        .build();
+      post_ids.push(Post::create(pool, &post_form).await.unwrap().id);
+    }
+
+    let post_view = PostView::read(pool, post_ids[0], None, None).await.unwrap();
+    assert_eq!(community_ids[1..], post_view.related_community_ids);
+
+    // Posts without a url have no related communities
+    let post_view = PostView::read(pool, data.inserted_post.id, None, None)
+      .await
+      .unwrap();
+    assert!(post_view.related_community_ids.is_empty());
+
+    // Deleting the communities deletes their posts as well
+    for community_id in &community_ids[1..] {
+      Community::delete(pool, *community_id).await.unwrap();
+    }
+    Post::delete(pool, post_ids[0]).await.unwrap();
+    cleanup(data, pool).await;
+  }
+
   async fn cleanup(data: Data, pool: &DbPool) {
     let num_deleted = Post::delete(pool, data.inserted_post.id).await.unwrap();
     Community::delete(pool, data.inserted_community.id)
@@ -967,7 +3230,11 @@ mod tests {
         featured_local: false,
       },
       my_vote: None,
-      unread_comments: 0,
+      read_comments: None,
+      unread_comments: None,
+      related_community_ids: vec![],
+      community_moderators: vec![],
+      creator_karma: None,
       creator: Person {
         id: inserted_person.id,
         name: inserted_person.name.clone(),
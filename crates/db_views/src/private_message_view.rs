@@ -1,8 +1,10 @@
-use crate::structs::PrivateMessageView;
+use crate::structs::{ConversationPreview, PrivateMessageView};
 use diesel::{
   debug_query,
+  dsl::sql,
   pg::Pg,
   result::Error,
+  sql_types,
   BoolExpressionMethods,
   ExpressionMethods,
   JoinOnDsl,
@@ -21,6 +23,13 @@ use typed_builder::TypedBuilder;
 
 type PrivateMessageViewTuple = (PrivateMessage, Person, Person);
 
+/// Only keeps the latest message between each pair of persons
+const LATEST_IN_CONVERSATION_SQL: &str = "private_message.id = (SELECT max(pm.id) \
+  FROM private_message pm WHERE pm.deleted = false AND (\
+    (pm.creator_id = private_message.creator_id AND pm.recipient_id = private_message.recipient_id) \
+    OR (pm.creator_id = private_message.recipient_id AND pm.recipient_id = private_message.creator_id)\
+  ))";
+
 impl PrivateMessageView {
   pub async fn read(pool: &DbPool, private_message_id: PrivateMessageId) -> Result<Self, Error> {
     let conn = &mut get_conn(pool).await?;
@@ -60,6 +69,61 @@ impl PrivateMessageView {
       .first::<i64>(conn)
       .await
   }
+
+  /// Lists the conversations of a person, with the latest message of each, newest first.
+  pub async fn get_conversation_partners(
+    pool: &DbPool,
+    my_person_id: PersonId,
+    page: Option<i64>,
+    limit: Option<i64>,
+  ) -> Result<Vec<ConversationPreview>, Error> {
+    let conn = &mut get_conn(pool).await?;
+    let (limit, offset) = limit_and_offset(page, limit)?;
+
+    // Joins the other person of the conversation
+    let other_person_join = private_message::creator_id
+      .eq(person::id)
+      .and(private_message::recipient_id.eq(my_person_id))
+      .or(
+        private_message::recipient_id
+          .eq(person::id)
+          .and(private_message::creator_id.eq(my_person_id)),
+      );
+
+    let res = private_message::table
+      .inner_join(person::table.on(other_person_join))
+      .filter(private_message::deleted.eq(false))
+      .filter(sql::<sql_types::Bool>(LATEST_IN_CONVERSATION_SQL))
+      .select((
+        person::all_columns,
+        private_message::all_columns,
+        sql::<sql_types::BigInt>(
+          "(SELECT COUNT(*) FROM private_message pm \
+            WHERE pm.creator_id = person.id AND pm.read = false AND pm.deleted = false \
+            AND pm.recipient_id = ",
+        )
+        .bind::<sql_types::Integer, _>(my_person_id)
+        .sql(")"),
+      ))
+      .order_by(private_message::published.desc())
+      .limit(limit)
+      .offset(offset)
+      .load::<(Person, PrivateMessage, i64)>(conn)
+      .await?;
+
+    Ok(
+      res
+        .into_iter()
+        .map(
+          |(other_person, last_message, unread_count)| ConversationPreview {
+            other_person,
+            last_message,
+            unread_count,
+          },
+        )
+        .collect(),
+    )
+  }
 }
 
 #[derive(TypedBuilder)]
@@ -70,6 +134,10 @@ pub struct PrivateMessageQuery<'a> {
   #[builder(!default)]
   recipient_id: PersonId,
   unread_only: Option<bool>,
+  /// Only lists the messages to recipient_id, leaving out the ones they sent
+  recipient_only: Option<bool>,
+  /// Lists the oldest messages first instead of the newest
+  oldest_first: Option<bool>,
   page: Option<i64>,
   limit: Option<i64>,
 }
@@ -96,6 +164,8 @@ impl<'a> PrivateMessageQuery<'a> {
       query = query
         .filter(private_message::read.eq(false))
         .filter(private_message::recipient_id.eq(self.recipient_id));
+    } else if self.recipient_only.unwrap_or(false) {
+      query = query.filter(private_message::recipient_id.eq(self.recipient_id));
     }
     // Otherwise, I want the ALL view to show both sent and received
     else {
@@ -111,8 +181,12 @@ impl<'a> PrivateMessageQuery<'a> {
     query = query
       .filter(private_message::deleted.eq(false))
       .limit(limit)
-      .offset(offset)
-      .order_by(private_message::published.desc());
+      .offset(offset);
+    query = if self.oldest_first.unwrap_or(false) {
+      query.order_by(private_message::published.asc())
+    } else {
+      query.order_by(private_message::published.desc())
+    };
 
     debug!(
       "Private Message View Query: {:?}",
@@ -140,3 +214,78 @@ impl JoinView for PrivateMessageView {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::structs::PrivateMessageView;
+  use lemmy_db_schema::{
+    source::{
+      instance::Instance,
+      person::{Person, PersonInsertForm},
+      private_message::{PrivateMessage, PrivateMessageInsertForm},
+    },
+    traits::Crud,
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_conversation_partners() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let mut persons = vec![];
+    for name in ["pm_me", "pm_alice", "pm_bob", "pm_carol"] {
+      let person_form = PersonInsertForm::builder()
+        .name(name.into())
+        .public_key("pubkey".to_string())
+        .instance_id(inserted_instance.id)
+        .build();
+      persons.push(Person::create(pool, &person_form).await.unwrap());
+    }
+    let (me, alice, bob, carol) = (&persons[0], &persons[1], &persons[2], &persons[3]);
+
+    let send = |creator: &Person, recipient: &Person, content: &str| {
+      let form = PrivateMessageInsertForm::builder()
+        .creator_id(creator.id)
+        .recipient_id(recipient.id)
+        .content(content.into())
+        .build();
+      async move { PrivateMessage::create(pool, &form).await.unwrap() }
+    };
+    send(alice, me, "hi").await;
+    let reply_to_alice = send(me, alice, "hello alice").await;
+    send(bob, me, "are you there?").await;
+    let latest_from_bob = send(bob, me, "hello?").await;
+    let message_to_carol = send(me, carol, "hi carol").await;
+    // Conversations between other persons aren't included
+    send(alice, bob, "hi bob").await;
+
+    let previews = PrivateMessageView::get_conversation_partners(pool, me.id, None, None)
+      .await
+      .unwrap();
+    assert_eq!(3, previews.len());
+
+    // Newest conversation first
+    assert_eq!(carol.id, previews[0].other_person.id);
+    assert_eq!(message_to_carol.id, previews[0].last_message.id);
+    assert_eq!(0, previews[0].unread_count);
+
+    assert_eq!(bob.id, previews[1].other_person.id);
+    assert_eq!(latest_from_bob.id, previews[1].last_message.id);
+    assert_eq!(2, previews[1].unread_count);
+
+    assert_eq!(alice.id, previews[2].other_person.id);
+    assert_eq!(reply_to_alice.id, previews[2].last_message.id);
+    assert_eq!(1, previews[2].unread_count);
+
+    for person in &persons {
+      Person::delete(pool, person.id).await.unwrap();
+    }
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+}
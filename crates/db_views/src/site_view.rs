@@ -1,16 +1,46 @@
 use crate::structs::SiteView;
 use diesel::{result::Error, ExpressionMethods, JoinOnDsl, QueryDsl};
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use lemmy_db_schema::{
   aggregates::structs::SiteAggregates,
-  schema::{local_site, local_site_rate_limit, site, site_aggregates},
-  source::{local_site::LocalSite, local_site_rate_limit::LocalSiteRateLimit, site::Site},
+  schema::{local_site, local_site_rate_limit, person, site, site_aggregates},
+  source::{
+    local_site::LocalSite,
+    local_site_rate_limit::LocalSiteRateLimit,
+    person::Person,
+    site::Site,
+  },
   utils::{get_conn, DbPool},
 };
 
 impl SiteView {
   pub async fn read_local(pool: &DbPool) -> Result<Self, Error> {
     let conn = &mut get_conn(pool).await?;
+    Self::read_local_conn(conn).await
+  }
+
+  /// Reads the local site together with its admins which aren't deleted, oldest first, in one
+  /// transaction.
+  pub async fn read_local_with_admins(pool: &DbPool) -> Result<(Self, Vec<Person>), Error> {
+    let conn = &mut get_conn(pool).await?;
+    conn
+      .build_transaction()
+      .run(|conn| {
+        Box::pin(async move {
+          let site_view = Self::read_local_conn(conn).await?;
+          let admins = person::table
+            .filter(person::admin.eq(true))
+            .filter(person::deleted.eq(false))
+            .order_by(person::published)
+            .load::<Person>(conn)
+            .await?;
+          Ok((site_view, admins))
+        }) as _
+      })
+      .await
+  }
+
+  async fn read_local_conn(conn: &mut AsyncPgConnection) -> Result<Self, Error> {
     let (mut site, local_site, local_site_rate_limit, counts) = site::table
       .inner_join(local_site::table)
       .inner_join(
@@ -35,3 +65,74 @@ impl SiteView {
     })
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::structs::SiteView;
+  use lemmy_db_schema::{
+    source::{
+      instance::Instance,
+      local_site::{LocalSite, LocalSiteInsertForm},
+      local_site_rate_limit::{LocalSiteRateLimit, LocalSiteRateLimitInsertForm},
+      person::{Person, PersonInsertForm},
+      site::{Site, SiteInsertForm},
+    },
+    traits::Crud,
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_read_local_admins() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let site_form = SiteInsertForm::builder()
+      .name("test site".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let site = Site::create(pool, &site_form).await.unwrap();
+    let local_site_form = LocalSiteInsertForm::builder().site_id(site.id).build();
+    let local_site = LocalSite::create(pool, &local_site_form).await.unwrap();
+    let rate_limit_form = LocalSiteRateLimitInsertForm::builder()
+      .local_site_id(local_site.id)
+      .build();
+    LocalSiteRateLimit::create(pool, &rate_limit_form)
+      .await
+      .unwrap();
+
+    let mut persons = vec![];
+    for (name, admin) in [
+      ("site_admin_1", true),
+      ("site_admin_2", true),
+      ("site_user", false),
+    ] {
+      let person_form = PersonInsertForm::builder()
+        .name(name.into())
+        .public_key("pubkey".to_string())
+        .admin(Some(admin))
+        .instance_id(inserted_instance.id)
+        .build();
+      persons.push(Person::create(pool, &person_form).await.unwrap());
+    }
+
+    let (site_view, admins) = SiteView::read_local_with_admins(pool).await.unwrap();
+    assert_eq!(site.id, site_view.site.id);
+    let admins: Vec<_> = admins
+      .into_iter()
+      .filter(|a| persons.iter().any(|p| p.id == a.id))
+      .collect();
+    assert_eq!(persons[..2], admins);
+
+    for person in persons {
+      Person::delete(pool, person.id).await.unwrap();
+    }
+    Site::delete(pool, site.id).await.unwrap();
+    LocalSite::delete(pool).await.unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+}
@@ -1,5 +1,6 @@
 use lemmy_db_schema::{
   aggregates::structs::{CommentAggregates, PersonAggregates, PostAggregates, SiteAggregates},
+  newtypes::{CommunityId, DbUrl},
   source::{
     comment::Comment,
     comment_report::CommentReport,
@@ -50,14 +51,25 @@ pub struct CommentReportView {
 pub struct CommentView {
   pub comment: Comment,
   pub creator: Person,
+  /// The avatar of the creator, same as creator.avatar. Saves clients from digging into the
+  /// creator when rendering feeds.
+  pub creator_avatar: Option<DbUrl>,
+  /// The matrix user id of the creator. Only set if the viewer is the creator or follows them,
+  /// creator.matrix_user_id is stripped otherwise.
+  pub creator_matrix_user_id: Option<String>,
   pub post: Post,
   pub community: Community,
   pub counts: CommentAggregates,
   pub creator_banned_from_community: bool,
+  /// When the creator's ban from the community expires, if it is temporary.
+  pub creator_ban_expires: Option<chrono::NaiveDateTime>,
   pub subscribed: SubscribedType,
   pub saved: bool,
   pub creator_blocked: bool,
   pub my_vote: Option<i16>,
+  /// The number of distinct people who commented in this thread. Only computed for logged in
+  /// users, or when thread stats are requested.
+  pub thread_participants: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -103,7 +115,16 @@ pub struct PostView {
   pub read: bool,
   pub creator_blocked: bool,
   pub my_vote: Option<i16>,
-  pub unread_comments: i64,
+  /// The number of comments when the user last read the post. None for logged out users.
+  pub read_comments: Option<i64>,
+  /// The number of comments added since the user last read the post. None for logged out users.
+  pub unread_comments: Option<i64>,
+  /// The other communities where the url of this post was shared
+  pub related_community_ids: Vec<CommunityId>,
+  /// The moderators of the community, top moderator first. Only filled in when requested
+  pub community_moderators: Vec<Person>,
+  /// The sum of the post and comment scores of the creator. Only filled in when requested
+  pub creator_karma: Option<i64>,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
@@ -116,6 +137,18 @@ pub struct PrivateMessageView {
   pub recipient: Person,
 }
 
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "full", derive(TS))]
+#[cfg_attr(feature = "full", ts(export))]
+/// The latest message of a private conversation, for the inbox.
+pub struct ConversationPreview {
+  /// The other person in the conversation.
+  pub other_person: Person,
+  pub last_message: PrivateMessage,
+  /// The number of messages from the other person which haven't been read yet.
+  pub unread_count: i64,
+}
+
 #[skip_serializing_none]
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
 #[cfg_attr(feature = "full", derive(TS))]
@@ -160,3 +193,13 @@ pub struct CustomEmojiView {
   pub custom_emoji: CustomEmoji,
   pub keywords: Vec<CustomEmojiKeyword>,
 }
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "full", derive(TS))]
+#[cfg_attr(feature = "full", ts(export))]
+/// A vote on a post or comment, for vote inspection.
+pub struct VoteView {
+  pub creator: Person,
+  pub score: i16,
+  pub published: chrono::NaiveDateTime,
+}
@@ -0,0 +1,204 @@
+use crate::structs::VoteView;
+use diesel::{result::Error, ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use lemmy_db_schema::{
+  newtypes::{CommentId, PostId},
+  schema::{comment_like, person, post_like},
+  source::person::Person,
+  traits::JoinView,
+  utils::{get_conn, limit_and_offset, DbPool},
+};
+
+type VoteViewTuple = (Person, i16, chrono::NaiveDateTime);
+
+impl VoteView {
+  /// Lists the votes on a post, newest first. With a score, only the upvotes (1) or downvotes (-1)
+  /// are listed.
+  pub async fn list_for_post(
+    pool: &DbPool,
+    post_id: PostId,
+    score: Option<i16>,
+    page: Option<i64>,
+    limit: Option<i64>,
+  ) -> Result<Vec<Self>, Error> {
+    let conn = &mut get_conn(pool).await?;
+    let (limit, offset) = limit_and_offset(page, limit)?;
+
+    let mut query = post_like::table
+      .inner_join(person::table)
+      .select((person::all_columns, post_like::score, post_like::published))
+      .filter(post_like::post_id.eq(post_id))
+      .into_boxed();
+
+    if let Some(score) = score {
+      query = query.filter(post_like::score.eq(score));
+    }
+
+    let res = query
+      .order_by(post_like::published.desc())
+      .limit(limit)
+      .offset(offset)
+      .load::<VoteViewTuple>(conn)
+      .await?;
+
+    Ok(res.into_iter().map(Self::from_tuple).collect())
+  }
+
+  /// Lists the votes on a comment, newest first. With a score, only the upvotes (1) or downvotes
+  /// (-1) are listed.
+  pub async fn list_for_comment(
+    pool: &DbPool,
+    comment_id: CommentId,
+    score: Option<i16>,
+    page: Option<i64>,
+    limit: Option<i64>,
+  ) -> Result<Vec<Self>, Error> {
+    let conn = &mut get_conn(pool).await?;
+    let (limit, offset) = limit_and_offset(page, limit)?;
+
+    let mut query = comment_like::table
+      .inner_join(person::table)
+      .select((
+        person::all_columns,
+        comment_like::score,
+        comment_like::published,
+      ))
+      .filter(comment_like::comment_id.eq(comment_id))
+      .into_boxed();
+
+    if let Some(score) = score {
+      query = query.filter(comment_like::score.eq(score));
+    }
+
+    let res = query
+      .order_by(comment_like::published.desc())
+      .limit(limit)
+      .offset(offset)
+      .load::<VoteViewTuple>(conn)
+      .await?;
+
+    Ok(res.into_iter().map(Self::from_tuple).collect())
+  }
+}
+
+impl JoinView for VoteView {
+  type JoinTuple = VoteViewTuple;
+  fn from_tuple(a: Self::JoinTuple) -> Self {
+    Self {
+      creator: a.0,
+      score: a.1,
+      published: a.2,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::structs::VoteView;
+  use lemmy_db_schema::{
+    source::{
+      comment::{Comment, CommentInsertForm, CommentLike, CommentLikeForm},
+      community::{Community, CommunityInsertForm},
+      instance::Instance,
+      person::{Person, PersonInsertForm},
+      post::{Post, PostInsertForm, PostLike, PostLikeForm},
+    },
+    traits::{Crud, Likeable},
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_list_votes() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let mut voters = vec![];
+    for i in 0..5 {
+      let person_form = PersonInsertForm::builder()
+        .name(format!("vera_voter_{i}"))
+        .public_key("pubkey".to_string())
+        .instance_id(inserted_instance.id)
+        .build();
+      voters.push(Person::create(pool, &person_form).await.unwrap());
+    }
+
+    let community_form = CommunityInsertForm::builder()
+      .name("vote_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &community_form).await.unwrap();
+
+    let post_form = PostInsertForm::builder()
+      .name("A voted post".into())
+      .creator_id(voters[0].id)
+      .community_id(inserted_community.id)
+      .build();
+    let inserted_post = Post::create(pool, &post_form).await.unwrap();
+
+    let comment_form = CommentInsertForm::builder()
+      .content("A voted comment".into())
+      .creator_id(voters[0].id)
+      .post_id(inserted_post.id)
+      .build();
+    let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    // Three upvotes and two downvotes on both
+    for (voter, score) in voters.iter().zip([1, 1, 1, -1, -1]) {
+      let comment_like_form = CommentLikeForm {
+        comment_id: inserted_comment.id,
+        post_id: inserted_post.id,
+        person_id: voter.id,
+        score,
+      };
+      CommentLike::like(pool, &comment_like_form).await.unwrap();
+      let post_like_form = PostLikeForm {
+        post_id: inserted_post.id,
+        person_id: voter.id,
+        score,
+      };
+      PostLike::like(pool, &post_like_form).await.unwrap();
+    }
+
+    let comment_votes = |score| async move {
+      VoteView::list_for_comment(pool, inserted_comment.id, score, None, None)
+        .await
+        .unwrap()
+    };
+    let upvotes = comment_votes(Some(1)).await;
+    assert_eq!(3, upvotes.len());
+    assert!(upvotes.iter().all(|v| v.score == 1));
+    assert_eq!(2, comment_votes(Some(-1)).await.len());
+    assert_eq!(5, comment_votes(None).await.len());
+
+    let post_downvotes = VoteView::list_for_post(pool, inserted_post.id, Some(-1), None, None)
+      .await
+      .unwrap();
+    assert_eq!(2, post_downvotes.len());
+    let downvoter_ids = post_downvotes
+      .iter()
+      .map(|v| v.creator.id)
+      .collect::<Vec<_>>();
+    assert!(downvoter_ids.contains(&voters[3].id));
+    assert!(downvoter_ids.contains(&voters[4].id));
+
+    let second_page = VoteView::list_for_post(pool, inserted_post.id, None, Some(2), Some(3))
+      .await
+      .unwrap();
+    assert_eq!(2, second_page.len());
+
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    for voter in voters {
+      Person::delete(pool, voter.id).await.unwrap();
+    }
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+}
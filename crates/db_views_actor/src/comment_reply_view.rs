@@ -6,6 +6,7 @@ use diesel::{
   JoinOnDsl,
   NullableExpressionMethods,
   QueryDsl,
+  SelectableHelper,
 };
 use diesel_async::RunQueryDsl;
 use lemmy_db_schema::{
@@ -125,7 +126,7 @@ impl CommentReplyView {
         comment_reply::all_columns,
         comment::all_columns,
         person::all_columns,
-        post::all_columns,
+        Post::as_select(),
         community::all_columns,
         person_alias_1.fields(person::all_columns),
         comment_aggregates::all_columns,
@@ -241,7 +242,7 @@ impl<'a> CommentReplyQuery<'a> {
         comment_reply::all_columns,
         comment::all_columns,
         person::all_columns,
-        post::all_columns,
+        Post::as_select(),
         community::all_columns,
         person_alias_1.fields(person::all_columns),
         comment_aggregates::all_columns,
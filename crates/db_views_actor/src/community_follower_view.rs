@@ -1,4 +1,4 @@
-use crate::structs::CommunityFollowerView;
+use crate::structs::{CommunityFollowerPendingView, CommunityFollowerView};
 use diesel::{result::Error, ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 use lemmy_db_schema::{
@@ -6,10 +6,11 @@ use lemmy_db_schema::{
   schema::{community, community_follower, person},
   source::{community::Community, person::Person},
   traits::JoinView,
-  utils::{get_conn, DbPool},
+  utils::{get_conn, limit_and_offset, DbPool},
 };
 
 type CommunityFollowerViewTuple = (Community, Person);
+type CommunityFollowerPendingViewTuple = (Person, chrono::NaiveDateTime);
 
 impl CommunityFollowerView {
   pub async fn for_community(pool: &DbPool, community_id: CommunityId) -> Result<Vec<Self>, Error> {
@@ -52,3 +53,119 @@ impl JoinView for CommunityFollowerView {
     }
   }
 }
+
+impl CommunityFollowerPendingView {
+  /// Lists the pending follow requests of a community, oldest requests first.
+  pub async fn list(
+    pool: &DbPool,
+    community_id: CommunityId,
+    page: Option<i64>,
+    limit: Option<i64>,
+  ) -> Result<Vec<Self>, Error> {
+    let conn = &mut get_conn(pool).await?;
+    let (limit, offset) = limit_and_offset(page, limit)?;
+    let res = community_follower::table
+      .inner_join(person::table)
+      .select((person::all_columns, community_follower::published))
+      .filter(community_follower::community_id.eq(community_id))
+      .filter(community_follower::pending.eq(true))
+      .order_by(community_follower::published)
+      .then_order_by(community_follower::id)
+      .limit(limit)
+      .offset(offset)
+      .load::<CommunityFollowerPendingViewTuple>(conn)
+      .await?;
+
+    Ok(res.into_iter().map(Self::from_tuple).collect())
+  }
+}
+
+impl JoinView for CommunityFollowerPendingView {
+  type JoinTuple = CommunityFollowerPendingViewTuple;
+  fn from_tuple(a: Self::JoinTuple) -> Self {
+    Self {
+      follower: a.0,
+      published: a.1,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::structs::CommunityFollowerPendingView;
+  use lemmy_db_schema::{
+    source::{
+      community::{Community, CommunityFollower, CommunityFollowerForm, CommunityInsertForm},
+      instance::Instance,
+      person::{Person, PersonInsertForm},
+    },
+    traits::{Crud, Followable},
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_pending_list() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let community_form = CommunityInsertForm::builder()
+      .name("pending_list_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &community_form).await.unwrap();
+
+    let mut persons = vec![];
+    for name in ["pia_pending", "pete_pending"] {
+      let person_form = PersonInsertForm::builder()
+        .name(name.into())
+        .public_key("pubkey".to_string())
+        .instance_id(inserted_instance.id)
+        .build();
+      let person = Person::create(pool, &person_form).await.unwrap();
+      let follower_form = CommunityFollowerForm {
+        community_id: inserted_community.id,
+        person_id: person.id,
+        pending: true,
+      };
+      CommunityFollower::follow(pool, &follower_form)
+        .await
+        .unwrap();
+      persons.push(person);
+    }
+
+    let pending = CommunityFollowerPendingView::list(pool, inserted_community.id, None, None)
+      .await
+      .unwrap();
+    let pending_ids: Vec<_> = pending.iter().map(|p| p.follower.id).collect();
+    assert_eq!(vec![persons[0].id, persons[1].id], pending_ids);
+
+    let approved = CommunityFollower::approve(pool, inserted_community.id, persons[0].id)
+      .await
+      .unwrap();
+    assert!(!approved.pending);
+    let rejected = CommunityFollower::reject(pool, inserted_community.id, persons[1].id)
+      .await
+      .unwrap();
+    assert_eq!(1, rejected);
+
+    let pending = CommunityFollowerPendingView::list(pool, inserted_community.id, None, None)
+      .await
+      .unwrap();
+    assert!(pending.is_empty());
+
+    for person in persons {
+      Person::delete(pool, person.id).await.unwrap();
+    }
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+}
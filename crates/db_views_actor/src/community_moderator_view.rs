@@ -8,8 +8,9 @@ use lemmy_db_schema::{
   traits::JoinView,
   utils::{get_conn, DbPool},
 };
+use std::collections::HashMap;
 
-type CommunityModeratorViewTuple = (Community, Person);
+type CommunityModeratorViewTuple = (Community, Person, chrono::NaiveDateTime);
 
 impl CommunityModeratorView {
   pub async fn for_community(pool: &DbPool, community_id: CommunityId) -> Result<Vec<Self>, Error> {
@@ -17,20 +18,55 @@ impl CommunityModeratorView {
     let res = community_moderator::table
       .inner_join(community::table)
       .inner_join(person::table)
-      .select((community::all_columns, person::all_columns))
+      .select((
+        community::all_columns,
+        person::all_columns,
+        community_moderator::published,
+      ))
       .filter(community_moderator::community_id.eq(community_id))
+      .order_by((community_moderator::published, community_moderator::id))
       .load::<CommunityModeratorViewTuple>(conn)
       .await?;
 
     Ok(res.into_iter().map(Self::from_tuple).collect())
   }
 
+  /// Loads the moderators of several communities at once, oldest moderator first
+  pub async fn for_communities(
+    pool: &DbPool,
+    community_ids: &[CommunityId],
+  ) -> Result<HashMap<CommunityId, Vec<Self>>, Error> {
+    let conn = &mut get_conn(pool).await?;
+    let res = community_moderator::table
+      .inner_join(community::table)
+      .inner_join(person::table)
+      .select((
+        community::all_columns,
+        person::all_columns,
+        community_moderator::published,
+      ))
+      .filter(community_moderator::community_id.eq_any(community_ids))
+      .order_by((community_moderator::published, community_moderator::id))
+      .load::<CommunityModeratorViewTuple>(conn)
+      .await?;
+
+    let mut moderators = HashMap::<CommunityId, Vec<Self>>::new();
+    for view in res.into_iter().map(Self::from_tuple) {
+      moderators.entry(view.community.id).or_default().push(view);
+    }
+    Ok(moderators)
+  }
+
   pub async fn for_person(pool: &DbPool, person_id: PersonId) -> Result<Vec<Self>, Error> {
     let conn = &mut get_conn(pool).await?;
     let res = community_moderator::table
       .inner_join(community::table)
       .inner_join(person::table)
-      .select((community::all_columns, person::all_columns))
+      .select((
+        community::all_columns,
+        person::all_columns,
+        community_moderator::published,
+      ))
       .filter(community_moderator::person_id.eq(person_id))
       .filter(community::deleted.eq(false))
       .filter(community::removed.eq(false))
@@ -47,7 +83,11 @@ impl CommunityModeratorView {
     let res = community_moderator::table
       .inner_join(community::table)
       .inner_join(person::table)
-      .select((community::all_columns, person::all_columns))
+      .select((
+        community::all_columns,
+        person::all_columns,
+        community_moderator::published,
+      ))
       // A hacky workaround instead of group_bys
       // https://stackoverflow.com/questions/24042359/how-to-join-only-one-row-in-joined-table-with-postgres
       .distinct_on(community_moderator::community_id)
@@ -68,6 +108,104 @@ impl JoinView for CommunityModeratorView {
     Self {
       community: a.0,
       moderator: a.1,
+      published: a.2,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::structs::CommunityModeratorView;
+  use lemmy_db_schema::{
+    source::{
+      community::{Community, CommunityInsertForm, CommunityModerator, CommunityModeratorForm},
+      instance::Instance,
+      person::{Person, PersonInsertForm},
+    },
+    traits::{Crud, Joinable},
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_for_communities() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let mut persons = vec![];
+    for name in ["mona_mod", "max_mod", "mia_mod"] {
+      let person_form = PersonInsertForm::builder()
+        .name(name.into())
+        .public_key("pubkey".to_string())
+        .instance_id(inserted_instance.id)
+        .build();
+      persons.push(Person::create(pool, &person_form).await.unwrap());
+    }
+
+    // The first community is moderated by mona and max, the second one by mia and mona
+    let mut communities = vec![];
+    for (name, moderators) in [
+      ("mod_list_community_1", [&persons[0], &persons[1]]),
+      ("mod_list_community_2", [&persons[2], &persons[0]]),
+    ] {
+      let community_form = CommunityInsertForm::builder()
+        .name(name.to_string())
+        .title("nada".to_owned())
+        .public_key("pubkey".to_string())
+        .instance_id(inserted_instance.id)
+        .build();
+      let community = Community::create(pool, &community_form).await.unwrap();
+      for moderator in moderators {
+        let moderator_form = CommunityModeratorForm {
+          community_id: community.id,
+          person_id: moderator.id,
+        };
+        CommunityModerator::join(pool, &moderator_form)
+          .await
+          .unwrap();
+      }
+      communities.push(community);
+    }
+
+    let community_ids: Vec<_> = communities.iter().map(|c| c.id).collect();
+    let moderators = CommunityModeratorView::for_communities(pool, &community_ids)
+      .await
+      .unwrap();
+    assert_eq!(2, moderators.len());
+    let moderator_ids = |community: &Community| -> Vec<_> {
+      moderators[&community.id]
+        .iter()
+        .map(|m| m.moderator.id)
+        .collect()
+    };
+    assert_eq!(
+      vec![persons[0].id, persons[1].id],
+      moderator_ids(&communities[0])
+    );
+    assert_eq!(
+      vec![persons[2].id, persons[0].id],
+      moderator_ids(&communities[1])
+    );
+    let first = &moderators[&communities[0].id];
+    assert!(first[0].published <= first[1].published);
+
+    // The single community version returns the same moderators in the same order
+    let single = CommunityModeratorView::for_community(pool, communities[1].id)
+      .await
+      .unwrap();
+    let single_ids: Vec<_> = single.iter().map(|m| m.moderator.id).collect();
+    assert_eq!(moderator_ids(&communities[1]), single_ids);
+
+    for community in communities {
+      Community::delete(pool, community.id).await.unwrap();
+    }
+    for person in persons {
+      Person::delete(pool, person.id).await.unwrap();
     }
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
   }
 }
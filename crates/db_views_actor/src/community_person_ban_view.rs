@@ -1,13 +1,21 @@
 use crate::structs::CommunityPersonBanView;
-use diesel::{result::Error, ExpressionMethods, QueryDsl};
+use diesel::{dsl::now, result::Error, BoolExpressionMethods, ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 use lemmy_db_schema::{
   newtypes::{CommunityId, PersonId},
   schema::{community, community_person_ban, person},
   source::{community::Community, person::Person},
-  utils::{get_conn, DbPool},
+  traits::JoinView,
+  utils::{get_conn, limit_and_offset, DbPool},
 };
 
+type CommunityPersonBanViewTuple = (
+  Community,
+  Person,
+  chrono::NaiveDateTime,
+  Option<chrono::NaiveDateTime>,
+);
+
 impl CommunityPersonBanView {
   pub async fn get(
     pool: &DbPool,
@@ -15,16 +23,152 @@ impl CommunityPersonBanView {
     from_community_id: CommunityId,
   ) -> Result<Self, Error> {
     let conn = &mut get_conn(pool).await?;
-    let (community, person) = community_person_ban::table
+    let res = community_person_ban::table
       .inner_join(community::table)
       .inner_join(person::table)
-      .select((community::all_columns, person::all_columns))
+      .select((
+        community::all_columns,
+        person::all_columns,
+        community_person_ban::published,
+        community_person_ban::expires,
+      ))
       .filter(community_person_ban::community_id.eq(from_community_id))
       .filter(community_person_ban::person_id.eq(from_person_id))
       .order_by(community_person_ban::published)
-      .first::<(Community, Person)>(conn)
+      .first::<CommunityPersonBanViewTuple>(conn)
       .await?;
 
-    Ok(CommunityPersonBanView { community, person })
+    Ok(Self::from_tuple(res))
+  }
+
+  /// Lists the persons banned from a community, newest bans first. With active_only, expired
+  /// bans are left out.
+  pub async fn list(
+    pool: &DbPool,
+    for_community_id: CommunityId,
+    active_only: bool,
+    page: Option<i64>,
+    limit: Option<i64>,
+  ) -> Result<Vec<Self>, Error> {
+    let conn = &mut get_conn(pool).await?;
+    let (limit, offset) = limit_and_offset(page, limit)?;
+
+    let mut query = community_person_ban::table
+      .inner_join(community::table)
+      .inner_join(person::table)
+      .select((
+        community::all_columns,
+        person::all_columns,
+        community_person_ban::published,
+        community_person_ban::expires,
+      ))
+      .filter(community_person_ban::community_id.eq(for_community_id))
+      .into_boxed();
+
+    if active_only {
+      query = query.filter(
+        community_person_ban::expires
+          .is_null()
+          .or(community_person_ban::expires.gt(now)),
+      );
+    }
+
+    let res = query
+      .order_by(community_person_ban::published.desc())
+      .then_order_by(community_person_ban::id.desc())
+      .limit(limit)
+      .offset(offset)
+      .load::<CommunityPersonBanViewTuple>(conn)
+      .await?;
+
+    Ok(res.into_iter().map(Self::from_tuple).collect())
+  }
+}
+
+impl JoinView for CommunityPersonBanView {
+  type JoinTuple = CommunityPersonBanViewTuple;
+  fn from_tuple(a: Self::JoinTuple) -> Self {
+    Self {
+      community: a.0,
+      person: a.1,
+      published: a.2,
+      expires: a.3,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::structs::CommunityPersonBanView;
+  use lemmy_db_schema::{
+    source::{
+      community::{Community, CommunityInsertForm, CommunityPersonBan, CommunityPersonBanForm},
+      instance::Instance,
+      person::{Person, PersonInsertForm},
+    },
+    traits::{Bannable, Crud},
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_list() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let mut banned_persons = vec![];
+    for name in ["bart_banned_forever", "bert_banned_before"] {
+      let person_form = PersonInsertForm::builder()
+        .name(name.into())
+        .public_key("pubkey".to_string())
+        .instance_id(inserted_instance.id)
+        .build();
+      banned_persons.push(Person::create(pool, &person_form).await.unwrap());
+    }
+
+    let community_form = CommunityInsertForm::builder()
+      .name("ban_list_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &community_form).await.unwrap();
+
+    let expired = chrono::Utc::now().naive_utc() - chrono::Duration::days(1);
+    for (person, expires) in banned_persons.iter().zip([None, Some(expired)]) {
+      let ban_form = CommunityPersonBanForm {
+        community_id: inserted_community.id,
+        person_id: person.id,
+        expires: Some(expires),
+      };
+      CommunityPersonBan::ban(pool, &ban_form).await.unwrap();
+    }
+
+    let active_bans = CommunityPersonBanView::list(pool, inserted_community.id, true, None, None)
+      .await
+      .unwrap();
+    assert_eq!(1, active_bans.len());
+    assert_eq!(banned_persons[0].id, active_bans[0].person.id);
+    assert!(active_bans[0].expires.is_none());
+
+    let all_bans = CommunityPersonBanView::list(pool, inserted_community.id, false, None, None)
+      .await
+      .unwrap();
+    assert_eq!(2, all_bans.len());
+    // Newest bans first
+    assert_eq!(banned_persons[1].id, all_bans[0].person.id);
+    assert!(all_bans[0].expires.is_some());
+
+    for person in banned_persons {
+      Person::delete(pool, person.id).await.unwrap();
+    }
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
   }
 }
@@ -1,6 +1,13 @@
-use crate::structs::{CommunityModeratorView, CommunityView, PersonView};
+use crate::structs::{
+  CommunityModeratorView,
+  CommunityView,
+  CommunityViewWithLatestPost,
+  PersonView,
+};
 use diesel::{
+  dsl::sql,
   result::Error,
+  sql_types,
   BoolExpressionMethods,
   ExpressionMethods,
   JoinOnDsl,
@@ -8,10 +15,10 @@ use diesel::{
   PgTextExpressionMethods,
   QueryDsl,
 };
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use lemmy_db_schema::{
   aggregates::structs::CommunityAggregates,
-  newtypes::{CommunityId, PersonId},
+  newtypes::{CommunityId, LanguageId, PersonId},
   schema::{community, community_aggregates, community_block, community_follower, local_user},
   source::{
     community::{Community, CommunityFollower},
@@ -23,6 +30,10 @@ use lemmy_db_schema::{
   ListingType,
   SortType,
 };
+use lemmy_db_views::{
+  post_view::PostQuery,
+  structs::{LocalUserView, PostView},
+};
 use typed_builder::TypedBuilder;
 
 type CommunityViewTuple = (
@@ -30,8 +41,12 @@ type CommunityViewTuple = (
   CommunityAggregates,
   Option<CommunityFollower>,
   Option<CommunityBlock>,
+  Vec<LanguageId>,
 );
 
+const COMMUNITY_LANGUAGES_SQL: &str = "ARRAY(SELECT cl.language_id FROM community_language cl \
+  WHERE cl.community_id = community.id ORDER BY cl.language_id)";
+
 impl CommunityView {
   pub async fn read(
     pool: &DbPool,
@@ -40,6 +55,15 @@ impl CommunityView {
     is_mod_or_admin: Option<bool>,
   ) -> Result<Self, Error> {
     let conn = &mut get_conn(pool).await?;
+    Self::read_conn(conn, community_id, my_person_id, is_mod_or_admin).await
+  }
+
+  async fn read_conn(
+    conn: &mut AsyncPgConnection,
+    community_id: CommunityId,
+    my_person_id: Option<PersonId>,
+    is_mod_or_admin: Option<bool>,
+  ) -> Result<Self, Error> {
     // The left join below will return None in this case
     let person_id_join = my_person_id.unwrap_or(PersonId(-1));
 
@@ -65,6 +89,7 @@ impl CommunityView {
         community_aggregates::all_columns,
         community_follower::all_columns.nullable(),
         community_block::all_columns.nullable(),
+        sql::<sql_types::Array<sql_types::Integer>>(COMMUNITY_LANGUAGES_SQL),
       ))
       .into_boxed();
 
@@ -75,16 +100,72 @@ impl CommunityView {
         .filter(community::deleted.eq(false));
     }
 
-    let (community, counts, follower, blocked) = query.first::<CommunityViewTuple>(conn).await?;
+    let (community, counts, follower, blocked, community_languages) =
+      query.first::<CommunityViewTuple>(conn).await?;
 
     Ok(CommunityView {
       community,
       subscribed: CommunityFollower::to_subscribed_type(&follower),
       blocked: blocked.is_some(),
       counts,
+      community_languages,
     })
   }
 
+  /// Reads the community together with its `n` hottest posts, for community previews.
+  pub async fn read_with_top_posts(
+    pool: &DbPool,
+    community_id: CommunityId,
+    n: i64,
+    my_person_id: Option<PersonId>,
+  ) -> Result<(Self, Vec<PostView>), Error> {
+    let conn = &mut get_conn(pool).await?;
+    conn
+      .build_transaction()
+      .run(|conn| {
+        Box::pin(async move {
+          let community_view = Self::read_conn(conn, community_id, my_person_id, None).await?;
+          // Only local users have personalized post listings
+          let local_user = LocalUserView::read_local_user_conn(conn, my_person_id).await?;
+          let posts = PostQuery::builder()
+            .pool(pool)
+            .community_id(Some(community_id))
+            .local_user(local_user.as_ref())
+            .hide_blocked(Some(true))
+            .sort(Some(SortType::Hot))
+            .limit(Some(n))
+            .build()
+            .list_conn(conn)
+            .await?;
+          Ok((community_view, posts))
+        }) as _
+      })
+      .await
+  }
+
+  /// Reads the community together with its newest post, if it has any.
+  pub async fn read_with_latest_post(
+    pool: &DbPool,
+    community_id: CommunityId,
+    my_person_id: Option<PersonId>,
+  ) -> Result<CommunityViewWithLatestPost, Error> {
+    let conn = &mut get_conn(pool).await?;
+    conn
+      .build_transaction()
+      .run(|conn| {
+        Box::pin(async move {
+          let community_view = Self::read_conn(conn, community_id, my_person_id, None).await?;
+          let latest_post =
+            PostView::read_latest_for_community_conn(conn, community_id, my_person_id).await?;
+          Ok(CommunityViewWithLatestPost {
+            community_view,
+            latest_post,
+          })
+        }) as _
+      })
+      .await
+  }
+
   pub async fn is_mod_or_admin(
     pool: &DbPool,
     person_id: PersonId,
@@ -126,6 +207,12 @@ pub struct CommunityQuery<'a> {
   local_user: Option<&'a LocalUser>,
   search_term: Option<String>,
   is_mod_or_admin: Option<bool>,
+  /// Only returns communities with at least this many posts
+  min_post_count: Option<i64>,
+  /// Only returns communities with at least this many subscribers
+  min_subscriber_count: Option<i64>,
+  /// Only returns communities with a post or comment in the last n days
+  has_activity_in_last_days: Option<i64>,
   page: Option<i64>,
   limit: Option<i64>,
 }
@@ -159,6 +246,7 @@ impl<'a> CommunityQuery<'a> {
         community_aggregates::all_columns,
         community_follower::all_columns.nullable(),
         community_block::all_columns.nullable(),
+        sql::<sql_types::Array<sql_types::Integer>>(COMMUNITY_LANGUAGES_SQL),
       ))
       .into_boxed();
 
@@ -181,6 +269,30 @@ impl<'a> CommunityQuery<'a> {
         );
     }
 
+    if let Some(min_post_count) = self.min_post_count {
+      query = query.filter(community_aggregates::posts.ge(min_post_count));
+    }
+
+    if let Some(min_subscriber_count) = self.min_subscriber_count {
+      query = query.filter(community_aggregates::subscribers.ge(min_subscriber_count));
+    }
+
+    if let Some(days) = self.has_activity_in_last_days {
+      query = query.filter(
+        sql::<sql_types::Bool>(
+          "(EXISTS (SELECT 1 FROM post p WHERE p.community_id = community.id \
+            AND p.published > now() - make_interval(days => ",
+        )
+        .bind::<sql_types::BigInt, _>(days)
+        .sql(
+          "::int)) OR EXISTS (SELECT 1 FROM comment c JOIN post p ON p.id = c.post_id \
+            WHERE p.community_id = community.id AND c.published > now() - make_interval(days => ",
+        )
+        .bind::<sql_types::BigInt, _>(days)
+        .sql("::int)))"),
+      );
+    }
+
     match self.sort.unwrap_or(SortType::Hot) {
       SortType::New => query = query.order_by(community::published.desc()),
       SortType::TopAll => query = query.order_by(community_aggregates::subscribers.desc()),
@@ -228,6 +340,305 @@ impl JoinView for CommunityView {
       counts: a.1,
       subscribed: CommunityFollower::to_subscribed_type(&a.2),
       blocked: a.3.is_some(),
+      community_languages: a.4,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{community_view::CommunityQuery, structs::CommunityView};
+  use lemmy_db_schema::{
+    newtypes::CommunityId,
+    source::{
+      actor_language::CommunityLanguage,
+      comment::{Comment, CommentInsertForm},
+      community::{
+        Community,
+        CommunityFollower,
+        CommunityFollowerForm,
+        CommunityInsertForm,
+        CommunityUpdateForm,
+      },
+      instance::Instance,
+      language::Language,
+      person::{Person, PersonInsertForm},
+      post::{Post, PostInsertForm, PostUpdateForm},
+    },
+    traits::{Crud, Followable},
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_read_with_top_posts() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("tom_top_posts".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("top_posts_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    for i in 0..3 {
+      let post_form = PostInsertForm::builder()
+        .name(format!("top post {i}"))
+        .creator_id(inserted_person.id)
+        .community_id(inserted_community.id)
+        .build();
+      Post::create(pool, &post_form).await.unwrap();
+    }
+
+    let (community_view, posts) =
+      CommunityView::read_with_top_posts(pool, inserted_community.id, 2, None)
+        .await
+        .unwrap();
+    assert_eq!(inserted_community.id, community_view.community.id);
+    assert_eq!(2, posts.len());
+    assert!(posts
+      .iter()
+      .all(|p| p.community.id == inserted_community.id));
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_read_with_latest_post() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("lena_latest_post".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("latest_post_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let empty = CommunityView::read_with_latest_post(pool, inserted_community.id, None)
+      .await
+      .unwrap();
+    assert!(empty.latest_post.is_none());
+
+    let post_form = PostInsertForm::builder()
+      .name("The latest post".into())
+      .creator_id(inserted_person.id)
+      .community_id(inserted_community.id)
+      .build();
+    let latest_post = Post::create(pool, &post_form).await.unwrap();
+
+    // Created afterwards, but published earlier
+    let post_form = PostInsertForm::builder()
+      .name("An older post".into())
+      .creator_id(inserted_person.id)
+      .community_id(inserted_community.id)
+      .published(Some(
+        chrono::Utc::now().naive_utc() - chrono::Duration::hours(1),
+      ))
+      .build();
+    let older_post = Post::create(pool, &post_form).await.unwrap();
+
+    // A featured post doesn't count as the latest one
+    let feature_form = PostUpdateForm::builder()
+      .featured_community(Some(true))
+      .build();
+    Post::update(pool, older_post.id, &feature_form)
+      .await
+      .unwrap();
+
+    let with_latest_post =
+      CommunityView::read_with_latest_post(pool, inserted_community.id, Some(inserted_person.id))
+        .await
+        .unwrap();
+    assert_eq!(
+      inserted_community.id,
+      with_latest_post.community_view.community.id
+    );
+    assert_eq!(
+      Some(latest_post.id),
+      with_latest_post.latest_post.map(|p| p.post.id)
+    );
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_community_languages() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("community_languages".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let en = Language::read_id_from_code(pool, Some("en"))
+      .await
+      .unwrap()
+      .unwrap();
+    let fr = Language::read_id_from_code(pool, Some("fr"))
+      .await
+      .unwrap()
+      .unwrap();
+    CommunityLanguage::update(pool, vec![fr, en], inserted_community.id)
+      .await
+      .unwrap();
+
+    let community_view = CommunityView::read(pool, inserted_community.id, None, None)
+      .await
+      .unwrap();
+    let mut expected = vec![en, fr];
+    expected.sort_by_key(|l| l.0);
+    assert_eq!(expected, community_view.community_languages);
+
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_activity_filters() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("tom_activity_filters".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    // An active community with 5 posts, a quiet one with 1 post and an abandoned one
+    let mut community_ids = vec![];
+    for (name, post_count) in [("active", 5), ("quiet", 1), ("abandoned", 0)] {
+      let community_form = CommunityInsertForm::builder()
+        .name(name.to_string())
+        .title("nada".to_owned())
+        .public_key("pubkey".to_string())
+        .instance_id(inserted_instance.id)
+        .build();
+      let community = Community::create(pool, &community_form).await.unwrap();
+      for i in 0..post_count {
+        let post_form = PostInsertForm::builder()
+          .name(format!("{name} post {i}"))
+          .creator_id(inserted_person.id)
+          .community_id(community.id)
+          .build();
+        Post::create(pool, &post_form).await.unwrap();
+      }
+      community_ids.push(community.id);
+    }
+    let follower_form = CommunityFollowerForm {
+      community_id: community_ids[1],
+      person_id: inserted_person.id,
+      pending: false,
+    };
+    CommunityFollower::follow(pool, &follower_form)
+      .await
+      .unwrap();
+
+    // A deleted community with a recent comment must stay hidden by the activity filter
+    let community_form = CommunityInsertForm::builder()
+      .name("deleted_active".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let deleted_community = Community::create(pool, &community_form).await.unwrap();
+    let post_form = PostInsertForm::builder()
+      .name("deleted_active post".into())
+      .creator_id(inserted_person.id)
+      .community_id(deleted_community.id)
+      .build();
+    let deleted_post = Post::create(pool, &post_form).await.unwrap();
+    let comment_form = CommentInsertForm::builder()
+      .content("A recent comment".into())
+      .creator_id(inserted_person.id)
+      .post_id(deleted_post.id)
+      .build();
+    Comment::create(pool, &comment_form, None).await.unwrap();
+    let update_form = CommunityUpdateForm::builder().deleted(Some(true)).build();
+    Community::update(pool, deleted_community.id, &update_form)
+      .await
+      .unwrap();
+    community_ids.push(deleted_community.id);
+
+    let list_ids = |min_post_count, min_subscriber_count, has_activity_in_last_days| async move {
+      let mut ids = CommunityQuery::builder()
+        .pool(pool)
+        .min_post_count(min_post_count)
+        .min_subscriber_count(min_subscriber_count)
+        .has_activity_in_last_days(has_activity_in_last_days)
+        .build()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|c| c.community.id)
+        .collect::<Vec<CommunityId>>();
+      ids.sort_by_key(|id| id.0);
+      ids
+    };
+
+    assert_eq!(vec![community_ids[0]], list_ids(Some(5), None, None).await);
+    assert_eq!(vec![community_ids[1]], list_ids(None, Some(1), None).await);
+    assert_eq!(
+      community_ids[..2].to_vec(),
+      list_ids(None, None, Some(7)).await
+    );
+
+    CommunityFollower::unfollow(pool, &follower_form)
+      .await
+      .unwrap();
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    for community_id in community_ids {
+      Community::delete(pool, community_id).await.unwrap();
     }
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
   }
 }
@@ -0,0 +1,303 @@
+use crate::{
+  comment_reply_view::CommentReplyQuery,
+  person_mention_view::PersonMentionQuery,
+  structs::{CommentReplyView, NotificationView, PersonMentionView},
+};
+use diesel::result::Error;
+use lemmy_db_schema::{
+  newtypes::PersonId,
+  utils::{limit_and_offset, merged_pages, DbPool},
+  CommentSortType,
+};
+use lemmy_db_views::{private_message_view::PrivateMessageQuery, structs::PrivateMessageView};
+use std::cmp::Reverse;
+use typed_builder::TypedBuilder;
+
+impl NotificationView {
+  /// Gets the combined number of unread replies, mentions and private messages
+  pub async fn get_unread_count(pool: &DbPool, my_person_id: PersonId) -> Result<i64, Error> {
+    let replies = CommentReplyView::get_unread_replies(pool, my_person_id).await?;
+    let mentions = PersonMentionView::get_unread_mentions(pool, my_person_id).await?;
+    let private_messages = PrivateMessageView::get_unread_messages(pool, my_person_id).await?;
+    Ok(replies + mentions + private_messages)
+  }
+}
+
+#[derive(TypedBuilder)]
+#[builder(field_defaults(default))]
+pub struct NotificationQuery<'a> {
+  #[builder(!default)]
+  pool: &'a DbPool,
+  #[builder(!default)]
+  person_id: PersonId,
+  unread_only: Option<bool>,
+  show_bot_accounts: Option<bool>,
+  /// Only New and Old are supported, private messages have no score
+  sort: Option<CommentSortType>,
+  page: Option<i64>,
+  limit: Option<i64>,
+}
+
+impl<'a> NotificationQuery<'a> {
+  /// Lists the notifications, newest first unless the sort is Old. The three sources are merged in
+  /// memory.
+  pub async fn list(self) -> Result<Vec<NotificationView>, Error> {
+    let sort = self.sort.unwrap_or(CommentSortType::New);
+    if !matches!(sort, CommentSortType::New | CommentSortType::Old) {
+      return Err(Error::QueryBuilderError(
+        "Notifications can only be sorted by New or Old".into(),
+      ));
+    }
+    let oldest_first = matches!(sort, CommentSortType::Old);
+    let (limit, offset) = limit_and_offset(self.page, self.limit)?;
+
+    let mut notifications = vec![];
+    for (page, fetch_limit) in merged_pages(limit, offset) {
+      let replies = CommentReplyQuery::builder()
+        .pool(self.pool)
+        .my_person_id(Some(self.person_id))
+        .recipient_id(Some(self.person_id))
+        .sort(Some(sort))
+        .unread_only(self.unread_only)
+        .show_bot_accounts(self.show_bot_accounts)
+        .page(Some(page))
+        .limit(Some(fetch_limit))
+        .build()
+        .list()
+        .await?;
+      let mentions = PersonMentionQuery::builder()
+        .pool(self.pool)
+        .my_person_id(Some(self.person_id))
+        .recipient_id(Some(self.person_id))
+        .sort(Some(sort))
+        .unread_only(self.unread_only)
+        .show_bot_accounts(self.show_bot_accounts)
+        .page(Some(page))
+        .limit(Some(fetch_limit))
+        .build()
+        .list()
+        .await?;
+      let private_messages = PrivateMessageQuery::builder()
+        .pool(self.pool)
+        .recipient_id(self.person_id)
+        .recipient_only(Some(true))
+        .unread_only(self.unread_only)
+        .oldest_first(Some(oldest_first))
+        .page(Some(page))
+        .limit(Some(fetch_limit))
+        .build()
+        .list()
+        .await?;
+
+      notifications.extend(replies.into_iter().map(NotificationView::CommentReply));
+      notifications.extend(mentions.into_iter().map(NotificationView::PersonMention));
+      notifications.extend(
+        private_messages
+          .into_iter()
+          .map(NotificationView::PrivateMessage),
+      );
+    }
+
+    let published = |n: &NotificationView| match n {
+      NotificationView::CommentReply(r) => r.comment_reply.published,
+      NotificationView::PersonMention(m) => m.person_mention.published,
+      NotificationView::PrivateMessage(p) => p.private_message.published,
+    };
+    if oldest_first {
+      notifications.sort_by_key(published);
+    } else {
+      notifications.sort_by_key(|n| Reverse(published(n)));
+    }
+
+    Ok(
+      notifications
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{notification_view::NotificationQuery, structs::NotificationView};
+  use lemmy_db_schema::{
+    source::{
+      comment::{Comment, CommentInsertForm},
+      comment_reply::{CommentReply, CommentReplyInsertForm},
+      community::{Community, CommunityInsertForm},
+      instance::Instance,
+      person::{Person, PersonInsertForm},
+      person_mention::{PersonMention, PersonMentionInsertForm},
+      post::{Post, PostInsertForm},
+      private_message::{PrivateMessage, PrivateMessageInsertForm},
+    },
+    traits::Crud,
+    utils::build_db_pool_for_tests,
+    CommentSortType,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_combined_notifications() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let recipient_form = PersonInsertForm::builder()
+      .name("terry_notified".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let recipient = Person::create(pool, &recipient_form).await.unwrap();
+
+    let sender_form = PersonInsertForm::builder()
+      .name("sam_notifier".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let sender = Person::create(pool, &sender_form).await.unwrap();
+
+    let community_form = CommunityInsertForm::builder()
+      .name("notification_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &community_form).await.unwrap();
+
+    let post_form = PostInsertForm::builder()
+      .name("A test post".into())
+      .creator_id(recipient.id)
+      .community_id(inserted_community.id)
+      .build();
+    let inserted_post = Post::create(pool, &post_form).await.unwrap();
+
+    let comment_form = CommentInsertForm::builder()
+      .content("A test comment".into())
+      .creator_id(sender.id)
+      .post_id(inserted_post.id)
+      .build();
+    let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let reply_form = CommentReplyInsertForm {
+      recipient_id: recipient.id,
+      comment_id: inserted_comment.id,
+      read: None,
+    };
+    CommentReply::create(pool, &reply_form).await.unwrap();
+
+    let mention_form = PersonMentionInsertForm {
+      recipient_id: recipient.id,
+      comment_id: inserted_comment.id,
+      read: None,
+    };
+    PersonMention::create(pool, &mention_form).await.unwrap();
+
+    let private_message_form = PrivateMessageInsertForm::builder()
+      .content("A test private message".into())
+      .creator_id(sender.id)
+      .recipient_id(recipient.id)
+      .build();
+    PrivateMessage::create(pool, &private_message_form)
+      .await
+      .unwrap();
+
+    // The recipient's own answer isn't a notification for them
+    let sent_message_form = PrivateMessageInsertForm::builder()
+      .content("An answer".into())
+      .creator_id(recipient.id)
+      .recipient_id(sender.id)
+      .build();
+    PrivateMessage::create(pool, &sent_message_form)
+      .await
+      .unwrap();
+
+    let notifications = NotificationQuery::builder()
+      .pool(pool)
+      .person_id(recipient.id)
+      .unread_only(Some(false))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(3, notifications.len());
+    assert!(!notifications.iter().any(|n| matches!(
+      n,
+      NotificationView::PrivateMessage(p) if p.private_message.creator_id == recipient.id
+    )));
+    assert!(notifications
+      .iter()
+      .any(|n| matches!(n, NotificationView::CommentReply(_))));
+    assert!(notifications
+      .iter()
+      .any(|n| matches!(n, NotificationView::PersonMention(_))));
+    assert!(notifications
+      .iter()
+      .any(|n| matches!(n, NotificationView::PrivateMessage(_))));
+
+    let unread_count = NotificationView::get_unread_count(pool, recipient.id)
+      .await
+      .unwrap();
+    assert_eq!(3, unread_count);
+
+    // Paging goes through the merged list
+    let second_page = NotificationQuery::builder()
+      .pool(pool)
+      .person_id(recipient.id)
+      .page(Some(2))
+      .limit(Some(2))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert_eq!(1, second_page.len());
+
+    // Oldest first, the comment reply was created before the private message
+    let oldest_first = NotificationQuery::builder()
+      .pool(pool)
+      .person_id(recipient.id)
+      .sort(Some(CommentSortType::Old))
+      .limit(Some(1))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert!(matches!(
+      oldest_first.as_slice(),
+      [NotificationView::CommentReply(_) | NotificationView::PersonMention(_)]
+    ));
+    let newest_first = NotificationQuery::builder()
+      .pool(pool)
+      .person_id(recipient.id)
+      .limit(Some(1))
+      .build()
+      .list()
+      .await
+      .unwrap();
+    assert!(matches!(
+      newest_first.as_slice(),
+      [NotificationView::PrivateMessage(_)]
+    ));
+
+    let top = NotificationQuery::builder()
+      .pool(pool)
+      .person_id(recipient.id)
+      .sort(Some(CommentSortType::Top))
+      .build()
+      .list()
+      .await;
+    assert!(top.is_err());
+
+    Person::delete(pool, sender.id).await.unwrap();
+    Person::delete(pool, recipient.id).await.unwrap();
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+}
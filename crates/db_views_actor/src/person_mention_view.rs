@@ -7,6 +7,7 @@ use diesel::{
   JoinOnDsl,
   NullableExpressionMethods,
   QueryDsl,
+  SelectableHelper,
 };
 use diesel_async::RunQueryDsl;
 use lemmy_db_schema::{
@@ -126,7 +127,7 @@ impl PersonMentionView {
         person_mention::all_columns,
         comment::all_columns,
         person::all_columns,
-        post::all_columns,
+        Post::as_select(),
         community::all_columns,
         person_alias_1.fields(person::all_columns),
         comment_aggregates::all_columns,
@@ -246,7 +247,7 @@ impl<'a> PersonMentionQuery<'a> {
         person_mention::all_columns,
         comment::all_columns,
         person::all_columns,
-        post::all_columns,
+        Post::as_select(),
         community::all_columns,
         person_alias_1.fields(person::all_columns),
         comment_aggregates::all_columns,
@@ -308,3 +309,124 @@ impl JoinView for PersonMentionView {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::structs::PersonMentionView;
+  use lemmy_db_schema::{
+    source::{
+      comment::{
+        Comment,
+        CommentInsertForm,
+        CommentLike,
+        CommentLikeForm,
+        CommentSaved,
+        CommentSavedForm,
+      },
+      community::{Community, CommunityInsertForm},
+      instance::Instance,
+      person::{Person, PersonInsertForm},
+      person_mention::{PersonMention, PersonMentionInsertForm},
+      post::{Post, PostInsertForm},
+    },
+    traits::{Crud, Likeable, Saveable},
+    utils::build_db_pool_for_tests,
+    SubscribedType,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_read() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let mut persons = vec![];
+    for name in ["sam_sender", "rita_recipient"] {
+      let person_form = PersonInsertForm::builder()
+        .name(name.into())
+        .public_key("pubkey".to_string())
+        .instance_id(inserted_instance.id)
+        .build();
+      persons.push(Person::create(pool, &person_form).await.unwrap());
+    }
+    let (sender, recipient) = (&persons[0], &persons[1]);
+
+    let community_form = CommunityInsertForm::builder()
+      .name("mention_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &community_form).await.unwrap();
+
+    let post_form = PostInsertForm::builder()
+      .name("A post with a mention".into())
+      .creator_id(sender.id)
+      .community_id(inserted_community.id)
+      .build();
+    let inserted_post = Post::create(pool, &post_form).await.unwrap();
+
+    let comment_form = CommentInsertForm::builder()
+      .content("Hello @rita_recipient".into())
+      .creator_id(sender.id)
+      .post_id(inserted_post.id)
+      .build();
+    let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+
+    let mention_form = PersonMentionInsertForm {
+      recipient_id: recipient.id,
+      comment_id: inserted_comment.id,
+      read: None,
+    };
+    let inserted_mention = PersonMention::create(pool, &mention_form).await.unwrap();
+
+    // The recipient upvotes and saves the comment
+    let like_form = CommentLikeForm {
+      comment_id: inserted_comment.id,
+      post_id: inserted_post.id,
+      person_id: recipient.id,
+      score: 1,
+    };
+    CommentLike::like(pool, &like_form).await.unwrap();
+    let saved_form = CommentSavedForm {
+      comment_id: inserted_comment.id,
+      person_id: recipient.id,
+    };
+    CommentSaved::save(pool, &saved_form).await.unwrap();
+
+    let mention_view = PersonMentionView::read(pool, inserted_mention.id, Some(recipient.id))
+      .await
+      .unwrap();
+    assert_eq!(inserted_mention, mention_view.person_mention);
+    assert_eq!(inserted_comment.id, mention_view.comment.id);
+    assert_eq!(*sender, mention_view.creator);
+    assert_eq!(*recipient, mention_view.recipient);
+    assert_eq!(inserted_post.id, mention_view.post.id);
+    assert_eq!(inserted_community.id, mention_view.community.id);
+    assert_eq!(1, mention_view.counts.score);
+    assert_eq!(SubscribedType::NotSubscribed, mention_view.subscribed);
+    assert!(mention_view.saved);
+    assert!(!mention_view.creator_blocked);
+    assert!(!mention_view.creator_banned_from_community);
+    assert_eq!(Some(1), mention_view.my_vote);
+
+    // Without a person, the personal fields are empty
+    let anonymous_view = PersonMentionView::read(pool, inserted_mention.id, None)
+      .await
+      .unwrap();
+    assert!(!anonymous_view.saved);
+    assert_eq!(None, anonymous_view.my_vote);
+
+    for person in persons {
+      Person::delete(pool, person.id).await.unwrap();
+    }
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+}
@@ -1,37 +1,164 @@
-use crate::structs::PersonView;
+use crate::structs::{PersonSettings, PersonView, PersonViewWithActivity};
 use diesel::{
   dsl::{now, IntervalDsl},
   result::Error,
   BoolExpressionMethods,
   ExpressionMethods,
+  JoinOnDsl,
+  NullableExpressionMethods,
   PgTextExpressionMethods,
   QueryDsl,
 };
 use diesel_async::RunQueryDsl;
 use lemmy_db_schema::{
   aggregates::structs::PersonAggregates,
+  error::LemmyDbError,
   newtypes::PersonId,
-  schema::{person, person_aggregates},
-  source::person::Person,
+  schema::{local_user, person, person_aggregates, person_block, person_follower},
+  source::{
+    local_user::LocalUser,
+    person::{Person, PersonFollower},
+    person_block::PersonBlock,
+  },
   traits::JoinView,
   utils::{fuzzy_search, get_conn, limit_and_offset, DbPool},
+  CommentSortType,
   SortType,
 };
+use lemmy_db_views::{comment_view::CommentQuery, post_view::PostQuery};
 use std::iter::Iterator;
 use typed_builder::TypedBuilder;
 
 type PersonViewTuple = (Person, PersonAggregates);
 
 impl PersonView {
-  pub async fn read(pool: &DbPool, person_id: PersonId) -> Result<Self, Error> {
+  pub async fn read(
+    pool: &DbPool,
+    person_id: PersonId,
+    my_person_id: Option<PersonId>,
+  ) -> Result<Self, Error> {
     let conn = &mut get_conn(pool).await?;
-    let res = person::table
+
+    // The left join below will return None in this case
+    let person_id_join = my_person_id.unwrap_or(PersonId(-1));
+
+    let (person, counts, blocked, follower) = person::table
       .find(person_id)
       .inner_join(person_aggregates::table)
-      .select((person::all_columns, person_aggregates::all_columns))
-      .first::<PersonViewTuple>(conn)
+      .left_join(
+        person_block::table.on(
+          person::id
+            .eq(person_block::target_id)
+            .and(person_block::person_id.eq(person_id_join)),
+        ),
+      )
+      .left_join(
+        person_follower::table.on(
+          person::id
+            .eq(person_follower::person_id)
+            .and(person_follower::follower_id.eq(person_id_join)),
+        ),
+      )
+      .select((
+        person::all_columns,
+        person_aggregates::all_columns,
+        person_block::all_columns.nullable(),
+        person_follower::all_columns.nullable(),
+      ))
+      .first::<(
+        Person,
+        PersonAggregates,
+        Option<PersonBlock>,
+        Option<PersonFollower>,
+      )>(conn)
       .await?;
-    Ok(Self::from_tuple(res))
+
+    Ok(Self {
+      person,
+      counts,
+      is_blocked_by_viewer: blocked.is_some(),
+      is_followed_by_viewer: follower.is_some(),
+      settings: None,
+    })
+  }
+
+  /// Reads a local person together with their display settings, for their own account page.
+  pub async fn read_settings(pool: &DbPool, person_id: PersonId) -> Result<Self, Error> {
+    let conn = &mut get_conn(pool).await?;
+
+    let (person, counts, settings) = person::table
+      .find(person_id)
+      .inner_join(person_aggregates::table)
+      .inner_join(local_user::table)
+      .select((
+        person::all_columns,
+        person_aggregates::all_columns,
+        (
+          local_user::interface_language,
+          local_user::show_nsfw,
+          local_user::show_scores,
+          local_user::show_bot_accounts,
+          local_user::show_read_posts,
+          local_user::send_notifications_to_email,
+        ),
+      ))
+      .first::<(
+        Person,
+        PersonAggregates,
+        (String, bool, bool, bool, bool, bool),
+      )>(conn)
+      .await?;
+
+    let settings = PersonSettings {
+      interface_language: settings.0,
+      show_nsfw: settings.1,
+      show_scores: settings.2,
+      show_bot_accounts: settings.3,
+      show_read_posts: settings.4,
+      send_notifications_to_email: settings.5,
+    };
+    Ok(Self {
+      settings: Some(settings),
+      ..Self::from_tuple((person, counts))
+    })
+  }
+
+  /// Reads a person together with their n newest posts and comments, as seen by the local user.
+  pub async fn read_with_activity(
+    pool: &DbPool,
+    person_id: PersonId,
+    local_user: Option<&LocalUser>,
+    n: i64,
+  ) -> Result<PersonViewWithActivity, LemmyDbError> {
+    let person_view = Self::read(pool, person_id, local_user.map(|l| l.person_id)).await?;
+
+    let recent_posts = PostQuery::builder()
+      .pool(pool)
+      .creator_id(Some(person_id))
+      .local_user(local_user)
+      .sort(Some(SortType::New))
+      .featured_first(Some(false))
+      .limit(Some(n))
+      .build()
+      .list()
+      .await?;
+
+    let recent_comments = CommentQuery::builder()
+      .pool(pool)
+      .creator_id(Some(person_id))
+      .local_user(local_user)
+      .hide_globally_banned(Some(false))
+      .sort(Some(CommentSortType::New))
+      .limit(Some(n))
+      .build()
+      .list()
+      .await?;
+
+    Ok(PersonViewWithActivity {
+      person_view,
+      recent_posts,
+      recent_comments,
+    })
   }
 
   pub async fn admins(pool: &DbPool) -> Result<Vec<Self>, Error> {
@@ -97,13 +224,16 @@ impl<'a> PersonQuery<'a> {
     query = match self.sort.unwrap_or(SortType::Hot) {
       SortType::New | SortType::NewComments => query.order_by(person::published.desc()),
       SortType::Old => query.order_by(person::published.asc()),
-      SortType::Hot | SortType::Active | SortType::TopAll => {
+      SortType::Hot | SortType::Active | SortType::BestMatch | SortType::TopAll => {
         query.order_by(person_aggregates::comment_score.desc())
       }
       SortType::MostComments => query.order_by(person_aggregates::comment_count.desc()),
       SortType::TopYear => query
         .filter(person::published.gt(now - 1.years()))
         .order_by(person_aggregates::comment_score.desc()),
+      SortType::TopThreeYears => query
+        .filter(person::published.gt(now - 3.years()))
+        .order_by(person_aggregates::comment_score.desc()),
       SortType::TopMonth => query
         .filter(person::published.gt(now - 1.months()))
         .order_by(person_aggregates::comment_score.desc()),
@@ -139,6 +269,247 @@ impl JoinView for PersonView {
     Self {
       person: a.0,
       counts: a.1,
+      is_blocked_by_viewer: false,
+      is_followed_by_viewer: false,
+      settings: None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::structs::{PersonSettings, PersonView};
+  use lemmy_db_schema::{
+    newtypes::{CommentId, PostId},
+    source::{
+      comment::{Comment, CommentInsertForm},
+      community::{Community, CommunityInsertForm},
+      instance::Instance,
+      local_user::{LocalUser, LocalUserInsertForm},
+      person::{Person, PersonFollower, PersonFollowerForm, PersonInsertForm, PersonUpdateForm},
+      person_block::{PersonBlock, PersonBlockForm},
+      post::{Post, PostInsertForm, PostUpdateForm},
+    },
+    traits::{Blockable, Crud, Followable},
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_viewer_relationship() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("nina".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_viewer = PersonInsertForm::builder()
+      .name("viola".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_viewer = Person::create(pool, &new_viewer).await.unwrap();
+
+    let block_form = PersonBlockForm {
+      person_id: inserted_viewer.id,
+      target_id: inserted_person.id,
+    };
+    PersonBlock::block(pool, &block_form).await.unwrap();
+
+    let follow_form = PersonFollowerForm {
+      person_id: inserted_person.id,
+      follower_id: inserted_viewer.id,
+      pending: false,
+    };
+    PersonFollower::follow(pool, &follow_form).await.unwrap();
+
+    let read_by_viewer = PersonView::read(pool, inserted_person.id, Some(inserted_viewer.id))
+      .await
+      .unwrap();
+    assert!(read_by_viewer.is_blocked_by_viewer);
+    assert!(read_by_viewer.is_followed_by_viewer);
+
+    // Keys and federation internals are never serialized, whoever reads the person
+    let serialized = serde_json::to_value(&read_by_viewer).unwrap();
+    for private_field in [
+      "private_key",
+      "public_key",
+      "last_refreshed_at",
+      "inbox_url",
+    ] {
+      assert!(serialized["person"].get(private_field).is_none());
     }
+
+    let read_anonymous = PersonView::read(pool, inserted_person.id, None)
+      .await
+      .unwrap();
+    assert!(!read_anonymous.is_blocked_by_viewer);
+    assert!(!read_anonymous.is_followed_by_viewer);
+
+    // The relationship is one-sided
+    let read_viewer = PersonView::read(pool, inserted_viewer.id, Some(inserted_person.id))
+      .await
+      .unwrap();
+    assert!(!read_viewer.is_blocked_by_viewer);
+    assert!(!read_viewer.is_followed_by_viewer);
+
+    PersonBlock::unblock(pool, &block_form).await.unwrap();
+    PersonFollower::unfollow(pool, &follow_form).await.unwrap();
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Person::delete(pool, inserted_viewer.id).await.unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_read_settings() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("person_view_settings".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    // Remote people have no settings
+    assert!(PersonView::read_settings(pool, inserted_person.id)
+      .await
+      .is_err());
+
+    let local_user_form = LocalUserInsertForm::builder()
+      .person_id(inserted_person.id)
+      .password_encrypted(String::new())
+      .interface_language(Some("fi".into()))
+      .show_nsfw(Some(true))
+      .show_scores(Some(false))
+      .show_bot_accounts(Some(false))
+      .show_read_posts(Some(true))
+      .send_notifications_to_email(Some(true))
+      .build();
+    LocalUser::create(pool, &local_user_form).await.unwrap();
+
+    let person_view = PersonView::read_settings(pool, inserted_person.id)
+      .await
+      .unwrap();
+    assert_eq!(inserted_person.id, person_view.person.id);
+    let expected_settings = PersonSettings {
+      interface_language: "fi".into(),
+      show_nsfw: true,
+      show_scores: false,
+      show_bot_accounts: false,
+      show_read_posts: true,
+      send_notifications_to_email: true,
+    };
+    assert_eq!(Some(expected_settings), person_view.settings);
+
+    // Regular reads never include the settings
+    let read_person = PersonView::read(pool, inserted_person.id, Some(inserted_person.id))
+      .await
+      .unwrap();
+    assert_eq!(None, read_person.settings);
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn test_read_with_activity() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("ada_active".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("activity_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let mut post_ids = vec![];
+    let mut comment_ids = vec![];
+    for i in 0..5 {
+      let post_form = PostInsertForm::builder()
+        .name(format!("post {i}"))
+        .creator_id(inserted_person.id)
+        .community_id(inserted_community.id)
+        .build();
+      let inserted_post = Post::create(pool, &post_form).await.unwrap();
+      post_ids.push(inserted_post.id);
+
+      let comment_form = CommentInsertForm::builder()
+        .content(format!("comment {i}"))
+        .creator_id(inserted_person.id)
+        .post_id(inserted_post.id)
+        .build();
+      let inserted_comment = Comment::create(pool, &comment_form, None).await.unwrap();
+      comment_ids.push(inserted_comment.id);
+    }
+
+    // A featured post only shows up if it's among the newest
+    let feature_form = PostUpdateForm::builder().featured_local(Some(true)).build();
+    Post::update(pool, post_ids[0], &feature_form)
+      .await
+      .unwrap();
+
+    let activity = PersonView::read_with_activity(pool, inserted_person.id, None, 3)
+      .await
+      .unwrap();
+    assert_eq!(inserted_person.id, activity.person_view.person.id);
+
+    // The newest first
+    let recent_post_ids = activity
+      .recent_posts
+      .iter()
+      .map(|p| p.post.id)
+      .collect::<Vec<PostId>>();
+    post_ids.reverse();
+    assert_eq!(post_ids[..3], recent_post_ids);
+    let recent_comment_ids = activity
+      .recent_comments
+      .iter()
+      .map(|c| c.comment.id)
+      .collect::<Vec<CommentId>>();
+    comment_ids.reverse();
+    assert_eq!(comment_ids[..3], recent_comment_ids);
+
+    // A banned person's profile still shows their comments
+    let ban_form = PersonUpdateForm::builder().banned(Some(true)).build();
+    Person::update(pool, inserted_person.id, &ban_form)
+      .await
+      .unwrap();
+    let activity = PersonView::read_with_activity(pool, inserted_person.id, None, 3)
+      .await
+      .unwrap();
+    assert_eq!(3, activity.recent_comments.len());
+
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Instance::delete(pool, inserted_instance.id).await.unwrap();
   }
 }
@@ -1,5 +1,6 @@
 use lemmy_db_schema::{
   aggregates::structs::{CommentAggregates, CommunityAggregates, PersonAggregates},
+  newtypes::LanguageId,
   source::{
     comment::Comment,
     comment_reply::CommentReply,
@@ -10,6 +11,7 @@ use lemmy_db_schema::{
   },
   SubscribedType,
 };
+use lemmy_db_views::structs::{CommentView, PostView, PrivateMessageView};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 #[cfg(feature = "full")]
@@ -33,6 +35,16 @@ pub struct CommunityFollowerView {
   pub follower: Person,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "full", derive(TS))]
+#[cfg_attr(feature = "full", ts(export))]
+/// A follow request to a community which wasn't approved yet.
+pub struct CommunityFollowerPendingView {
+  pub follower: Person,
+  /// When the follow was requested.
+  pub published: chrono::NaiveDateTime,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(feature = "full", derive(TS))]
 #[cfg_attr(feature = "full", ts(export))]
@@ -40,6 +52,8 @@ pub struct CommunityFollowerView {
 pub struct CommunityModeratorView {
   pub community: Community,
   pub moderator: Person,
+  /// When the person became a moderator of the community.
+  pub published: chrono::NaiveDateTime,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -47,6 +61,9 @@ pub struct CommunityModeratorView {
 pub struct CommunityPersonBanView {
   pub community: Community,
   pub person: Person,
+  pub published: chrono::NaiveDateTime,
+  /// Permanent bans don't expire
+  pub expires: Option<chrono::NaiveDateTime>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -58,6 +75,17 @@ pub struct CommunityView {
   pub subscribed: SubscribedType,
   pub blocked: bool,
   pub counts: CommunityAggregates,
+  /// The languages which can be used for posts and comments in the community.
+  pub community_languages: Vec<LanguageId>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "full", derive(TS))]
+#[cfg_attr(feature = "full", ts(export))]
+/// A community view with its newest post, for community listings.
+pub struct CommunityViewWithLatestPost {
+  pub community_view: CommunityView,
+  pub latest_post: Option<PostView>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -116,4 +144,43 @@ pub struct CommentReplyView {
 pub struct PersonView {
   pub person: Person,
   pub counts: PersonAggregates,
+  /// Whether the viewer blocks this person. Always false for anonymous views.
+  pub is_blocked_by_viewer: bool,
+  /// Whether the viewer follows this person. Always false for anonymous views.
+  pub is_followed_by_viewer: bool,
+  /// The display settings of a local user. Only included when people read their own settings.
+  pub settings: Option<PersonSettings>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "full", derive(TS))]
+#[cfg_attr(feature = "full", ts(export))]
+/// A person view with their newest posts and comments, for profile pages.
+pub struct PersonViewWithActivity {
+  pub person_view: PersonView,
+  pub recent_posts: Vec<PostView>,
+  pub recent_comments: Vec<CommentView>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(TS))]
+#[cfg_attr(feature = "full", ts(export))]
+/// The local user settings which affect how content is displayed.
+pub struct PersonSettings {
+  pub interface_language: String,
+  pub show_nsfw: bool,
+  pub show_scores: bool,
+  pub show_bot_accounts: bool,
+  pub show_read_posts: bool,
+  pub send_notifications_to_email: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "full", derive(TS))]
+#[cfg_attr(feature = "full", ts(export))]
+/// A notification from the inbox, which can be a reply, mention or private message.
+pub enum NotificationView {
+  CommentReply(CommentReplyView),
+  PersonMention(PersonMentionView),
+  PrivateMessage(PrivateMessageView),
 }
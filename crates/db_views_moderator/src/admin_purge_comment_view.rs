@@ -7,6 +7,7 @@ use diesel::{
   JoinOnDsl,
   NullableExpressionMethods,
   QueryDsl,
+  SelectableHelper,
 };
 use diesel_async::RunQueryDsl;
 use lemmy_db_schema::{
@@ -36,7 +37,7 @@ impl AdminPurgeCommentView {
       .select((
         admin_purge_comment::all_columns,
         person::all_columns.nullable(),
-        post::all_columns,
+        Post::as_select(),
       ))
       .into_boxed();
 
@@ -28,4 +28,6 @@ pub mod mod_remove_community_view;
 pub mod mod_remove_post_view;
 #[cfg(feature = "full")]
 pub mod mod_transfer_community_view;
+#[cfg(feature = "full")]
+pub mod modlog_view;
 pub mod structs;
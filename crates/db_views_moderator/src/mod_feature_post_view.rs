@@ -7,6 +7,7 @@ use diesel::{
   JoinOnDsl,
   NullableExpressionMethods,
   QueryDsl,
+  SelectableHelper,
 };
 use diesel_async::RunQueryDsl;
 use lemmy_db_schema::{
@@ -38,7 +39,7 @@ impl ModFeaturePostView {
       .select((
         mod_feature_post::all_columns,
         person::all_columns.nullable(),
-        post::all_columns,
+        Post::as_select(),
         community::all_columns,
       ))
       .into_boxed();
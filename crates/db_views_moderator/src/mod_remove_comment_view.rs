@@ -7,6 +7,7 @@ use diesel::{
   JoinOnDsl,
   NullableExpressionMethods,
   QueryDsl,
+  SelectableHelper,
 };
 use diesel_async::RunQueryDsl;
 use lemmy_db_schema::{
@@ -54,7 +55,7 @@ impl ModRemoveCommentView {
         person::all_columns.nullable(),
         comment::all_columns,
         person_alias_1.fields(person::all_columns),
-        post::all_columns,
+        Post::as_select(),
         community::all_columns,
       ))
       .into_boxed();
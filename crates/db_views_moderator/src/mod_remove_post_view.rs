@@ -7,6 +7,7 @@ use diesel::{
   JoinOnDsl,
   NullableExpressionMethods,
   QueryDsl,
+  SelectableHelper,
 };
 use diesel_async::RunQueryDsl;
 use lemmy_db_schema::{
@@ -39,7 +40,7 @@ impl ModRemovePostView {
       .select((
         mod_remove_post::all_columns,
         person::all_columns.nullable(),
-        post::all_columns,
+        Post::as_select(),
         community::all_columns,
       ))
       .into_boxed();
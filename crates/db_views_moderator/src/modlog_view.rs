@@ -0,0 +1,320 @@
+use crate::structs::{
+  AdminPurgeCommentView,
+  AdminPurgeCommunityView,
+  AdminPurgePersonView,
+  AdminPurgePostView,
+  ModAddCommunityView,
+  ModAddView,
+  ModBanFromCommunityView,
+  ModBanView,
+  ModFeaturePostView,
+  ModHideCommunityView,
+  ModLockPostView,
+  ModRemoveCommentView,
+  ModRemoveCommunityView,
+  ModRemovePostView,
+  ModTransferCommunityView,
+  ModlogItem,
+  ModlogListParams,
+};
+use diesel::result::Error;
+use lemmy_db_schema::utils::{limit_and_offset, merged_pages, DbPool};
+use std::cmp::Reverse;
+
+impl ModlogItem {
+  /// Lists the actions of all modlog tables, newest first, merged in memory. Site wide actions are
+  /// left out when filtering by community, and actions without an affected person are left out
+  /// when filtering by one.
+  pub async fn combined(pool: &DbPool, params: ModlogListParams) -> Result<Vec<Self>, Error> {
+    let (limit, offset) = limit_and_offset(params.page, params.limit)?;
+    let site_wide = params.community_id.is_none();
+    let without_person = params.other_person_id.is_none();
+
+    let mut items = vec![];
+    for (page, fetch_limit) in merged_pages(limit, offset) {
+      let params = ModlogListParams {
+        page: Some(page),
+        limit: Some(fetch_limit),
+        ..params
+      };
+
+      items.extend(
+        ModRemovePostView::list(pool, params)
+          .await?
+          .into_iter()
+          .map(Self::ModRemovePost),
+      );
+      items.extend(
+        ModLockPostView::list(pool, params)
+          .await?
+          .into_iter()
+          .map(Self::ModLockPost),
+      );
+      items.extend(
+        ModFeaturePostView::list(pool, params)
+          .await?
+          .into_iter()
+          .map(Self::ModFeaturePost),
+      );
+      items.extend(
+        ModRemoveCommentView::list(pool, params)
+          .await?
+          .into_iter()
+          .map(Self::ModRemoveComment),
+      );
+      items.extend(
+        ModBanFromCommunityView::list(pool, params)
+          .await?
+          .into_iter()
+          .map(Self::ModBanFromCommunity),
+      );
+      items.extend(
+        ModAddCommunityView::list(pool, params)
+          .await?
+          .into_iter()
+          .map(Self::ModAddCommunity),
+      );
+      items.extend(
+        ModTransferCommunityView::list(pool, params)
+          .await?
+          .into_iter()
+          .map(Self::ModTransferCommunity),
+      );
+      if without_person {
+        items.extend(
+          ModHideCommunityView::list(pool, params)
+            .await?
+            .into_iter()
+            .map(Self::ModHideCommunity),
+        );
+      }
+
+      if site_wide {
+        items.extend(
+          ModBanView::list(pool, params)
+            .await?
+            .into_iter()
+            .map(Self::ModBan),
+        );
+        items.extend(
+          ModAddView::list(pool, params)
+            .await?
+            .into_iter()
+            .map(Self::ModAdd),
+        );
+      }
+      if site_wide && without_person {
+        items.extend(
+          ModRemoveCommunityView::list(pool, params)
+            .await?
+            .into_iter()
+            .map(Self::ModRemoveCommunity),
+        );
+        items.extend(
+          AdminPurgePersonView::list(pool, params)
+            .await?
+            .into_iter()
+            .map(Self::AdminPurgePerson),
+        );
+        items.extend(
+          AdminPurgeCommunityView::list(pool, params)
+            .await?
+            .into_iter()
+            .map(Self::AdminPurgeCommunity),
+        );
+        items.extend(
+          AdminPurgePostView::list(pool, params)
+            .await?
+            .into_iter()
+            .map(Self::AdminPurgePost),
+        );
+        items.extend(
+          AdminPurgeCommentView::list(pool, params)
+            .await?
+            .into_iter()
+            .map(Self::AdminPurgeComment),
+        );
+      }
+    }
+
+    items.sort_by_key(|i| Reverse(i.when_()));
+
+    Ok(
+      items
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect(),
+    )
+  }
+
+  /// The time of the action.
+  pub fn when_(&self) -> chrono::NaiveDateTime {
+    match self {
+      Self::ModRemovePost(v) => v.mod_remove_post.when_,
+      Self::ModLockPost(v) => v.mod_lock_post.when_,
+      Self::ModFeaturePost(v) => v.mod_feature_post.when_,
+      Self::ModRemoveComment(v) => v.mod_remove_comment.when_,
+      Self::ModRemoveCommunity(v) => v.mod_remove_community.when_,
+      Self::ModBanFromCommunity(v) => v.mod_ban_from_community.when_,
+      Self::ModBan(v) => v.mod_ban.when_,
+      Self::ModAddCommunity(v) => v.mod_add_community.when_,
+      Self::ModTransferCommunity(v) => v.mod_transfer_community.when_,
+      Self::ModAdd(v) => v.mod_add.when_,
+      Self::AdminPurgePerson(v) => v.admin_purge_person.when_,
+      Self::AdminPurgeCommunity(v) => v.admin_purge_community.when_,
+      Self::AdminPurgePost(v) => v.admin_purge_post.when_,
+      Self::AdminPurgeComment(v) => v.admin_purge_comment.when_,
+      Self::ModHideCommunity(v) => v.mod_hide_community.when_,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::structs::{ModlogItem, ModlogListParams};
+  use lemmy_db_schema::{
+    source::{
+      community::{Community, CommunityInsertForm},
+      instance::Instance,
+      moderator::{
+        ModBan,
+        ModBanForm,
+        ModLockPost,
+        ModLockPostForm,
+        ModRemovePost,
+        ModRemovePostForm,
+      },
+      person::{Person, PersonInsertForm},
+      post::{Post, PostInsertForm},
+    },
+    traits::Crud,
+    utils::build_db_pool_for_tests,
+  };
+  use serial_test::serial;
+
+  #[tokio::test]
+  #[serial]
+  async fn test_combined() {
+    let pool = &build_db_pool_for_tests().await;
+
+    let inserted_instance = Instance::read_or_create(pool, "my_domain.tld".to_string())
+      .await
+      .unwrap();
+
+    let new_mod = PersonInsertForm::builder()
+      .name("the_modlog_mod".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_mod = Person::create(pool, &new_mod).await.unwrap();
+
+    let new_person = PersonInsertForm::builder()
+      .name("the_modlog_person".into())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_person = Person::create(pool, &new_person).await.unwrap();
+
+    let new_community = CommunityInsertForm::builder()
+      .name("modlog_community".to_string())
+      .title("nada".to_owned())
+      .public_key("pubkey".to_string())
+      .instance_id(inserted_instance.id)
+      .build();
+    let inserted_community = Community::create(pool, &new_community).await.unwrap();
+
+    let new_post = PostInsertForm::builder()
+      .name("A test post".into())
+      .creator_id(inserted_person.id)
+      .community_id(inserted_community.id)
+      .build();
+    let inserted_post = Post::create(pool, &new_post).await.unwrap();
+
+    // A post removal, a post lock and a site ban, in that order
+    let remove_form = ModRemovePostForm {
+      mod_person_id: inserted_mod.id,
+      post_id: inserted_post.id,
+      reason: None,
+      removed: None,
+    };
+    ModRemovePost::create(pool, &remove_form).await.unwrap();
+    let lock_form = ModLockPostForm {
+      mod_person_id: inserted_mod.id,
+      post_id: inserted_post.id,
+      locked: None,
+    };
+    ModLockPost::create(pool, &lock_form).await.unwrap();
+    let ban_form = ModBanForm {
+      mod_person_id: inserted_mod.id,
+      other_person_id: inserted_person.id,
+      reason: None,
+      banned: None,
+      expires: None,
+    };
+    ModBan::create(pool, &ban_form).await.unwrap();
+
+    let params = ModlogListParams {
+      community_id: None,
+      mod_person_id: Some(inserted_mod.id),
+      other_person_id: None,
+      page: None,
+      limit: None,
+      hide_modlog_names: false,
+    };
+    let items = ModlogItem::combined(pool, params).await.unwrap();
+    assert_eq!(3, items.len());
+    assert!(matches!(items[0], ModlogItem::ModBan(_)));
+    assert!(matches!(items[1], ModlogItem::ModLockPost(_)));
+    assert!(matches!(items[2], ModlogItem::ModRemovePost(_)));
+
+    // Paging applies to the combined list
+    let second_page = ModlogItem::combined(
+      pool,
+      ModlogListParams {
+        page: Some(2),
+        limit: Some(2),
+        ..params
+      },
+    )
+    .await
+    .unwrap();
+    assert_eq!(1, second_page.len());
+    assert!(matches!(second_page[0], ModlogItem::ModRemovePost(_)));
+
+    // Site bans don't belong to a community
+    let community_items = ModlogItem::combined(
+      pool,
+      ModlogListParams {
+        community_id: Some(inserted_community.id),
+        ..params
+      },
+    )
+    .await
+    .unwrap();
+    assert_eq!(2, community_items.len());
+
+    // Pages beyond the maximum limit are read from the tables in several parts
+    for _ in 0..50 {
+      ModLockPost::create(pool, &lock_form).await.unwrap();
+    }
+    let last_page = ModlogItem::combined(
+      pool,
+      ModlogListParams {
+        page: Some(2),
+        limit: Some(50),
+        ..params
+      },
+    )
+    .await
+    .unwrap();
+    assert_eq!(3, last_page.len());
+    assert!(matches!(last_page[2], ModlogItem::ModRemovePost(_)));
+
+    Community::delete(pool, inserted_community.id)
+      .await
+      .unwrap();
+    Person::delete(pool, inserted_person.id).await.unwrap();
+    Person::delete(pool, inserted_mod.id).await.unwrap();
+  }
+}
@@ -201,6 +201,29 @@ pub struct AdminPurgePostView {
   pub community: Community,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "full", derive(TS))]
+#[cfg_attr(feature = "full", ts(export))]
+/// A single entry of the combined modlog.
+#[allow(clippy::large_enum_variant)]
+pub enum ModlogItem {
+  ModRemovePost(ModRemovePostView),
+  ModLockPost(ModLockPostView),
+  ModFeaturePost(ModFeaturePostView),
+  ModRemoveComment(ModRemoveCommentView),
+  ModRemoveCommunity(ModRemoveCommunityView),
+  ModBanFromCommunity(ModBanFromCommunityView),
+  ModBan(ModBanView),
+  ModAddCommunity(ModAddCommunityView),
+  ModTransferCommunity(ModTransferCommunityView),
+  ModAdd(ModAddView),
+  AdminPurgePerson(AdminPurgePersonView),
+  AdminPurgeCommunity(AdminPurgeCommunityView),
+  AdminPurgePost(AdminPurgePostView),
+  AdminPurgeComment(AdminPurgeCommentView),
+  ModHideCommunity(ModHideCommunityView),
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 #[cfg_attr(feature = "full", derive(TS))]
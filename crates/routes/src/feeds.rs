@@ -316,6 +316,7 @@ async fn get_feed_front(
     .pool(pool)
     .listing_type(Some(ListingType::Subscribed))
     .local_user(Some(&local_user))
+    .hide_blocked(Some(true))
     .sort(Some(*sort_type))
     .limit(Some(*limit))
     .page(Some(*page))
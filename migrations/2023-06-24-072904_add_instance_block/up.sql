@@ -0,0 +1,7 @@
+create table instance_block (
+  id serial primary key,
+  person_id int references person on update cascade on delete cascade not null,
+  instance_id int references instance on update cascade on delete cascade not null,
+  published timestamp not null default now(),
+  unique(person_id, instance_id)
+);
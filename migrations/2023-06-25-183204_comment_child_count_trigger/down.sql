@@ -0,0 +1,10 @@
+drop trigger comment_aggregates_child_count on comment;
+drop function comment_aggregates_child_count;
+
+update comment_aggregates ca set child_count = c2.child_count
+from (
+  select c.id, count(c2.id) as child_count from comment c
+  left join comment c2 on c2.path <@ c.path and c2.path != c.path
+  group by c.id
+) as c2
+where ca.comment_id = c2.id;
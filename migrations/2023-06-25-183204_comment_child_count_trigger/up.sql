@@ -0,0 +1,42 @@
+-- Keep the child counts of all ancestors up to date when a comment is added to a tree,
+-- deleted, removed, or purged. Deleted and removed comments no longer count as children.
+create or replace function comment_aggregates_child_count()
+returns trigger language plpgsql
+as $$
+declare
+  changed_path ltree;
+begin
+  IF (TG_OP = 'DELETE') THEN
+    changed_path := OLD.path;
+  ELSE
+    changed_path := NEW.path;
+  END IF;
+
+  update comment_aggregates ca set child_count = (
+    select count(*) from comment c
+    where c.path <@ ancestor.path
+    and c.id != ancestor.id
+    and c.deleted = false
+    and c.removed = false
+  )
+  from comment ancestor
+  where ca.comment_id = ancestor.id
+  and ancestor.path @> changed_path;
+
+  return null;
+end $$;
+
+create trigger comment_aggregates_child_count
+after update of path, deleted, removed or delete on comment
+for each row
+execute procedure comment_aggregates_child_count();
+
+-- Fix the existing drift
+update comment_aggregates ca set child_count = c2.child_count
+from (
+  select c.id, count(c2.id) as child_count from comment c
+  left join comment c2 on c2.path <@ c.path and c2.path != c.path
+  and c2.deleted = false and c2.removed = false
+  group by c.id
+) as c2
+where ca.comment_id = c2.id;
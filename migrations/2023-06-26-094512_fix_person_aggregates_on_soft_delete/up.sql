@@ -0,0 +1,98 @@
+-- Only actual changes of the visibility count, so that removing an already deleted post or
+-- deleting an already removed one doesn't decrement the creator's aggregates twice. A post is
+-- visible if it's neither deleted nor removed. Full recounts are left to
+-- PersonAggregates::recalculate.
+create or replace function person_aggregates_post_count()
+    returns trigger language plpgsql
+as $$
+begin
+    IF (TG_OP = 'INSERT') THEN
+        IF (NEW.deleted = 'f' AND NEW.removed = 'f') THEN
+            update person_aggregates
+            set post_count = post_count + 1 where person_id = NEW.creator_id;
+        END IF;
+    ELSIF (TG_OP = 'UPDATE') THEN
+        IF ((OLD.deleted = 'f' AND OLD.removed = 'f') AND (NEW.deleted = 't' OR NEW.removed = 't')) THEN
+            update person_aggregates
+            set post_count = post_count - 1,
+                post_score = post_score - (
+                    select coalesce(sum(pl.score), 0) from post_like pl where pl.post_id = OLD.id
+                )
+            where person_id = OLD.creator_id;
+        ELSIF ((OLD.deleted = 't' OR OLD.removed = 't') AND (NEW.deleted = 'f' AND NEW.removed = 'f')) THEN
+            update person_aggregates
+            set post_count = post_count + 1,
+                post_score = post_score + (
+                    select coalesce(sum(pl.score), 0) from post_like pl where pl.post_id = NEW.id
+                )
+            where person_id = NEW.creator_id;
+        END IF;
+    ELSIF (OLD.deleted = 'f' AND OLD.removed = 'f') THEN
+        -- The votes are already gone with the post, and the score trigger can't find the post
+        -- anymore, so the score of the remaining posts is recounted
+        update person_aggregates ua
+        set post_count = post_count - 1,
+            post_score = (
+                select coalesce(sum(pl.score), 0)
+                from post p
+                join post_like pl on p.id = pl.post_id
+                where p.creator_id = ua.person_id
+                and p.deleted = 'f' and p.removed = 'f'
+            )
+        where ua.person_id = OLD.creator_id;
+    END IF;
+    return null;
+end $$;
+
+create or replace function person_aggregates_comment_count()
+    returns trigger language plpgsql
+as $$
+begin
+    IF (TG_OP = 'INSERT') THEN
+        IF (NEW.deleted = 'f' AND NEW.removed = 'f') THEN
+            update person_aggregates
+            set comment_count = comment_count + 1 where person_id = NEW.creator_id;
+        END IF;
+    ELSIF (TG_OP = 'UPDATE') THEN
+        IF ((OLD.deleted = 'f' AND OLD.removed = 'f') AND (NEW.deleted = 't' OR NEW.removed = 't')) THEN
+            update person_aggregates
+            set comment_count = comment_count - 1,
+                comment_score = comment_score - (
+                    select coalesce(sum(cl.score), 0) from comment_like cl where cl.comment_id = OLD.id
+                )
+            where person_id = OLD.creator_id;
+        ELSIF ((OLD.deleted = 't' OR OLD.removed = 't') AND (NEW.deleted = 'f' AND NEW.removed = 'f')) THEN
+            update person_aggregates
+            set comment_count = comment_count + 1,
+                comment_score = comment_score + (
+                    select coalesce(sum(cl.score), 0) from comment_like cl where cl.comment_id = NEW.id
+                )
+            where person_id = NEW.creator_id;
+        END IF;
+    ELSIF (OLD.deleted = 'f' AND OLD.removed = 'f') THEN
+        -- The votes are already gone with the comment, and the score trigger can't find the
+        -- comment anymore, so the score of the remaining comments is recounted
+        update person_aggregates ua
+        set comment_count = comment_count - 1,
+            comment_score = (
+                select coalesce(sum(cl.score), 0)
+                from comment c
+                join comment_like cl on c.id = cl.comment_id
+                where c.creator_id = ua.person_id
+                and c.deleted = 'f' and c.removed = 'f'
+            )
+        where ua.person_id = OLD.creator_id;
+    END IF;
+    return null;
+end $$;
+
+-- Fix any counts which drifted under the old triggers
+update person_aggregates ua
+set post_count = (
+        select count(*) from post p
+        where p.creator_id = ua.person_id and p.deleted = 'f' and p.removed = 'f'
+    ),
+    comment_count = (
+        select count(*) from comment c
+        where c.creator_id = ua.person_id and c.deleted = 'f' and c.removed = 'f'
+    );
@@ -0,0 +1 @@
+ALTER TYPE sort_type_enum ADD VALUE 'TopThreeYears';
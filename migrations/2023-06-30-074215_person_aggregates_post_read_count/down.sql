@@ -0,0 +1,3 @@
+drop trigger person_aggregates_post_read_count on post_read;
+drop function person_aggregates_post_read_count;
+alter table person_aggregates drop column post_read_count;
@@ -0,0 +1,25 @@
+alter table person_aggregates add column post_read_count bigint not null default 0;
+
+update person_aggregates pa
+set post_read_count = (
+    select count(*) from post_read pr where pr.person_id = pa.person_id
+);
+
+create function person_aggregates_post_read_count()
+returns trigger language plpgsql
+as $$
+begin
+    IF (TG_OP = 'INSERT') THEN
+        update person_aggregates
+        set post_read_count = post_read_count + 1 where person_id = NEW.person_id;
+    ELSIF (TG_OP = 'DELETE') THEN
+        update person_aggregates
+        set post_read_count = post_read_count - 1 where person_id = OLD.person_id;
+    END IF;
+    return null;
+end $$;
+
+create trigger person_aggregates_post_read_count
+after insert or delete on post_read
+for each row
+execute procedure person_aggregates_post_read_count();
@@ -0,0 +1,4 @@
+drop trigger comment_aggregates_edit_count on comment_edit_history;
+drop function comment_aggregates_edit_count;
+alter table comment_aggregates drop column edit_count;
+drop table comment_edit_history;
@@ -0,0 +1,26 @@
+create table comment_edit_history (
+  id serial primary key,
+  comment_id int references comment on update cascade on delete cascade not null,
+  content text not null,
+  edited_at timestamp not null default now()
+);
+
+create index idx_comment_edit_history_comment on comment_edit_history (comment_id);
+
+alter table comment_aggregates add column edit_count bigint not null default 0;
+
+-- Count the previous versions of a comment
+create or replace function comment_aggregates_edit_count()
+returns trigger language plpgsql
+as $$
+begin
+  update comment_aggregates ca set edit_count = edit_count + 1
+  where ca.comment_id = NEW.comment_id;
+
+  return null;
+end $$;
+
+create trigger comment_aggregates_edit_count
+after insert on comment_edit_history
+for each row
+execute procedure comment_aggregates_edit_count();
@@ -0,0 +1,19 @@
+drop index idx_post_search_vector;
+alter table post drop column search_vector;
+
+-- update the default sort type
+update local_user set default_sort_type = 'Hot' where default_sort_type = 'BestMatch';
+
+-- rename the old enum
+alter type sort_type_enum rename to sort_type_enum__;
+-- create the new enum
+CREATE TYPE sort_type_enum AS ENUM ('Active', 'Hot', 'New', 'Old', 'TopDay', 'TopWeek', 'TopMonth', 'TopYear', 'TopAll', 'MostComments', 'NewComments', 'TopHour', 'TopSixHour', 'TopTwelveHour', 'TopThreeYears');
+
+-- alter all you enum columns
+alter table local_user alter column default_sort_type drop default;
+alter table local_user
+  alter column default_sort_type type sort_type_enum using default_sort_type::text::sort_type_enum;
+alter table local_user alter column default_sort_type set default 'Active';
+
+-- drop the old enum
+drop type sort_type_enum__;
@@ -0,0 +1,8 @@
+-- The search vector of the title and body, kept up to date by postgres, for full text search of
+-- posts
+alter table post add column search_vector tsvector
+  generated always as (to_tsvector('english', name || ' ' || coalesce(body, ''))) stored;
+create index idx_post_search_vector on post using gin (search_vector);
+
+-- Sorts full text search results by relevance
+ALTER TYPE sort_type_enum ADD VALUE 'BestMatch';
@@ -94,6 +94,7 @@ use lemmy_api_common::{
     PurgeCommunity,
     PurgePerson,
     PurgePost,
+    RecalculateSiteAggregates,
     ResolveObject,
     Search,
   },
@@ -336,6 +337,10 @@ pub fn config(cfg: &mut web::ServiceConfig, rate_limit: &RateLimitCell) {
             "/registration_application/approve",
             web::put().to(route_post::<ApproveRegistrationApplication>),
           )
+          .route(
+            "/recalculate_aggregates",
+            web::post().to(route_post::<RecalculateSiteAggregates>),
+          )
           .service(
             web::scope("/purge")
               .route("/person", web::post().to(route_post::<PurgePerson>))
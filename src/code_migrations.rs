@@ -5,6 +5,7 @@ use diesel::{
   ExpressionMethods,
   IntoSql,
   QueryDsl,
+  SelectableHelper,
   TextExpressionMethods,
 };
 use diesel_async::RunQueryDsl;
@@ -145,6 +146,7 @@ async fn post_updates_2020_04_03(
   let incorrect_posts = post
     .filter(ap_id.like("http://changeme%"))
     .filter(local.eq(true))
+    .select(Post::as_select())
     .load::<Post>(conn)
     .await?;
 
@@ -261,6 +263,7 @@ async fn post_thumbnail_url_updates_2020_07_27(
           .concat(thumbnail_url),
       ),
     )
+    .returning(Post::as_returning())
     .get_results::<Post>(conn)
     .await?;
 